@@ -0,0 +1,232 @@
+use crate::config::VpnConfig;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{command, AppHandle, Manager};
+
+/// The active file is rotated once it exceeds this size.
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+/// Rotated files kept next to the active one (`audit.1.jsonl` is the newest).
+const MAX_ROTATED_FILES: usize = 5;
+/// Command output stored per entry; longer results are truncated.
+const MAX_RESULT_CHARS: usize = 2000;
+
+/// Serializes writers and readers across threads (jobs and direct commands audit concurrently),
+/// so a rotation never runs while another entry is written or the files are read.
+static AUDIT_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub timestamp: String,
+    /// `DOMAIN\user` the app runs as.
+    pub user: String,
+    pub machine: String,
+    pub command: String,
+    /// SHA-256 of the configuration as JSON.
+    pub config_hash: Option<String>,
+    /// SHA-256 of every script, command line or task XML executed, in order.
+    pub script_hashes: Vec<String>,
+    pub success: bool,
+    pub result: String,
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn config_hash(config: &VpnConfig) -> Option<String> {
+    serde_json::to_vec(config).ok().map(|json| sha256_hex(&json))
+}
+
+pub fn windows_user() -> String {
+    match (std::env::var("USERDOMAIN"), std::env::var("USERNAME")) {
+        (Ok(domain), Ok(user)) => format!("{}\\{}", domain, user),
+        (_, Ok(user)) => user,
+        _ => "unknown".to_string(),
+    }
+}
+
+pub fn windows_machine() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| "unknown".to_string())
+}
+
+impl AuditEntry {
+    pub fn new(command: &str, config: Option<&VpnConfig>, script_hashes: Vec<String>, result: &Result<String, String>) -> AuditEntry {
+        let (success, text) = match result {
+            Ok(output) => (true, output),
+            Err(error) => (false, error),
+        };
+        AuditEntry {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            user: windows_user(),
+            machine: windows_machine(),
+            command: command.to_string(),
+            config_hash: config.and_then(config_hash),
+            script_hashes,
+            success,
+            result: text.trim().chars().take(MAX_RESULT_CHARS).collect(),
+        }
+    }
+}
+
+pub struct AuditLog {
+    root: PathBuf,
+}
+
+impl AuditLog {
+    pub fn new(root: PathBuf) -> AuditLog {
+        AuditLog { root }
+    }
+
+    pub fn open(app: &AppHandle) -> Result<AuditLog, String> {
+        let dir = app
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+        Ok(AuditLog::new(dir.join("audit")))
+    }
+
+    fn file(&self, index: usize) -> PathBuf {
+        match index {
+            0 => self.root.join("audit.jsonl"),
+            n => self.root.join(format!("audit.{}.jsonl", n)),
+        }
+    }
+
+    fn rotate(&self) -> Result<(), String> {
+        let _ = std::fs::remove_file(self.file(MAX_ROTATED_FILES));
+        for n in (0..MAX_ROTATED_FILES).rev() {
+            let from = self.file(n);
+            if from.exists() {
+                std::fs::rename(&from, self.file(n + 1)).map_err(|e| format!("Failed to rotate audit log: {}", e))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn append(&self, entry: &AuditEntry) -> Result<(), String> {
+        let _guard = AUDIT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        std::fs::create_dir_all(&self.root)
+            .map_err(|e| format!("Failed to create audit directory {}: {}", self.root.display(), e))?;
+        if std::fs::metadata(self.file(0)).is_ok_and(|m| m.len() >= MAX_FILE_BYTES) {
+            self.rotate()?;
+        }
+        let line = serde_json::to_string(entry).map_err(|e| format!("Failed to serialize audit entry: {}", e))?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.file(0))
+            .map_err(|e| format!("Failed to open audit log: {}", e))?;
+        writeln!(file, "{}", line).map_err(|e| format!("Failed to write audit log: {}", e))
+    }
+
+    /// All retained lines, oldest first.
+    pub fn read_lines(&self) -> Vec<String> {
+        let _guard = AUDIT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        (0..=MAX_ROTATED_FILES)
+            .rev()
+            .filter_map(|n| std::fs::read_to_string(self.file(n)).ok())
+            .flat_map(|content| content.lines().map(str::to_string).collect::<Vec<_>>())
+            .filter(|line| !line.trim().is_empty())
+            .collect()
+    }
+}
+
+thread_local! {
+    /// Hashes collected for the audited command running on this thread.
+    static SCRIPTS: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// Records the hash of a script (or command line / task XML) the current audited command executes.
+pub fn track_script(script: &str) {
+    SCRIPTS.with(|s| {
+        if let Some(hashes) = s.borrow_mut().as_mut() {
+            hashes.push(sha256_hex(script.as_bytes()));
+        }
+    });
+}
+
+/// Runs a mutating command and appends its audit entry. Failing to write the entry never
+/// changes the command's outcome; successful output gets a warning appended instead.
+pub fn audited(
+    app: &AppHandle,
+    command: &str,
+    config: Option<&VpnConfig>,
+    action: impl FnOnce() -> Result<String, String>,
+) -> Result<String, String> {
+    let outer = SCRIPTS.with(|s| s.replace(Some(Vec::new())));
+    let result = action();
+    let hashes = SCRIPTS.with(|s| s.replace(outer)).unwrap_or_default();
+    // Nested audited calls also count towards the caller's entry.
+    SCRIPTS.with(|s| {
+        if let Some(outer) = s.borrow_mut().as_mut() {
+            outer.extend(hashes.iter().cloned());
+        }
+    });
+
+    let entry = AuditEntry::new(command, config, hashes, &result);
+    match AuditLog::open(app).and_then(|log| log.append(&entry)) {
+        Ok(()) => result,
+        Err(e) => {
+            log::warn!("Audit log: {}", e);
+            result.map(|output| format!("{}\nWARNING: Failed to write audit log: {}", output, e))
+        }
+    }
+}
+
+/// Most recent entries, newest first.
+#[command]
+pub async fn get_audit_log(app: AppHandle, limit: Option<usize>) -> Result<String, String> {
+    let lines = AuditLog::open(&app)?.read_lines();
+    let entries: Vec<AuditEntry> = lines
+        .iter()
+        .rev()
+        .take(limit.unwrap_or(200))
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    serde_json::to_string(&entries).map_err(|e| format!("Failed to serialize audit log: {}", e))
+}
+
+/// The complete retained log as JSON lines, oldest first, for saving with `write_file_to_path`.
+#[command]
+pub async fn export_audit_log(app: AppHandle) -> Result<String, String> {
+    let lines = AuditLog::open(&app)?.read_lines();
+    if lines.is_empty() {
+        return Err("The audit log is empty.".to_string());
+    }
+    Ok(lines.join("\n") + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_appends_survive_rotation() {
+        let root = std::env::temp_dir().join(format!("aovpn-audit-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        // Each entry is a little over 2 KB, so 800 of them rotate the active file once.
+        let output = Ok("x".repeat(MAX_RESULT_CHARS));
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    let log = AuditLog::new(root.clone());
+                    for _ in 0..100 {
+                        log.append(&AuditEntry::new("test", None, Vec::new(), &output)).unwrap();
+                    }
+                });
+            }
+        });
+
+        let log = AuditLog::new(root.clone());
+        assert!(log.file(1).exists());
+        let lines = log.read_lines();
+        assert_eq!(lines.len(), 800);
+        assert!(lines.iter().all(|line| serde_json::from_str::<AuditEntry>(line).is_ok()));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
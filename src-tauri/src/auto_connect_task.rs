@@ -0,0 +1,368 @@
+use crate::audit::{self, audited};
+use crate::config::{encode_powershell_script, ps_quote, AutoConnectTrigger, VpnConfig};
+use crate::profiles::{resolve_config, resolve_config_for_change};
+use crate::task_xml::{ExecAction, Principal, TaskDefinition, TaskSettings, Trigger};
+use crate::temp_task::TASK_AUTHOR;
+use serde::{Deserialize, Serialize};
+use std::os::windows::process::CommandExt;
+use std::process::Command;
+use tauri::{command, AppHandle};
+
+/// Splits a full task path into the `-TaskPath` folder (with leading and trailing `\`)
+/// and the `-TaskName` the ScheduledTasks cmdlets expect.
+pub fn split_task_path(path: &str) -> (String, String) {
+    let path = path.trim().trim_start_matches('\\');
+    match path.rsplit_once('\\') {
+        Some((folder, name)) => (format!("\\{}\\", folder), name.to_string()),
+        None => ("\\".to_string(), path.to_string()),
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TaskStatus {
+    pub task: String,
+    pub exists: bool,
+    pub enabled: bool,
+    /// `Ready`, `Running`, `Disabled`, ...
+    pub state: String,
+    pub last_run_time: String,
+    /// Exit code of the last run (0 = connected or already on the internal network).
+    pub last_task_result: Option<i64>,
+    pub next_run_time: String,
+}
+
+/// Script run by the auto-connect task: wait, skip if the internal network is reachable,
+/// otherwise dial the Device Tunnel with retries.
+pub fn auto_connect_script(config: &VpnConfig) -> String {
+    let ping_target = if config.internal_ping_target.trim().is_empty() {
+        &config.dns_suffix
+    } else {
+        &config.internal_ping_target
+    };
+    let backoff = if config.auto_connect_exponential_backoff {
+        "    $retryDelay = [Math]::Min($retryDelay * 2, $maxRetryDelay)\n"
+    } else {
+        ""
+    };
+
+    format!(r#"
+$vpnName = {vpn_name}
+$internalDomain = {ping_target}
+$maxRetries = {max_retries}
+$retryDelay = {retry_interval}
+$maxRetryDelay = {max_retry_interval}
+$retryCount = 0
+
+Start-Sleep -Seconds {initial_delay}
+
+if (Test-Connection -ComputerName $internalDomain -Count 2 -Quiet) {{ exit 0 }}
+
+do {{
+    $retryCount++
+    rasdial "$vpnName"
+    $status = Get-VpnConnection -Name "$vpnName" -ErrorAction SilentlyContinue
+    if ($status.ConnectionStatus -eq 'Connected') {{ exit 0 }}
+    Start-Sleep -Seconds $retryDelay
+{backoff}}} while ($retryCount -lt $maxRetries)
+exit 1
+"#,
+        vpn_name = ps_quote(&config.tunnel_names().device),
+        ping_target = ps_quote(ping_target),
+        max_retries = config.auto_connect_max_retries.max(1),
+        retry_interval = config.auto_connect_retry_interval_secs,
+        max_retry_interval = config.auto_connect_max_retry_interval_secs.max(config.auto_connect_retry_interval_secs),
+        initial_delay = config.auto_connect_initial_delay_secs,
+        backoff = backoff,
+    )
+}
+
+pub fn trigger_for(trigger: AutoConnectTrigger) -> Trigger {
+    match trigger {
+        AutoConnectTrigger::Boot => Trigger::Boot,
+        AutoConnectTrigger::Logon => Trigger::Logon,
+        AutoConnectTrigger::NetworkChange => {
+            Trigger::event("Microsoft-Windows-NetworkProfile/Operational", "Microsoft-Windows-NetworkProfile", 10000)
+        }
+        AutoConnectTrigger::Resume => Trigger::event("System", "Microsoft-Windows-Power-Troubleshooter", 1),
+    }
+}
+
+/// Task definition registered by `enable_task_scheduler_trigger`.
+pub fn auto_connect_task(config: &VpnConfig) -> TaskDefinition {
+    let mut triggers: Vec<Trigger> = Vec::new();
+    for trigger in config.auto_connect_triggers.iter().map(|t| trigger_for(*t)) {
+        if !triggers.contains(&trigger) {
+            triggers.push(trigger);
+        }
+    }
+    if triggers.is_empty() {
+        triggers.push(Trigger::Boot);
+    }
+
+    // Long enough for the initial delay and every retry at the longest interval (one minute per
+    // dial). Computed in u64 and saturating, since all three values come from the config.
+    let longest_interval = config.auto_connect_max_retry_interval_secs.max(config.auto_connect_retry_interval_secs);
+    let worst_case = u64::from(config.auto_connect_initial_delay_secs).saturating_add(
+        u64::from(config.auto_connect_max_retries.max(1)).saturating_mul(60 + u64::from(longest_interval)),
+    );
+
+    TaskDefinition {
+        author: TASK_AUTHOR.to_string(),
+        description: format!("Connects {} when the machine is off the internal network.", config.tunnel_names().device),
+        triggers,
+        principal: Principal::system(),
+        settings: TaskSettings {
+            execution_time_limit_secs: u32::try_from(worst_case).unwrap_or(u32::MAX).max(7200),
+            ..TaskSettings::default()
+        },
+        actions: vec![ExecAction {
+            command: "powershell.exe".to_string(),
+            arguments: format!(
+                "-NoProfile -NonInteractive -ExecutionPolicy Bypass -EncodedCommand {}",
+                encode_powershell_script(&auto_connect_script(config))
+            ),
+        }],
+    }
+}
+
+fn run_powershell(script: &str) -> Result<String, String> {
+    audit::track_script(script);
+    let output = Command::new("powershell")
+        .args(["-Command", script])
+        .creation_flags(0x08000000)
+        .output()
+        .map_err(|e| format!("PowerShell error: {}", e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).to_string())
+    }
+}
+
+fn task_selector(task: &str) -> String {
+    let (folder, name) = split_task_path(task);
+    format!("-TaskPath {} -TaskName {}", ps_quote(&folder), ps_quote(&name))
+}
+
+pub fn task_status(task: &str) -> Result<TaskStatus, String> {
+    let script = format!(r#"
+$t = Get-ScheduledTask {selector} -ErrorAction SilentlyContinue
+if (-not $t) {{ '{{"exists":false}}'; exit 0 }}
+$i = $t | Get-ScheduledTaskInfo -ErrorAction SilentlyContinue
+@{{
+    exists = $true
+    enabled = [bool]$t.Settings.Enabled
+    state = "$($t.State)"
+    lastRunTime = "$($i.LastRunTime)"
+    lastTaskResult = $i.LastTaskResult
+    nextRunTime = "$($i.NextRunTime)"
+}} | ConvertTo-Json -Compress
+"#, selector = task_selector(task));
+    let output = run_powershell(&script)?;
+    let mut status: TaskStatus =
+        serde_json::from_str(&output).map_err(|e| format!("Unexpected Get-ScheduledTask output: {}", e))?;
+    status.task = task.to_string();
+    Ok(status)
+}
+
+pub fn set_task_enabled(task: &str, enabled: bool) -> Result<String, String> {
+    let cmdlet = if enabled { "Enable-ScheduledTask" } else { "Disable-ScheduledTask" };
+    run_powershell(&format!("{} {} -ErrorAction Stop | Out-Null", cmdlet, task_selector(task)))?;
+    Ok(format!("Task '{}' {}.", task, if enabled { "enabled" } else { "disabled" }))
+}
+
+/// Unregisters the task and removes its folder if nothing else is left in it.
+pub fn remove_task(task: &str) -> Result<String, String> {
+    let (folder, _) = split_task_path(task);
+    let script = format!(r#"
+$task = {task}
+$t = Get-ScheduledTask {selector} -ErrorAction SilentlyContinue
+if (-not $t) {{ "Task '$task' is not registered."; exit 0 }}
+Unregister-ScheduledTask {selector} -Confirm:$false -ErrorAction Stop
+$folder = {folder}.TrimEnd('\')
+if ($folder) {{
+    $service = New-Object -ComObject Schedule.Service
+    $service.Connect()
+    try {{
+        $f = $service.GetFolder($folder)
+        if ($f.GetTasks(1).Count -eq 0 -and $f.GetFolders(0).Count -eq 0) {{
+            $service.GetFolder('\').DeleteFolder($folder.TrimStart('\'), 0)
+        }}
+    }} catch {{ }}
+}}
+"Task '$task' removed."
+"#, selector = task_selector(task), task = ps_quote(task), folder = ps_quote(&folder));
+    run_powershell(&script)
+}
+
+#[command]
+pub async fn get_auto_connect_task_status(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let config = resolve_config(&app, config, profile_id)?;
+    let status = task_status(&config.tunnel_names().auto_connect_task)?;
+    serde_json::to_string(&status).map_err(|e| format!("Failed to serialize task status: {}", e))
+}
+
+#[command]
+pub async fn set_auto_connect_task_enabled(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+    enabled: bool,
+) -> Result<String, String> {
+    let config = resolve_config_for_change(&app, config, profile_id)?;
+    audited(&app, "set_auto_connect_task_enabled", Some(&config), || {
+        set_task_enabled(&config.tunnel_names().auto_connect_task, enabled)
+    })
+}
+
+#[command]
+pub async fn remove_auto_connect_task(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let config = resolve_config_for_change(&app, config, profile_id)?;
+    audited(&app, "remove_auto_connect_task", Some(&config), || remove_task(&config.tunnel_names().auto_connect_task))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+
+    fn task_xml(config: &VpnConfig) -> String {
+        auto_connect_task(config).to_xml()
+    }
+
+    fn child_text<'a>(doc: &'a roxmltree::Document, name: &str) -> Vec<&'a str> {
+        doc.descendants().filter(|n| n.has_tag_name(name)).filter_map(|n| n.text()).collect()
+    }
+
+    /// The script behind `-EncodedCommand` (base64 of UTF-16LE).
+    fn decoded_script(xml: &str) -> String {
+        let doc = roxmltree::Document::parse(xml).unwrap();
+        let arguments = child_text(&doc, "Arguments")[0];
+        let encoded = arguments.rsplit(' ').next().unwrap();
+        let bytes = base64::engine::general_purpose::STANDARD.decode(encoded).unwrap();
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+        String::from_utf16(&units).unwrap()
+    }
+
+    #[test]
+    fn triggers_are_rendered_as_task_scheduler_triggers() {
+        let config = VpnConfig {
+            company_prefix: "Contoso".into(),
+            auto_connect_triggers: vec![
+                AutoConnectTrigger::Boot,
+                AutoConnectTrigger::NetworkChange,
+                AutoConnectTrigger::Logon,
+                AutoConnectTrigger::Resume,
+                AutoConnectTrigger::Logon,
+            ],
+            ..VpnConfig::default()
+        };
+        let xml = task_xml(&config);
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+        let triggers: Vec<&str> = doc
+            .descendants()
+            .find(|n| n.has_tag_name("Triggers"))
+            .unwrap()
+            .children()
+            .filter(|n| n.is_element())
+            .map(|n| n.tag_name().name())
+            .collect();
+        assert_eq!(triggers, ["BootTrigger", "EventTrigger", "LogonTrigger", "EventTrigger"]);
+
+        // The subscription is escaped XML; parse it as Task Scheduler does.
+        let subscriptions: Vec<roxmltree::Document> =
+            child_text(&doc, "Subscription").into_iter().map(|s| roxmltree::Document::parse(s).unwrap()).collect();
+        let select = |d: &roxmltree::Document| {
+            let node = d.descendants().find(|n| n.has_tag_name("Select")).unwrap();
+            (node.attribute("Path").unwrap().to_string(), node.text().unwrap().to_string())
+        };
+        assert_eq!(
+            select(&subscriptions[0]),
+            (
+                "Microsoft-Windows-NetworkProfile/Operational".to_string(),
+                "*[System[Provider[@Name='Microsoft-Windows-NetworkProfile'] and EventID=10000]]".to_string()
+            )
+        );
+        assert_eq!(
+            select(&subscriptions[1]),
+            (
+                "System".to_string(),
+                "*[System[Provider[@Name='Microsoft-Windows-Power-Troubleshooter'] and EventID=1]]".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn no_triggers_falls_back_to_boot() {
+        let config = VpnConfig { auto_connect_triggers: Vec::new(), ..VpnConfig::default() };
+        let xml = task_xml(&config);
+        assert!(xml.contains("<BootTrigger>"));
+        assert!(!xml.contains("<EventTrigger>"));
+    }
+
+    #[test]
+    fn retries_and_backoff_reach_the_script_and_time_limit() {
+        let config = VpnConfig {
+            auto_connect_initial_delay_secs: 30,
+            auto_connect_max_retries: 40,
+            auto_connect_retry_interval_secs: 15,
+            auto_connect_exponential_backoff: true,
+            auto_connect_max_retry_interval_secs: 600,
+            ..VpnConfig::default()
+        };
+        let xml = task_xml(&config);
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+        // 30 + 40 * (60 + 600) seconds.
+        assert_eq!(child_text(&doc, "ExecutionTimeLimit"), ["PT26430S"]);
+
+        let script = decoded_script(&xml);
+        assert!(script.contains("$maxRetries = 40\n"));
+        assert!(script.contains("$retryDelay = 15\n"));
+        assert!(script.contains("$maxRetryDelay = 600\n"));
+        assert!(script.contains("Start-Sleep -Seconds 30\n"));
+        assert!(script.contains("$retryDelay = [Math]::Min($retryDelay * 2, $maxRetryDelay)"));
+
+        let linear = task_xml(&VpnConfig { auto_connect_exponential_backoff: false, ..config });
+        assert!(!decoded_script(&linear).contains("[Math]::Min"));
+
+        // Short schedules keep the two hour minimum.
+        let default_xml = task_xml(&VpnConfig::default());
+        assert!(default_xml.contains("<ExecutionTimeLimit>PT2H</ExecutionTimeLimit>"));
+    }
+
+    #[test]
+    fn huge_retry_values_do_not_overflow() {
+        let config = VpnConfig {
+            auto_connect_initial_delay_secs: u32::MAX,
+            auto_connect_max_retries: u32::MAX,
+            auto_connect_retry_interval_secs: u32::MAX,
+            auto_connect_max_retry_interval_secs: u32::MAX,
+            ..VpnConfig::default()
+        };
+        let task = auto_connect_task(&config);
+        assert_eq!(task.settings.execution_time_limit_secs, u32::MAX);
+    }
+
+    #[test]
+    fn names_are_single_quoted_literals() {
+        let config = VpnConfig {
+            device_tunnel_name: "O'Brien $(Stop-Computer) \"VPN\"".into(),
+            internal_ping_target: "dc01'; Remove-Item C:\\ #".into(),
+            ..VpnConfig::default()
+        };
+        let script = decoded_script(&task_xml(&config));
+        assert!(script.contains("\n$vpnName = 'O''Brien $(Stop-Computer) \"VPN\"'\n"));
+        assert!(script.contains("\n$internalDomain = 'dc01''; Remove-Item C:\\ #'\n"));
+
+        assert_eq!(task_selector("\\It's\\Task"), "-TaskPath '\\It''s\\' -TaskName 'Task'");
+    }
+}
@@ -0,0 +1,405 @@
+use crate::audit::{self, audited};
+use crate::config::ps_quote;
+use crate::jobs;
+use crate::phonebook::{Phonebook, PhonebookScope};
+use crate::profile_drift::{diff_profile_xml, read_deployed_profile_xml, ProfileContext};
+use crate::vpn_deploy::{
+    apply_device_profile, apply_user_profile, delete_device_profile, delete_user_profile, run_as_system_task,
+    run_as_system_task_with_output, DeployMode,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::os::windows::process::CommandExt;
+use std::path::PathBuf;
+use std::process::Command;
+use tauri::{command, AppHandle, Manager};
+
+/// `Get-VpnConnection` IPsec policy properties, in `Set-VpnConnectionIPsecConfiguration` parameter order.
+const IPSEC_PARAMETERS: [(&str, &str); 6] = [
+    ("AuthenticationTransformConstants", "AuthenticationTransformConstants"),
+    ("CipherTransformConstants", "CipherTransformConstants"),
+    ("DHGroup", "DHGroup"),
+    ("EncryptionMethod", "EncryptionMethod"),
+    ("IntegrityCheckMethod", "IntegrityCheckMethod"),
+    ("PfsGroup", "PFSgroup"),
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum TunnelKind {
+    Device,
+    User,
+    /// The All Users connection created with `Add-VpnConnection`, which has no ProfileXML.
+    UserAll,
+}
+
+impl TunnelKind {
+    /// Device and User Tunnel (All) live in the All Users phonebook.
+    fn all_users(self) -> bool {
+        self != TunnelKind::User
+    }
+}
+
+/// State of one connection before a command changed it. `None`/empty means the
+/// connection did not exist, so restoring it removes the connection, unless `profile_error`
+/// says the profile could not be read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TunnelSnapshot {
+    pub kind: TunnelKind,
+    pub name: String,
+    pub profile_xml: Option<String>,
+    /// rasphone.pbk section, only for the User Tunnel (All).
+    pub phonebook_entry: Option<String>,
+    /// Custom IPsec policy (`Get-VpnConnection` property name to value), if one was set.
+    pub ipsec: Option<BTreeMap<String, String>>,
+    pub routes: Vec<String>,
+    /// Why the profile or phonebook entry could not be read. Such a snapshot cannot be restored.
+    #[serde(default)]
+    pub profile_error: Option<String>,
+    /// Problems reading the IPsec policy and routes; those are left empty.
+    pub warnings: Vec<String>,
+}
+
+impl TunnelSnapshot {
+    pub fn existed(&self) -> bool {
+        self.profile_xml.is_some() || self.phonebook_entry.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Backup {
+    pub id: String,
+    pub created: String,
+    /// Command that triggered the backup, e.g. `deploy_device_tunnel`.
+    pub operation: String,
+    pub snapshots: Vec<TunnelSnapshot>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSummary {
+    pub id: String,
+    pub created: String,
+    pub operation: String,
+    pub tunnels: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct ConnectionState {
+    ipsec: Option<BTreeMap<String, String>>,
+    routes: Vec<String>,
+}
+
+/// Reads the custom IPsec policy and routes of a connection via `Get-VpnConnection`.
+fn read_connection_state(kind: TunnelKind, name: &str) -> Result<ConnectionState, String> {
+    let properties = IPSEC_PARAMETERS.iter().map(|(p, _)| format!("'{}'", p)).collect::<Vec<_>>().join(", ");
+    let script = format!(r#"
+$c = Get-VpnConnection -Name {name} {scope} -ErrorAction SilentlyContinue
+if (-not $c) {{ '{{}}'; exit 0 }}
+$ipsec = $null
+if ($c.IPsecCustomPolicy) {{
+    $ipsec = @{{}}
+    foreach ($p in @({properties})) {{ $ipsec[$p] = "$($c.IPsecCustomPolicy.$p)" }}
+}}
+$routes = @($c.Routes | ForEach-Object {{ "$($_.DestinationPrefix)" }})
+@{{ ipsec = $ipsec; routes = $routes }} | ConvertTo-Json -Compress
+"#, name = ps_quote(name), scope = if kind.all_users() { "-AllUserConnection" } else { "" }, properties = properties);
+
+    let output = Command::new("powershell")
+        .args(["-Command", &script])
+        .creation_flags(0x08000000)
+        .output()
+        .map_err(|e| format!("PowerShell error: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    serde_json::from_str(stdout.trim()).map_err(|e| format!("Unexpected Get-VpnConnection output: {}", e))
+}
+
+pub fn snapshot_tunnel(kind: TunnelKind, name: &str) -> TunnelSnapshot {
+    let mut snapshot = TunnelSnapshot {
+        kind,
+        name: name.to_string(),
+        profile_xml: None,
+        phonebook_entry: None,
+        ipsec: None,
+        routes: Vec::new(),
+        profile_error: None,
+        warnings: Vec::new(),
+    };
+
+    let profile = match kind {
+        TunnelKind::Device => read_deployed_profile_xml(ProfileContext::Device, name).map(|xml| snapshot.profile_xml = xml),
+        TunnelKind::User => read_deployed_profile_xml(ProfileContext::User, name).map(|xml| snapshot.profile_xml = xml),
+        TunnelKind::UserAll => PhonebookScope::AllUsers.read().map(|pb| {
+            snapshot.phonebook_entry = pb
+                .entry(name)
+                .map(|e| Phonebook { entries: vec![e.clone()] }.to_pbk_string());
+        }),
+    };
+    if let Err(e) = profile {
+        snapshot.profile_error = Some(e);
+    }
+
+    match read_connection_state(kind, name) {
+        Ok(state) => {
+            snapshot.ipsec = state.ipsec;
+            snapshot.routes = state.routes;
+        }
+        Err(e) => snapshot.warnings.push(format!("IPsec/routes: {}", e)),
+    }
+    snapshot
+}
+
+/// Timestamped backups, one JSON file each in `<app data>/backups`.
+pub struct BackupStore {
+    root: PathBuf,
+}
+
+impl BackupStore {
+    pub fn new(root: PathBuf) -> BackupStore {
+        BackupStore { root }
+    }
+
+    pub fn open(app: &AppHandle) -> Result<BackupStore, String> {
+        let dir = app
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+        Ok(BackupStore::new(dir.join("backups")))
+    }
+
+    fn path_for(&self, id: &str) -> Result<PathBuf, String> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!("Invalid backup id '{}'.", id));
+        }
+        Ok(self.root.join(format!("{}.json", id)))
+    }
+
+    pub fn save(&self, operation: &str, snapshots: Vec<TunnelSnapshot>) -> Result<Backup, String> {
+        std::fs::create_dir_all(&self.root)
+            .map_err(|e| format!("Failed to create backup directory {}: {}", self.root.display(), e))?;
+        let now = chrono::Local::now();
+        let base = format!("{}-{}", now.format("%Y%m%d-%H%M%S"), operation);
+        let mut id = base.clone();
+        let mut n = 2;
+        while self.root.join(format!("{}.json", id)).exists() {
+            id = format!("{}-{}", base, n);
+            n += 1;
+        }
+        let backup = Backup {
+            id,
+            created: now.to_rfc3339(),
+            operation: operation.to_string(),
+            snapshots,
+        };
+        let content = serde_json::to_string_pretty(&backup).map_err(|e| format!("Failed to serialize backup: {}", e))?;
+        std::fs::write(self.path_for(&backup.id)?, content).map_err(|e| format!("Failed to write backup: {}", e))?;
+        Ok(backup)
+    }
+
+    pub fn load(&self, id: &str) -> Result<Backup, String> {
+        let content = std::fs::read_to_string(self.path_for(id)?).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => format!("Backup '{}' does not exist.", id),
+            _ => format!("Failed to read backup '{}': {}", id, e),
+        })?;
+        serde_json::from_str(&content).map_err(|e| format!("Backup '{}' is damaged: {}", id, e))
+    }
+
+    /// Newest first.
+    pub fn list(&self) -> Result<Vec<BackupSummary>, String> {
+        let entries = match std::fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to list backups: {}", e)),
+        };
+        let mut backups: Vec<BackupSummary> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let id = entry.path().file_stem()?.to_str()?.to_string();
+                let backup = self.load(&id).ok()?;
+                Some(BackupSummary {
+                    id: backup.id,
+                    created: backup.created,
+                    operation: backup.operation,
+                    tunnels: backup.snapshots.into_iter().map(|s| s.name).collect(),
+                })
+            })
+            .collect();
+        backups.sort_by(|a, b| b.created.cmp(&a.created));
+        Ok(backups)
+    }
+}
+
+/// Snapshots `tunnels` and stores them before a deploy or remove command changes them.
+pub fn backup_before(app: &AppHandle, operation: &str, tunnels: &[(TunnelKind, String)]) -> Result<Backup, String> {
+    let snapshots = tunnels.iter().map(|(kind, name)| snapshot_tunnel(*kind, name)).collect();
+    BackupStore::open(app)?.save(operation, snapshots)
+}
+
+pub fn backup_note(backup: &Backup) -> String {
+    format!("Backup '{}' saved.", backup.id)
+}
+
+fn set_ipsec_script(snapshot: &TunnelSnapshot, ipsec: &BTreeMap<String, String>) -> String {
+    let args: Vec<String> = IPSEC_PARAMETERS
+        .iter()
+        .filter_map(|(property, parameter)| ipsec.get(*property).map(|v| format!("-{} {}", parameter, ps_quote(v))))
+        .collect();
+    format!(
+        "Set-VpnConnectionIPsecConfiguration -ConnectionName {} {} {} -Force -ErrorAction SilentlyContinue\n",
+        ps_quote(&snapshot.name),
+        if snapshot.kind == TunnelKind::User { "" } else { "-AllUserConnection" },
+        args.join(" ")
+    )
+}
+
+fn add_routes_script(snapshot: &TunnelSnapshot) -> String {
+    snapshot
+        .routes
+        .iter()
+        .map(|route| {
+            format!(
+                "Add-VpnConnectionRoute -ConnectionName {} -DestinationPrefix {} {} -ErrorAction SilentlyContinue | Out-Null\n",
+                ps_quote(&snapshot.name),
+                ps_quote(route),
+                if snapshot.kind == TunnelKind::User { "" } else { "-AllUserConnection" }
+            )
+        })
+        .collect()
+}
+
+/// Removes the connection, waits for the SYSTEM task to finish, then puts back only this
+/// connection's phonebook section; other entries changed since the snapshot are kept.
+fn restore_user_all(snapshot: &TunnelSnapshot) -> Result<String, String> {
+    let remove = format!(
+        "Remove-VpnConnection -Name {} -AllUserConnection -Force -ErrorAction SilentlyContinue\n",
+        ps_quote(&snapshot.name)
+    );
+    run_as_system_task_with_output("TempRestoreUserTunnelAll", &remove)?;
+    let Some(entry) = &snapshot.phonebook_entry else {
+        return Ok(format!("'{}' removed.", snapshot.name));
+    };
+    PhonebookScope::AllUsers.write_entry(&snapshot.name, Some(entry))?;
+    Ok(format!("Phonebook entry '{}' restored.", snapshot.name))
+}
+
+/// Reapplies one snapshot: the profile (or its removal), then IPsec policy and routes.
+pub fn restore_snapshot(snapshot: &TunnelSnapshot) -> Result<String, String> {
+    // Without the profile it is unknown whether the connection existed; never remove it.
+    if let Some(e) = &snapshot.profile_error {
+        return Err(format!("The profile could not be read when the backup was taken, so it is left as is: {}", e));
+    }
+    let mut output = match (snapshot.kind, &snapshot.profile_xml) {
+        (TunnelKind::Device, Some(xml)) => apply_device_profile(&snapshot.name, xml, DeployMode::Recreate)?,
+        (TunnelKind::Device, None) => delete_device_profile(&snapshot.name)?,
+        (TunnelKind::User, Some(xml)) => apply_user_profile(&snapshot.name, xml, DeployMode::Recreate)?,
+        (TunnelKind::User, None) => delete_user_profile(&snapshot.name)?,
+        (TunnelKind::UserAll, _) => restore_user_all(snapshot)?,
+    };
+    if !snapshot.existed() {
+        return Ok(output);
+    }
+
+    let mut script = String::new();
+    if let Some(ipsec) = &snapshot.ipsec {
+        script.push_str(&set_ipsec_script(snapshot, ipsec));
+    }
+    script.push_str(&add_routes_script(snapshot));
+    if script.is_empty() {
+        return Ok(output);
+    }
+    let result = if snapshot.kind == TunnelKind::User {
+        audit::track_script(&script);
+        Command::new("powershell")
+            .args(["-Command", &script])
+            .creation_flags(0x08000000)
+            .output()
+            .map_err(|e| format!("PowerShell error: {}", e))
+            .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+    } else {
+        run_as_system_task("TempRestoreIpsec", &script)
+    };
+    match result {
+        Ok(_) => output.push_str("\nIPsec configuration and routes restored."),
+        Err(e) => output.push_str(&format!("\nWARNING: Failed to restore IPsec configuration/routes: {}", e)),
+    }
+    Ok(output)
+}
+
+/// Restores every snapshot, even after one fails; fails with all outputs and errors if any did.
+pub fn restore(backup: &Backup) -> Result<String, String> {
+    let mut lines = Vec::new();
+    let mut failed = false;
+    for snapshot in &backup.snapshots {
+        match restore_snapshot(snapshot) {
+            Ok(output) => lines.push(format!("[{}] {}", snapshot.name, output.trim())),
+            Err(e) => {
+                failed = true;
+                lines.push(format!("[{}] Restoring failed: {}", snapshot.name, e.trim()));
+            }
+        }
+    }
+    if failed {
+        Err(lines.join("\n"))
+    } else {
+        Ok(lines.join("\n"))
+    }
+}
+
+/// Snapshot, back up and deploy; roll back to the snapshot if `deploy` (which includes the
+/// post-deployment verification) fails. In `Update` mode an unchanged profile is left alone.
+pub fn deploy_with_backup(
+    app: &AppHandle,
+    operation: &str,
+    kind: TunnelKind,
+    name: &str,
+    expected_xml: Option<&str>,
+    mode: DeployMode,
+    deploy: impl FnOnce() -> Result<String, String>,
+) -> Result<String, String> {
+    jobs::progress(&format!("Capturing current state of '{}'", name));
+    let snapshot = snapshot_tunnel(kind, name);
+    if let Some(e) = &snapshot.profile_error {
+        return Err(format!("Failed to read the current profile of '{}', so it cannot be backed up; nothing was changed: {}", name, e));
+    }
+    if let (DeployMode::Update, Some(deployed), Some(expected)) = (mode, &snapshot.profile_xml, expected_xml) {
+        if diff_profile_xml(expected, deployed).is_ok_and(|d| d.is_empty()) {
+            return Ok(format!("'{}' is already up to date; nothing was changed.", name));
+        }
+    }
+
+    let backup = BackupStore::open(app)?.save(operation, vec![snapshot])?;
+    jobs::check_cancelled()?;
+    jobs::progress(&format!("Backup '{}' saved, deploying '{}'", backup.id, name));
+    match deploy() {
+        Ok(output) => Ok(format!("{}\n{}", backup_note(&backup), output)),
+        Err(error) => {
+            jobs::progress(&format!("Deploy failed, rolling back to backup '{}'", backup.id));
+            // A cancelled or timed-out deploy must still be rolled back completely.
+            match jobs::uncancellable(|| restore(&backup)) {
+                Ok(_) => Err(format!("{}\nRolled back to backup '{}'.", error, backup.id)),
+                Err(rollback_error) => Err(format!(
+                    "{}\nRollback to backup '{}' failed: {}",
+                    error, backup.id, rollback_error
+                )),
+            }
+        }
+    }
+}
+
+#[command]
+pub async fn list_backups(app: AppHandle) -> Result<String, String> {
+    let backups = BackupStore::open(&app)?.list()?;
+    serde_json::to_string(&backups).map_err(|e| format!("Failed to serialize backups: {}", e))
+}
+
+#[command]
+pub async fn restore_backup(app: AppHandle, id: String) -> Result<String, String> {
+    let backup = BackupStore::open(&app)?.load(&id)?;
+    audited(&app, "restore_backup", None, || restore(&backup))
+}
@@ -1,8 +1,10 @@
 use base64::Engine;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(rename_all = "camelCase")]
+/// Missing fields fall back to `Default`, so configs exported before a field existed still load.
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
 pub struct VpnConfig {
     pub company_prefix: String,
     pub vpn_server_address: String,
@@ -18,12 +20,162 @@ pub struct VpnConfig {
     pub user_tunnel_always_on: bool,
     pub force_tunneling: bool,
     pub disable_class_based_route: bool,
+    /// Writes `DisableIKEv2Fragmentation` into both profiles; see `mtu_probe.rs`.
+    pub disable_ikev2_fragmentation: bool,
+    /// Host pinged by `diagnose_mtu` when the VPN server drops ICMP; skipped when empty.
+    pub mtu_reference_host: String,
     pub disable_disconnect_button: bool,
     pub sstp_disable_revocation: bool,
     pub internal_ping_target: String,
     pub device_tunnel_always_on: bool,
     pub eku_name: Option<String>,
     pub eku_oid: Option<String>,
+    /// Naming templates, see `tunnel_names`.
+    pub device_tunnel_name: String,
+    pub user_tunnel_name: String,
+    pub user_tunnel_all_name: String,
+    pub auto_connect_task_name: String,
+    /// Auto-connect task behaviour, see `auto_connect_task.rs`.
+    pub auto_connect_triggers: Vec<AutoConnectTrigger>,
+    pub auto_connect_initial_delay_secs: u32,
+    pub auto_connect_max_retries: u32,
+    pub auto_connect_retry_interval_secs: u32,
+    /// Doubles the retry interval after every failed attempt, up to `auto_connect_max_retry_interval_secs`.
+    pub auto_connect_exponential_backoff: bool,
+    pub auto_connect_max_retry_interval_secs: u32,
+}
+
+/// Events that start the auto-connect task.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum AutoConnectTrigger {
+    Boot,
+    /// A network was connected (NetworkProfile event 10000).
+    NetworkChange,
+    Logon,
+    /// Resume from sleep or hibernation (Power-Troubleshooter event 1).
+    Resume,
+}
+
+pub const DEFAULT_DEVICE_TUNNEL_NAME: &str = "%PREFIX% Device Tunnel";
+pub const DEFAULT_USER_TUNNEL_NAME: &str = "%PREFIX% User Tunnel";
+pub const DEFAULT_USER_TUNNEL_ALL_NAME: &str = "%PREFIX% User Tunnel All";
+pub const DEFAULT_AUTO_CONNECT_TASK_NAME: &str = "\\%PREFIX%\\Start %DEVICE%";
+
+/// Resolved connection and task names for one configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TunnelNames {
+    pub device: String,
+    pub user: String,
+    pub user_all: String,
+    /// Full Task Scheduler path of the auto-connect task, including its folder.
+    pub auto_connect_task: String,
+}
+
+/// Mirrors the initial form state in `App.tsx`.
+impl Default for VpnConfig {
+    fn default() -> Self {
+        VpnConfig {
+            company_prefix: String::new(),
+            vpn_server_address: String::new(),
+            dns_suffix: String::new(),
+            dns_servers: String::new(),
+            trusted_network: String::new(),
+            root_ca_hash: String::new(),
+            eap_server_names: String::new(),
+            device_routes: Vec::new(),
+            user_routes: Vec::new(),
+            enable_task_scheduler_trigger: true,
+            user_tunnel_protocol: "SSTP".to_string(),
+            user_tunnel_always_on: true,
+            force_tunneling: false,
+            disable_class_based_route: false,
+            disable_ikev2_fragmentation: false,
+            mtu_reference_host: String::new(),
+            disable_disconnect_button: false,
+            sstp_disable_revocation: false,
+            internal_ping_target: String::new(),
+            device_tunnel_always_on: true,
+            eku_name: None,
+            eku_oid: None,
+            device_tunnel_name: DEFAULT_DEVICE_TUNNEL_NAME.to_string(),
+            user_tunnel_name: DEFAULT_USER_TUNNEL_NAME.to_string(),
+            user_tunnel_all_name: DEFAULT_USER_TUNNEL_ALL_NAME.to_string(),
+            auto_connect_task_name: DEFAULT_AUTO_CONNECT_TASK_NAME.to_string(),
+            auto_connect_triggers: vec![AutoConnectTrigger::Boot],
+            auto_connect_initial_delay_secs: 20,
+            auto_connect_max_retries: 5,
+            auto_connect_retry_interval_secs: 10,
+            auto_connect_exponential_backoff: false,
+            auto_connect_max_retry_interval_secs: 300,
+        }
+    }
+}
+
+/// Lower-case ASCII letters and digits, other runs of characters collapsed to one `-`
+/// (`"Contoso AG"` -> `contoso-ag`). Empty if `text` has no letters or digits.
+pub fn slugify(text: &str) -> String {
+    let mut name = String::new();
+    for c in text.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_lowercase());
+        } else if !name.is_empty() && !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+    name.trim_end_matches('-').to_string()
+}
+
+impl VpnConfig {
+    /// Resolves the naming templates. `%PREFIX%` is replaced by `company_prefix` and, in the
+    /// task name, `%DEVICE%` by the resolved Device Tunnel name. An empty template falls back
+    /// to the default, so existing fleets keep their `<prefix> Device Tunnel` names.
+    pub fn tunnel_names(&self) -> TunnelNames {
+        let prefix = self.company_prefix.trim();
+        let resolve = |template: &str, default: &str| {
+            let template = if template.trim().is_empty() { default } else { template.trim() };
+            template.replace("%PREFIX%", prefix)
+        };
+        let device = resolve(&self.device_tunnel_name, DEFAULT_DEVICE_TUNNEL_NAME);
+        let auto_connect_task =
+            resolve(&self.auto_connect_task_name, DEFAULT_AUTO_CONNECT_TASK_NAME).replace("%DEVICE%", &device);
+        TunnelNames {
+            user: resolve(&self.user_tunnel_name, DEFAULT_USER_TUNNEL_NAME),
+            user_all: resolve(&self.user_tunnel_all_name, DEFAULT_USER_TUNNEL_ALL_NAME),
+            auto_connect_task,
+            device,
+        }
+    }
+
+    /// Server identity used for certificate validation: the first `eap_server_names`
+    /// entry (Windows separates them with `;`), or the server address if none is set.
+    pub fn remote_identity(&self) -> &str {
+        self.eap_server_names
+            .split(';')
+            .map(str::trim)
+            .find(|s| !s.is_empty())
+            .unwrap_or(self.vpn_server_address.trim())
+    }
+
+    /// Lower-case, dash-separated name derived from `company_prefix`, used for exported
+    /// connection and file names (`"Contoso AG"` + `"device"` -> `contoso-ag-device`).
+    pub fn slug(&self, suffix: &str) -> String {
+        let name = slugify(&self.company_prefix);
+        if name.is_empty() {
+            suffix.to_string()
+        } else {
+            format!("{}-{}", name, suffix)
+        }
+    }
+
+    /// `dns_servers` split on commas, as written into `<DnsServers>`.
+    pub fn dns_server_list(&self) -> Vec<&str> {
+        self.dns_servers
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
 }
 
 /// Helper to convert a string to a UTF-16LE Base64 string for PowerShell's -EncodedCommand
@@ -34,3 +186,23 @@ pub fn encode_powershell_script(script: &str) -> String {
         .collect();
     base64::engine::general_purpose::STANDARD.encode(&utf16)
 }
+
+/// Quotes a value as a PowerShell single-quoted literal, in which nothing is expanded.
+pub fn ps_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Deterministic UUID derived from `seed` (128-bit FNV-1a), so re-exporting the same
+/// configuration replaces an installed profile instead of adding a second one.
+pub fn stable_uuid(seed: &str) -> String {
+    let mut hash: u128 = 0x6c62272e07bb014262b821756295c58d;
+    for byte in seed.bytes() {
+        hash ^= byte as u128;
+        hash = hash.wrapping_mul(0x0000000001000000000000000000013B);
+    }
+    let mut bytes = hash.to_be_bytes();
+    bytes[6] = (bytes[6] & 0x0f) | 0x50;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
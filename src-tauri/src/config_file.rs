@@ -0,0 +1,198 @@
+use crate::config::VpnConfig;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::command;
+
+/// Version written by `export_config_file`. Bump it together with a new entry in `MIGRATIONS`.
+pub const CURRENT_SCHEMA_VERSION: u64 = 1;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ConfigMetadata {
+    /// Display name when the file is stored as a named profile.
+    pub name: String,
+    pub author: String,
+    /// RFC 3339 timestamp of the export.
+    pub created: String,
+    pub description: String,
+    /// Version of the AOVPN Dashboard that wrote the file.
+    pub app_version: String,
+}
+
+/// On-disk envelope for exported configurations.
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigFile {
+    pub schema_version: u64,
+    #[serde(default)]
+    pub metadata: ConfigMetadata,
+    pub config: VpnConfig,
+}
+
+type Migration = fn(Value) -> Result<Value, String>;
+
+/// `MIGRATIONS[n]` upgrades a document from schema version `n` to `n + 1`.
+const MIGRATIONS: [Migration; 1] = [migrate_v0_to_v1];
+
+/// Version 0 is the bare `VpnConfig` object exported by releases up to 1.7.
+/// Fields added over time are filled with the behaviour those releases had:
+/// the Device Tunnel was always AlwaysOn and the scheduled task pinged `dnsSuffix`.
+fn migrate_v0_to_v1(value: Value) -> Result<Value, String> {
+    let mut config = match value {
+        Value::Object(map) => map,
+        _ => return Err("Configuration file must contain a JSON object.".to_string()),
+    };
+    config.entry("deviceTunnelAlwaysOn").or_insert(json!(true));
+    config.entry("internalPingTarget").or_insert(json!(""));
+    // The frontend stores empty strings for unset EKU fields; the backend expects null.
+    for key in ["ekuName", "ekuOid"] {
+        if config.get(key).and_then(Value::as_str).is_some_and(|s| s.trim().is_empty()) {
+            config.insert(key.to_string(), Value::Null);
+        }
+    }
+    Ok(json!({
+        "schemaVersion": 1,
+        "metadata": {},
+        "config": Value::Object(config),
+    }))
+}
+
+fn schema_version(value: &Value) -> Result<u64, String> {
+    match value.get("schemaVersion") {
+        None => Ok(0),
+        Some(v) => v.as_u64().ok_or("schemaVersion must be a non-negative integer.".to_string()),
+    }
+}
+
+/// Upgrades any supported document to `CURRENT_SCHEMA_VERSION`.
+pub fn migrate(value: Value) -> Result<ConfigFile, String> {
+    let value = migrate_value(value)?;
+    serde_json::from_value(value).map_err(|e| format!("Invalid configuration file: {}", e))
+}
+
+/// Runs the migrations on the raw JSON document without filling in defaults, so callers
+/// can still tell which fields the file actually set.
+pub fn migrate_value(mut value: Value) -> Result<Value, String> {
+    let mut version = schema_version(&value)?;
+    if version > CURRENT_SCHEMA_VERSION {
+        return Err(format!(
+            "Configuration schema version {} is newer than this app supports ({}). Please update AOVPN Dashboard.",
+            version, CURRENT_SCHEMA_VERSION
+        ));
+    }
+    while version < CURRENT_SCHEMA_VERSION {
+        value = MIGRATIONS[version as usize](value)?;
+        version = schema_version(&value)?;
+    }
+    Ok(value)
+}
+
+pub fn parse_config_file(content: &str) -> Result<ConfigFile, String> {
+    let value: Value = serde_json::from_str(content).map_err(|e| format!("Invalid JSON: {}", e))?;
+    migrate(value)
+}
+
+pub fn to_config_file(config: VpnConfig, metadata: Option<ConfigMetadata>) -> ConfigFile {
+    let mut metadata = metadata.unwrap_or_default();
+    if metadata.created.is_empty() {
+        metadata.created = chrono::Local::now().to_rfc3339();
+    }
+    if metadata.author.is_empty() {
+        metadata.author = std::env::var("USERNAME").unwrap_or_default();
+    }
+    metadata.app_version = env!("CARGO_PKG_VERSION").to_string();
+    ConfigFile {
+        schema_version: CURRENT_SCHEMA_VERSION,
+        metadata,
+        config,
+    }
+}
+
+/// JSON Schema of the current `ConfigFile` format, for validating configs kept in git.
+pub fn config_schema() -> String {
+    let schema = schemars::schema_for!(ConfigFile);
+    serde_json::to_string_pretty(&schema).unwrap_or_default()
+}
+
+#[command]
+pub async fn export_config_file(config: VpnConfig, metadata: Option<ConfigMetadata>) -> Result<String, String> {
+    serde_json::to_string_pretty(&to_config_file(config, metadata))
+        .map_err(|e| format!("Failed to serialize configuration: {}", e))
+}
+
+#[command]
+pub async fn import_config_file(content: String) -> Result<VpnConfig, String> {
+    Ok(parse_config_file(&content)?.config)
+}
+
+#[command]
+pub async fn get_config_schema() -> Result<String, String> {
+    Ok(config_schema())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bare_config_migrates_to_v1() {
+        let file = parse_config_file(
+            r#"{ "companyPrefix": "Contoso", "vpnServerAddress": "vpn.contoso.com", "deviceTunnelAlwaysOn": false, "ekuName": " ", "ekuOid": "1.3.6.1.5.5.7.3.2" }"#,
+        )
+        .unwrap();
+        assert_eq!(file.schema_version, 1);
+        assert_eq!(file.config.company_prefix, "Contoso");
+        assert_eq!(file.config.vpn_server_address, "vpn.contoso.com");
+        assert!(!file.config.device_tunnel_always_on);
+        assert_eq!(file.config.eku_name, None);
+        assert_eq!(file.config.eku_oid.as_deref(), Some("1.3.6.1.5.5.7.3.2"));
+
+        let migrated = migrate_value(json!({ "companyPrefix": "Contoso" })).unwrap();
+        assert_eq!(migrated["schemaVersion"], 1);
+        assert_eq!(migrated["config"]["deviceTunnelAlwaysOn"], true);
+        assert_eq!(migrated["config"]["internalPingTarget"], "");
+        // Fields the file did not set stay unset, so layers can still tell them apart.
+        assert!(migrated["config"].get("userTunnelProtocol").is_none());
+    }
+
+    #[test]
+    fn newer_or_invalid_versions_are_rejected() {
+        let newer = json!({ "schemaVersion": CURRENT_SCHEMA_VERSION + 1, "config": {} });
+        assert!(migrate_value(newer).unwrap_err().contains("newer than this app supports"));
+        assert!(migrate_value(json!({ "schemaVersion": "1", "config": {} })).is_err());
+        assert!(parse_config_file("[1, 2]").is_err());
+        assert!(parse_config_file("not json").unwrap_err().starts_with("Invalid JSON"));
+    }
+
+    #[test]
+    fn export_and_import_round_trip() {
+        let config = VpnConfig {
+            company_prefix: "Contoso".into(),
+            user_routes: vec!["10.0.0.0/8".into()],
+            eku_name: Some("Client Authentication".into()),
+            force_tunneling: true,
+            ..VpnConfig::default()
+        };
+        let metadata = ConfigMetadata { name: "Branch".into(), author: "it".into(), ..ConfigMetadata::default() };
+        let exported = serde_json::to_string_pretty(&to_config_file(config.clone(), Some(metadata))).unwrap();
+        let file = parse_config_file(&exported).unwrap();
+
+        assert_eq!(file.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(file.metadata.name, "Branch");
+        assert_eq!(file.metadata.author, "it");
+        assert!(!file.metadata.created.is_empty());
+        assert_eq!(file.metadata.app_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(serde_json::to_value(&file.config).unwrap(), serde_json::to_value(&config).unwrap());
+    }
+
+    #[test]
+    fn schema_describes_the_config_fields() {
+        let schema: Value = serde_json::from_str(&config_schema()).unwrap();
+        assert_eq!(schema["required"], json!(["config", "schemaVersion"]));
+        let config_fields = &schema["definitions"]["VpnConfig"]["properties"];
+        for field in ["companyPrefix", "vpnServerAddress", "userRoutes", "autoConnectTriggers", "mtuReferenceHost"] {
+            assert!(config_fields.get(field).is_some(), "{} missing from the schema", field);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use crate::config::VpnConfig;
+use crate::config_file::migrate_value;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::path::Path;
+use tauri::command;
+
+/// Source name reported for fields no layer sets.
+const DEFAULT_SOURCE: &str = "default";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    pub fn from_path(path: &str) -> Result<ConfigFormat, String> {
+        let ext = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        match ext.as_str() {
+            "json" => Ok(ConfigFormat::Json),
+            "toml" => Ok(ConfigFormat::Toml),
+            "yaml" | "yml" => Ok(ConfigFormat::Yaml),
+            _ => Err(format!("Unsupported config format for '{}'. Use .json, .toml, .yaml or .yml.", path)),
+        }
+    }
+}
+
+fn migrated_config(value: Value) -> Result<Map<String, Value>, String> {
+    match migrate_value(value)?.get_mut("config").map(Value::take) {
+        Some(Value::Object(map)) => Ok(map),
+        _ => Err("Versioned config file has no 'config' object.".to_string()),
+    }
+}
+
+/// Parses one layer into the current `VpnConfig` JSON shape (camelCase keys). A layer may be a
+/// complete versioned config file or a bare object, which is schema version 0. Each layer is
+/// migrated on its own; for bare objects only the fields the layer sets are kept, so the
+/// defaults a migration fills in never override an earlier layer.
+pub fn parse_layer(content: &str, format: ConfigFormat) -> Result<Map<String, Value>, String> {
+    let value: Value = match format {
+        ConfigFormat::Json => serde_json::from_str(content).map_err(|e| format!("Invalid JSON: {}", e))?,
+        ConfigFormat::Toml => toml::from_str(content).map_err(|e| format!("Invalid TOML: {}", e))?,
+        ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|e| format!("Invalid YAML: {}", e))?,
+    };
+    if value.get("schemaVersion").is_some() {
+        return migrated_config(value);
+    }
+    match value {
+        Value::Object(map) => {
+            let set: Vec<String> = map.keys().cloned().collect();
+            let mut migrated = migrated_config(Value::Object(map))?;
+            migrated.retain(|key, _| set.contains(key));
+            Ok(migrated)
+        }
+        // An empty YAML document is a valid, empty overlay.
+        Value::Null => Ok(Map::new()),
+        _ => Err("A config layer must be a table/object of VpnConfig fields.".to_string()),
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldSource {
+    pub field: String,
+    pub value: Value,
+    pub source: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayeredConfig {
+    pub config: VpnConfig,
+    pub sources: Vec<FieldSource>,
+    pub warnings: Vec<String>,
+}
+
+/// Merges `(name, layer)` pairs in order; later layers win. Fields are replaced as a
+/// whole, so a site overlay's `userRoutes` replaces the base list instead of extending it.
+/// Layers must already be migrated by `parse_layer`.
+pub fn merge_layers(layers: &[(String, Map<String, Value>)]) -> Result<LayeredConfig, String> {
+    let known = match serde_json::to_value(VpnConfig::default()) {
+        Ok(Value::Object(map)) => map,
+        _ => return Err("Failed to enumerate VpnConfig fields.".to_string()),
+    };
+
+    let mut merged = Map::new();
+    let mut source_of: Map<String, Value> = Map::new();
+    let mut warnings = Vec::new();
+
+    for (name, layer) in layers {
+        for (key, value) in layer {
+            if !known.contains_key(key) {
+                warnings.push(format!("{}: unknown field '{}' ignored", name, key));
+                continue;
+            }
+            merged.insert(key.clone(), value.clone());
+            source_of.insert(key.clone(), Value::String(name.clone()));
+        }
+    }
+
+    let config: VpnConfig =
+        serde_json::from_value(Value::Object(merged)).map_err(|e| format!("Invalid merged configuration: {}", e))?;
+
+    let effective = match serde_json::to_value(&config) {
+        Ok(Value::Object(map)) => map,
+        _ => return Err("Failed to serialize merged configuration.".to_string()),
+    };
+    let sources = effective
+        .into_iter()
+        .map(|(field, value)| {
+            let source = source_of
+                .get(&field)
+                .and_then(Value::as_str)
+                .unwrap_or(DEFAULT_SOURCE)
+                .to_string();
+            FieldSource { field, value, source }
+        })
+        .collect();
+
+    Ok(LayeredConfig { config, sources, warnings })
+}
+
+/// Loads and merges config files in order: base, then site, then host overlays.
+pub fn load_layers(paths: &[String]) -> Result<LayeredConfig, String> {
+    if paths.is_empty() {
+        return Err("At least one config file is required.".to_string());
+    }
+    let mut layers = Vec::new();
+    for path in paths {
+        let format = ConfigFormat::from_path(path)?;
+        let content = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let layer = parse_layer(&content, format).map_err(|e| format!("{}: {}", path, e))?;
+        layers.push((path.clone(), layer));
+    }
+    merge_layers(&layers)
+}
+
+#[command]
+pub async fn load_layered_config(paths: Vec<String>) -> Result<VpnConfig, String> {
+    Ok(load_layers(&paths)?.config)
+}
+
+#[command]
+pub async fn explain_layered_config(paths: Vec<String>) -> Result<String, String> {
+    let layered = load_layers(&paths)?;
+    serde_json::to_string_pretty(&layered).map_err(|e| format!("Failed to serialize merged configuration: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source<'a>(layered: &'a LayeredConfig, field: &str) -> &'a str {
+        &layered.sources.iter().find(|s| s.field == field).unwrap().source
+    }
+
+    #[test]
+    fn every_format_parses_to_the_same_layer() {
+        let json = parse_layer(r#"{ "companyPrefix": "Contoso", "userRoutes": ["10.0.0.0/8"], "forceTunneling": true }"#, ConfigFormat::Json).unwrap();
+        let toml = parse_layer("companyPrefix = \"Contoso\"\nuserRoutes = [\"10.0.0.0/8\"]\nforceTunneling = true\n", ConfigFormat::Toml).unwrap();
+        let yaml = parse_layer("companyPrefix: Contoso\nuserRoutes:\n  - 10.0.0.0/8\nforceTunneling: true\n", ConfigFormat::Yaml).unwrap();
+        assert_eq!(json, toml);
+        assert_eq!(json, yaml);
+        assert_eq!(json.len(), 3);
+
+        assert!(parse_layer("", ConfigFormat::Yaml).unwrap().is_empty());
+        assert!(parse_layer("- a\n- b\n", ConfigFormat::Yaml).is_err());
+        assert!(parse_layer("companyPrefix = ", ConfigFormat::Toml).unwrap_err().starts_with("Invalid TOML"));
+        assert_eq!(ConfigFormat::from_path("site.YML"), Ok(ConfigFormat::Yaml));
+        assert!(ConfigFormat::from_path("site.ini").is_err());
+    }
+
+    #[test]
+    fn later_layers_override_only_the_keys_they_set() {
+        let base = parse_layer(
+            r#"{ "companyPrefix": "Contoso", "vpnServerAddress": "vpn.contoso.com", "userRoutes": ["10.0.0.0/8", "172.16.0.0/12"] }"#,
+            ConfigFormat::Json,
+        )
+        .unwrap();
+        let site = parse_layer("vpnServerAddress: vpn-eu.contoso.com\nuserRoutes: [192.168.10.0/24]\nbogus: 1\n", ConfigFormat::Yaml).unwrap();
+        let layered = merge_layers(&[("base.json".into(), base), ("site.yaml".into(), site)]).unwrap();
+
+        assert_eq!(layered.config.company_prefix, "Contoso");
+        assert_eq!(layered.config.vpn_server_address, "vpn-eu.contoso.com");
+        assert_eq!(layered.config.user_routes, ["192.168.10.0/24"]);
+        assert_eq!(layered.warnings, ["site.yaml: unknown field 'bogus' ignored"]);
+
+        assert_eq!(source(&layered, "companyPrefix"), "base.json");
+        assert_eq!(source(&layered, "vpnServerAddress"), "site.yaml");
+        assert_eq!(source(&layered, "userRoutes"), "site.yaml");
+        assert_eq!(source(&layered, "userTunnelProtocol"), DEFAULT_SOURCE);
+        assert_eq!(layered.sources.len(), serde_json::to_value(VpnConfig::default()).unwrap().as_object().unwrap().len());
+    }
+
+    #[test]
+    fn each_layer_is_migrated_on_its_own() {
+        // A bare (v0) overlay must not reset fields the migration fills in for it.
+        let base = parse_layer(
+            r#"{ "schemaVersion": 1, "config": { "companyPrefix": "Contoso", "deviceTunnelAlwaysOn": false } }"#,
+            ConfigFormat::Json,
+        )
+        .unwrap();
+        let overlay = parse_layer("ekuName = \"\"\nuserTunnelProtocol = \"IKEv2\"\n", ConfigFormat::Toml).unwrap();
+        assert_eq!(overlay.get("ekuName"), Some(&Value::Null));
+        assert!(!overlay.contains_key("deviceTunnelAlwaysOn"));
+        let layered = merge_layers(&[("base.json".into(), base), ("host.toml".into(), overlay)]).unwrap();
+        assert!(!layered.config.device_tunnel_always_on);
+        assert_eq!(source(&layered, "deviceTunnelAlwaysOn"), "base.json");
+        assert_eq!(layered.config.user_tunnel_protocol, "IKEv2");
+
+        // A v0 base followed by a v1 overlay.
+        let base = parse_layer(r#"{ "companyPrefix": "Contoso", "deviceTunnelAlwaysOn": false }"#, ConfigFormat::Json).unwrap();
+        let overlay =
+            parse_layer("schemaVersion = 1\n[config]\nvpnServerAddress = \"vpn.contoso.com\"\n", ConfigFormat::Toml).unwrap();
+        let layered = merge_layers(&[("base.json".into(), base), ("site.toml".into(), overlay)]).unwrap();
+        assert_eq!(layered.config.company_prefix, "Contoso");
+        assert!(!layered.config.device_tunnel_always_on);
+        assert_eq!(layered.config.vpn_server_address, "vpn.contoso.com");
+
+        let newer = r#"{ "schemaVersion": 99, "config": {} }"#;
+        assert!(parse_layer(newer, ConfigFormat::Json).is_err());
+    }
+}
@@ -0,0 +1,834 @@
+use crate::audit::{windows_machine, windows_user};
+use crate::backups::TunnelKind;
+use crate::config::VpnConfig;
+use crate::config_layers::load_layers;
+use crate::net_checks::{self, DnsCheck, PortCheck, PortState};
+use crate::profiles::{resolve_config, ProfileStore};
+use crate::sstp_probe::SstpStatus;
+use crate::verification::{expected_routes, CheckStatus};
+use crate::vpn_deploy::check_export_path;
+use crate::{jobs, vpn_commands};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+use std::time::Duration;
+#[cfg(windows)]
+use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+use tauri::{command, AppHandle};
+
+/// Certificates expiring within this many days are a warning.
+const EXPIRY_WARNING_DAYS: i64 = 30;
+const PROBE_TIMEOUT: Duration = Duration::from_millis(2000);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Finding {
+    pub status: CheckStatus,
+    pub title: String,
+    pub detail: String,
+    pub remediation: Option<String>,
+}
+
+fn finding(status: CheckStatus, title: impl Into<String>, detail: impl Into<String>, remediation: Option<&str>) -> Finding {
+    Finding { status, title: title.into(), detail: detail.into(), remediation: remediation.map(str::to_string) }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportSection {
+    pub title: &'static str,
+    /// Command whose output the section evaluates.
+    pub command: &'static str,
+    /// What the check looks at, shown above the findings.
+    pub explanation: &'static str,
+    pub status: CheckStatus,
+    pub findings: Vec<Finding>,
+    /// Output of the command, pretty-printed when it is JSON.
+    pub raw: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsReport {
+    /// RFC 3339, local time.
+    pub generated: String,
+    pub app_version: String,
+    pub machine: String,
+    pub user: String,
+    pub server: String,
+    pub status: CheckStatus,
+    pub sections: Vec<ReportSection>,
+}
+
+fn rank(status: CheckStatus) -> u8 {
+    match status {
+        CheckStatus::Skipped => 0,
+        CheckStatus::Pass => 1,
+        CheckStatus::Warning => 2,
+        CheckStatus::Fail => 3,
+    }
+}
+
+/// Worst status of the list; `Skipped` only if nothing else was checked.
+pub fn worst(statuses: impl IntoIterator<Item = CheckStatus>) -> CheckStatus {
+    statuses.into_iter().max_by_key(|s| rank(*s)).unwrap_or(CheckStatus::Skipped)
+}
+
+fn section(title: &'static str, command: &'static str, explanation: &'static str, findings: Vec<Finding>, raw: String) -> ReportSection {
+    ReportSection { title, command, explanation, status: worst(findings.iter().map(|f| f.status)), findings, raw }
+}
+
+fn pretty(text: &str) -> String {
+    serde_json::from_str::<Value>(text)
+        .ok()
+        .and_then(|v| serde_json::to_string_pretty(&v).ok())
+        .unwrap_or_else(|| text.trim().to_string())
+}
+
+/// `ConvertTo-Json` writes a single object instead of a one-element array.
+fn json_list(text: &str) -> Vec<Value> {
+    match serde_json::from_str::<Value>(text) {
+        Ok(Value::Array(items)) => items,
+        Ok(Value::Object(map)) => vec![Value::Object(map)],
+        _ => Vec::new(),
+    }
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(Value::as_str).unwrap_or_default()
+}
+
+/// Names of the connections `get_vpn_status` reports as connected.
+pub fn connected_names(status_json: &str) -> Vec<String> {
+    json_list(status_json)
+        .iter()
+        .filter(|c| str_field(c, "ConnectionStatus").eq_ignore_ascii_case("Connected"))
+        .map(|c| str_field(c, "Name").to_string())
+        .collect()
+}
+
+pub fn evaluate_status(config: &VpnConfig, status_json: &str) -> Vec<Finding> {
+    let connections = json_list(status_json);
+    let state = |name: &str| {
+        connections
+            .iter()
+            .find(|c| str_field(c, "Name").eq_ignore_ascii_case(name))
+            .map(|c| str_field(c, "ConnectionStatus").to_string())
+    };
+    let names = config.tunnel_names();
+    let tunnels = [
+        ("Device Tunnel", vec![names.device]),
+        ("User Tunnel", vec![names.user, names.user_all]),
+    ];
+    tunnels
+        .into_iter()
+        .map(|(label, candidates)| {
+            let found = candidates.iter().find_map(|name| state(name).map(|s| (name.clone(), s)));
+            match found {
+                None => finding(
+                    CheckStatus::Fail,
+                    label,
+                    format!("'{}' is not deployed on this machine.", candidates[0]),
+                    Some("Deploy the tunnel from the Client tab, or check that the profile prefix and naming templates match the deployment."),
+                ),
+                Some((name, s)) if s.eq_ignore_ascii_case("Connected") => {
+                    finding(CheckStatus::Pass, label, format!("'{}' is connected.", name), None)
+                }
+                Some((name, s)) => finding(
+                    CheckStatus::Warning,
+                    label,
+                    format!("'{}' is deployed but {}.", name, if s.is_empty() { "not connected" } else { s.as_str() }),
+                    Some("Expected on the corporate network (trusted network detection). Otherwise analyze the RasClient event log for the error code."),
+                ),
+            }
+        })
+        .collect()
+}
+
+/// `(Expires: yyyy-MM-dd)` of every certificate line written by `check_certificates`.
+fn expiry_dates(text: &str) -> Vec<(String, Option<chrono::NaiveDate>)> {
+    text.split("\n  * ")
+        .map(|line| {
+            let date = line
+                .split("(Expires: ")
+                .nth(1)
+                .and_then(|rest| rest.split(')').next())
+                .and_then(|d| chrono::NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d").ok());
+            let name = line.split(" [Issuer:").next().unwrap_or(line).trim().to_string();
+            (name, date)
+        })
+        .collect()
+}
+
+fn evaluate_cert_store(label: &str, text: &str, today: chrono::NaiveDate, missing_fix: &str) -> Finding {
+    if text.trim().is_empty() || text.trim() == "Missing" {
+        return finding(CheckStatus::Fail, label, "No certificate with Client Authentication found.", Some(missing_fix));
+    }
+    let certs = expiry_dates(text);
+    let days = |date: &Option<chrono::NaiveDate>| date.map(|d| (d - today).num_days());
+    if certs.iter().all(|(_, d)| days(d).is_some_and(|n| n < 0)) {
+        let names = certs.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>().join(", ");
+        return finding(CheckStatus::Fail, label, format!("All certificates have expired: {}.", names), Some(missing_fix));
+    }
+    if let Some((name, date)) = certs.iter().find(|(_, d)| days(d).is_some_and(|n| (0..EXPIRY_WARNING_DAYS).contains(&n))) {
+        return finding(
+            CheckStatus::Warning,
+            label,
+            format!("{} expires on {}.", name, date.map(|d| d.to_string()).unwrap_or_default()),
+            Some("Check that autoenrollment renews the certificate (certutil -pulse) before it expires."),
+        );
+    }
+    finding(CheckStatus::Pass, label, format!("{} valid certificate(s).", certs.len()), None)
+}
+
+pub fn evaluate_certificates(config: &VpnConfig, certs_json: &str, today: chrono::NaiveDate) -> Vec<Finding> {
+    let value: Value = serde_json::from_str(certs_json).unwrap_or(Value::Null);
+    let root = str_field(&value, "Root CA");
+    let root = if config.root_ca_hash.trim().is_empty() {
+        finding(CheckStatus::Skipped, "Root CA", "No Root CA hash configured.", None)
+    } else if root.starts_with("Installed") {
+        finding(CheckStatus::Pass, "Root CA", root, None)
+    } else {
+        finding(
+            CheckStatus::Fail,
+            "Root CA",
+            format!("No certificate with thumbprint {} in the machine Root or CA store.", config.root_ca_hash.trim()),
+            Some("Deploy the Root CA via GPO (Computer Configuration > Public Key Policies > Trusted Root Certification Authorities) and check the configured hash."),
+        )
+    };
+    vec![
+        root,
+        evaluate_cert_store(
+            "Machine certificate",
+            str_field(&value, "Machine Certs"),
+            today,
+            "The Device Tunnel fails with error 13806. Enroll the computer certificate template (certutil -pulse as SYSTEM) and check its autoenrollment GPO.",
+        ),
+        evaluate_cert_store(
+            "User certificate",
+            str_field(&value, "User Certs"),
+            today,
+            "The User Tunnel fails with error 798. Enroll the user certificate template and check the EKU filter of the profile.",
+        ),
+    ]
+}
+
+pub fn evaluate_routes(config: &VpnConfig, routes_json: &str, any_connected: bool) -> Vec<Finding> {
+    let mut expected = expected_routes(config, TunnelKind::Device);
+    expected.extend(expected_routes(config, TunnelKind::User));
+    expected.sort();
+    expected.dedup();
+    if config.force_tunneling && expected.is_empty() {
+        return vec![finding(CheckStatus::Skipped, "Routes", "Force tunneling sends all traffic through the tunnel; no routes configured.", None)];
+    }
+    if expected.is_empty() {
+        return vec![finding(CheckStatus::Skipped, "Routes", "No routes configured.", None)];
+    }
+    if !any_connected {
+        return vec![finding(CheckStatus::Skipped, "Routes", "No tunnel is connected; VPN routes only exist while connected.", None)];
+    }
+    let present: Vec<String> = json_list(routes_json).iter().map(|r| str_field(r, "DestinationPrefix").to_string()).collect();
+    let missing: Vec<&String> = expected.iter().filter(|r| !present.contains(r)).collect();
+    if missing.is_empty() {
+        vec![finding(CheckStatus::Pass, "Routes", format!("All {} configured routes are present.", expected.len()), None)]
+    } else {
+        vec![finding(
+            CheckStatus::Fail,
+            "Routes",
+            format!("Missing while connected: {}.", missing.iter().map(|r| r.as_str()).collect::<Vec<_>>().join(", ")),
+            Some("Run the drift check; redeploy the tunnel if the deployed profile lacks the routes."),
+        )]
+    }
+}
+
+pub fn evaluate_dns(check: &DnsCheck) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for answer in &check.answers {
+        let external = answer.resolver == "system" || answer.resolver == "os";
+        let addresses = answer.addresses();
+        let title = format!("Resolver {}", answer.resolver);
+        findings.push(match (&answer.error, addresses.is_empty()) {
+            (None, false) => finding(
+                CheckStatus::Pass,
+                title,
+                addresses.into_iter().collect::<Vec<_>>().join(", "),
+                None,
+            ),
+            (error, _) if external => finding(
+                CheckStatus::Fail,
+                title,
+                error.clone().unwrap_or_else(|| format!("No address for {}.", check.name)),
+                Some("Clients fail with error 868. Check the public DNS record of the VPN server."),
+            ),
+            (error, _) => finding(
+                CheckStatus::Warning,
+                title,
+                error.clone().unwrap_or_else(|| format!("No address for {}.", check.name)),
+                Some("Internal DNS servers are usually only reachable through the tunnel; repeat the check while connected."),
+            ),
+        });
+    }
+    if check.split_brain {
+        findings.push(finding(
+            CheckStatus::Warning,
+            "Split-brain DNS",
+            check.summary.join(" "),
+            Some("Internal clients resolve the VPN server to a different address. Make sure that address also accepts SSTP/IKEv2, or exclude the name via NRPT."),
+        ));
+    }
+    findings
+}
+
+pub fn evaluate_ports(check: &PortCheck) -> Vec<Finding> {
+    let required = [(443, "tcp", "SSTP (TCP 443)"), (500, "udp", "IKEv2 (UDP 500)"), (4500, "udp", "IKEv2 NAT-T (UDP 4500)")];
+    let mut findings: Vec<Finding> = required
+        .iter()
+        .filter_map(|(port, protocol, label)| {
+            let result = check.ports.iter().find(|p| p.port == *port && p.protocol == *protocol)?;
+            Some(if result.state == PortState::Open {
+                let latency = result.latency_ms.map(|ms| format!(" ({} ms)", ms)).unwrap_or_default();
+                finding(CheckStatus::Pass, *label, format!("{}{}", result.detail, latency), None)
+            } else if *protocol == "tcp" {
+                finding(
+                    CheckStatus::Fail,
+                    *label,
+                    result.detail.clone(),
+                    Some("SSTP cannot connect. Check the firewall and the RRAS HTTPS binding."),
+                )
+            } else {
+                finding(
+                    CheckStatus::Fail,
+                    *label,
+                    result.detail.clone(),
+                    Some("IKEv2 fails with error 809. Allow UDP 500 and 4500 to the VPN server, or use SSTP for the User Tunnel."),
+                )
+            })
+        })
+        .collect();
+    if let Some(sstp) = &check.sstp {
+        findings.push(if sstp.status == SstpStatus::Ok {
+            finding(CheckStatus::Pass, "SSTP endpoint", sstp.summary.clone(), None)
+        } else {
+            finding(
+                CheckStatus::Fail,
+                "SSTP endpoint",
+                sstp.summary.clone(),
+                Some("Check the certificate bound to RRAS and that SSTP is enabled on the server."),
+            )
+        });
+        for warning in &sstp.warnings {
+            findings.push(finding(
+                CheckStatus::Warning,
+                "SSTP certificate",
+                warning.clone(),
+                Some("Reissue the server certificate with the VPN server name and a trusted chain before it expires."),
+            ));
+        }
+    }
+    findings
+}
+
+fn failed(title: &str, error: &str) -> Vec<Finding> {
+    vec![finding(CheckStatus::Fail, title, error.trim(), Some("The check itself failed; run it from the app to see the full output."))]
+}
+
+/// Runs the diagnostics and evaluates them. Each check's failure only affects its own section.
+pub async fn build_report(config: &VpnConfig) -> DiagnosticsReport {
+    let server = config.vpn_server_address.trim().to_string();
+    let mut sections = Vec::new();
+
+    jobs::progress("Reading VPN status");
+    let status = vpn_commands::get_vpn_status().await;
+    let connected = status.as_deref().map(connected_names).unwrap_or_default();
+    let explanation = "Deployed Device and User Tunnel connections and whether they are connected.";
+    sections.push(match &status {
+        Ok(json) => section("VPN Status", "get_vpn_status", explanation, evaluate_status(config, json), pretty(json)),
+        Err(e) => section("VPN Status", "get_vpn_status", explanation, failed("VPN status", e), e.clone()),
+    });
+
+    jobs::progress("Checking certificates");
+    let explanation = "Root CA in the machine store and client authentication certificates for the Device (machine) and User Tunnel.";
+    sections.push(match vpn_commands::check_certificates(&config.root_ca_hash).await {
+        Ok(json) => section(
+            "Certificates",
+            "check_certificates",
+            explanation,
+            evaluate_certificates(config, &json, chrono::Local::now().date_naive()),
+            pretty(&json),
+        ),
+        Err(e) => section("Certificates", "check_certificates", explanation, failed("Certificates", &e), e),
+    });
+
+    jobs::progress("Checking routes");
+    let explanation = "Configured Device and User Tunnel routes compared with the routing table while a tunnel is connected.";
+    sections.push(match vpn_commands::check_routes().await {
+        Ok(json) => section("Routes", "check_routes", explanation, evaluate_routes(config, &json, !connected.is_empty()), pretty(&json)),
+        Err(e) => section("Routes", "check_routes", explanation, failed("Routes", &e), e),
+    });
+
+    let explanation = "The VPN server name resolved by system DNS, the Windows resolver and each internal DNS server.";
+    let ports_explanation = "TCP 443 for SSTP, UDP 500/4500 for IKEv2, and the SSTP handshake on 443.";
+    if server.is_empty() {
+        let skipped = || vec![finding(CheckStatus::Skipped, "VPN server", "No VPN server address configured.", None)];
+        sections.push(section("DNS", "check_dns", explanation, skipped(), String::new()));
+        sections.push(section("Ports", "check_ports", ports_explanation, skipped(), String::new()));
+    } else {
+        jobs::progress("Checking DNS");
+        let internal: Vec<String> = config.dns_server_list().into_iter().map(str::to_string).collect();
+        let dns = net_checks::check_dns_native(&server, &internal, PROBE_TIMEOUT).await;
+        let raw = serde_json::to_string_pretty(&dns).unwrap_or_default();
+        sections.push(section("DNS", "check_dns", explanation, evaluate_dns(&dns), raw));
+
+        jobs::progress("Checking ports");
+        sections.push(match net_checks::check_ports_native(&server, PROBE_TIMEOUT).await {
+            Ok(ports) => {
+                let raw = serde_json::to_string_pretty(&ports).unwrap_or_default();
+                section("Ports", "check_ports", ports_explanation, evaluate_ports(&ports), raw)
+            }
+            Err(e) => section("Ports", "check_ports", ports_explanation, failed("Ports", &e), e),
+        });
+    }
+
+    DiagnosticsReport {
+        generated: chrono::Local::now().to_rfc3339(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        machine: windows_machine(),
+        user: windows_user(),
+        server,
+        status: worst(sections.iter().map(|s| s.status)),
+        sections,
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn badge(status: CheckStatus) -> String {
+    let (class, label) = match status {
+        CheckStatus::Pass => ("pass", "PASS"),
+        CheckStatus::Warning => ("warn", "WARN"),
+        CheckStatus::Fail => ("fail", "FAIL"),
+        CheckStatus::Skipped => ("skip", "SKIP"),
+    };
+    format!(r#"<span class="badge {}">{}</span>"#, class, label)
+}
+
+const STYLE: &str = r#"
+body { font-family: "Segoe UI", Arial, sans-serif; margin: 2rem auto; max-width: 960px; color: #1f2328; background: #fff; }
+h1 { font-size: 1.5rem; margin-bottom: 0.2rem; }
+h2 { font-size: 1.15rem; display: flex; gap: 0.6rem; align-items: center; margin-top: 2rem; border-bottom: 1px solid #d0d7de; padding-bottom: 0.3rem; }
+.meta { color: #59636e; font-size: 0.85rem; }
+.explanation { color: #59636e; font-size: 0.9rem; }
+table { width: 100%; border-collapse: collapse; font-size: 0.9rem; }
+td { border-top: 1px solid #eaeef2; padding: 0.45rem 0.4rem; vertical-align: top; }
+td.status { width: 4rem; }
+.remediation { color: #0969da; font-size: 0.85rem; margin-top: 0.2rem; }
+.badge { display: inline-block; min-width: 3rem; text-align: center; border-radius: 4px; padding: 0.1rem 0.4rem; font-size: 0.75rem; font-weight: 600; color: #fff; }
+.pass { background: #1a7f37; } .warn { background: #9a6700; } .fail { background: #cf222e; } .skip { background: #6e7781; }
+details { margin-top: 0.6rem; } summary { cursor: pointer; color: #59636e; font-size: 0.85rem; }
+pre { background: #f6f8fa; padding: 0.8rem; overflow-x: auto; font-size: 0.8rem; }
+"#;
+
+fn render_section(section: &ReportSection) -> String {
+    let rows: String = section
+        .findings
+        .iter()
+        .map(|f| {
+            let remediation = f
+                .remediation
+                .as_deref()
+                .map(|r| format!(r#"<div class="remediation">{}</div>"#, escape(r)))
+                .unwrap_or_default();
+            format!(
+                r#"<tr><td class="status">{badge}</td><td><strong>{title}</strong><div>{detail}</div>{remediation}</td></tr>"#,
+                badge = badge(f.status),
+                title = escape(&f.title),
+                detail = escape(&f.detail),
+                remediation = remediation,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let raw = if section.raw.trim().is_empty() {
+        String::new()
+    } else {
+        format!("<details><summary>Output of {}</summary><pre>{}</pre></details>", escape(section.command), escape(&section.raw))
+    };
+    format!(
+        r#"<section>
+<h2>{badge} {title}</h2>
+<p class="explanation">{explanation}</p>
+<table>
+{rows}
+</table>
+{raw}
+</section>"#,
+        badge = badge(section.status),
+        title = escape(section.title),
+        explanation = escape(section.explanation),
+        rows = rows,
+        raw = raw,
+    )
+}
+
+/// A single HTML file with inline CSS and no scripts or external resources, so it can be
+/// mailed as is.
+pub fn render_html(report: &DiagnosticsReport) -> String {
+    let sections: String = report.sections.iter().map(render_section).collect::<Vec<_>>().join("\n");
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>AOVPN Diagnostics - {machine}</title>
+<style>{style}</style>
+</head>
+<body>
+<h1>{badge} AOVPN Diagnostics</h1>
+<div class="meta">Machine {machine} &middot; User {user} &middot; VPN server {server} &middot; {generated} &middot; AOVPN Dashboard {version}</div>
+{sections}
+</body>
+</html>
+"#,
+        style = STYLE,
+        badge = badge(report.status),
+        machine = escape(&report.machine),
+        user = escape(&report.user),
+        server = escape(if report.server.is_empty() { "-" } else { &report.server }),
+        generated = escape(&report.generated),
+        version = escape(&report.app_version),
+        sections = sections,
+    )
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportSummary<'a> {
+    path: &'a str,
+    status: CheckStatus,
+    sections: Vec<(&'a str, CheckStatus)>,
+}
+
+fn write_report(report: &DiagnosticsReport, path: &str) -> Result<String, String> {
+    check_export_path(path, &[".html"])?;
+    std::fs::write(path, render_html(report)).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    let summary = ReportSummary { path, status: report.status, sections: report.sections.iter().map(|s| (s.title, s.status)).collect() };
+    serde_json::to_string(&summary).map_err(|e| format!("Failed to serialize report summary: {}", e))
+}
+
+/// Runs the diagnostics and writes the HTML report to `path`. Returns the status per section.
+#[command]
+pub async fn generate_diagnostics_report(
+    app: AppHandle,
+    path: String,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    if path.trim().is_empty() {
+        return Err("No path for the report given.".to_string());
+    }
+    let config = resolve_config(&app, config, profile_id)?;
+    let report = build_report(&config).await;
+    write_report(&report, &path)
+}
+
+const HEADLESS_USAGE: &str = "Usage: aovpn.exe --report <file.html> [--config <file>]...";
+
+/// Where `run_headless` writes its error: next to the report, `report.html` -> `report.error.txt`.
+/// None unless the `--report` value is an allowed report path, so a rejected path never leads
+/// to a file being removed or created next to it.
+fn error_file(args: &[String]) -> Option<std::path::PathBuf> {
+    let path = args.iter().skip_while(|a| *a != "--report").nth(1)?;
+    if path.starts_with("--") || check_export_path(path, &[".html"]).is_err() {
+        return None;
+    }
+    Some(Path::new(path).with_extension("error.txt"))
+}
+
+/// Report path and config files from the command line.
+fn parse_headless_args(args: &[String]) -> Result<(String, Vec<String>), String> {
+    let mut path = None;
+    let mut configs = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .filter(|v| !v.starts_with("--"))
+                .cloned()
+                .ok_or(format!("{} needs a file name.\n{}", arg, HEADLESS_USAGE))
+        };
+        match arg.as_str() {
+            "--report" => path = Some(value()?),
+            "--config" => configs.push(value()?),
+            other => return Err(format!("Unknown argument '{}'.\n{}", other, HEADLESS_USAGE)),
+        }
+    }
+    let path = path.ok_or(HEADLESS_USAGE.to_string())?;
+    check_export_path(&path, &[".html"])?;
+    Ok((path, configs))
+}
+
+/// `aovpn.exe --report <file.html> [--config <file>]...` writes the report without opening a
+/// window, for RMM tools. Config files are merged like layered configs; without any the selected
+/// profile is used. Exit code 0 when nothing failed, 1 when a check failed, 2 on usage, config or
+/// write errors. None when `--report` is absent.
+///
+/// Release builds have no console of their own, so errors go to the console the tool was started
+/// from, if any, and to `error_file` next to the report.
+pub fn run_headless(args: &[String]) -> Option<i32> {
+    if !args.iter().any(|a| a == "--report") {
+        return None;
+    }
+    // SAFETY: no preconditions; fails harmlessly when there is no parent console.
+    #[cfg(windows)]
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+
+    let error_path = error_file(args);
+    if let Some(error_path) = &error_path {
+        let _ = std::fs::remove_file(error_path);
+    }
+    let result = parse_headless_args(args).and_then(|(path, configs)| {
+        let config = if configs.is_empty() {
+            ProfileStore::open_without_app()?.selected_config()?
+        } else {
+            load_layers(&configs)?.config
+        };
+        let report = tauri::async_runtime::block_on(build_report(&config));
+        write_report(&report, &path).map(|_| report.status)
+    });
+    Some(match result {
+        Ok(CheckStatus::Fail) => 1,
+        Ok(_) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            if let Some(error_path) = &error_path {
+                if let Err(write_error) = std::fs::write(error_path, format!("{}\n", e)) {
+                    eprintln!("Failed to write {}: {}", error_path.display(), write_error);
+                }
+            }
+            2
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net_checks::{DnsAnswer, DnsRecord, PortResult};
+    use chrono::NaiveDate;
+
+    fn config() -> VpnConfig {
+        VpnConfig {
+            company_prefix: "Contoso".into(),
+            vpn_server_address: "vpn.contoso.com".into(),
+            root_ca_hash: "ABCD".into(),
+            device_routes: vec!["10.0.0.0/8".into()],
+            user_routes: vec!["172.16.0.0/12".into(), "10.0.0.0/8".into()],
+            ..VpnConfig::default()
+        }
+    }
+
+    fn statuses(findings: &[Finding]) -> Vec<CheckStatus> {
+        findings.iter().map(|f| f.status).collect()
+    }
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn worst_status_wins() {
+        use CheckStatus::*;
+        assert_eq!(worst([Pass, Skipped, Warning]), Warning);
+        assert_eq!(worst([Pass, Fail, Warning]), Fail);
+        assert_eq!(worst([Skipped, Pass]), Pass);
+        assert_eq!(worst([Skipped]), Skipped);
+        assert_eq!(worst([]), Skipped);
+    }
+
+    #[test]
+    fn tunnel_status_classification() {
+        let names = config().tunnel_names();
+        let json = format!(
+            r#"[{{ "Name": "{}", "ConnectionStatus": "Connected" }}, {{ "Name": "{}", "ConnectionStatus": "Disconnected" }}]"#,
+            names.device, names.user_all
+        );
+        assert_eq!(statuses(&evaluate_status(&config(), &json)), [CheckStatus::Pass, CheckStatus::Warning]);
+        assert_eq!(connected_names(&json), [names.device.as_str()]);
+
+        // A single connection comes as an object, not an array.
+        let single = format!(r#"{{ "Name": "{}", "ConnectionStatus": "Connected" }}"#, names.user);
+        assert_eq!(statuses(&evaluate_status(&config(), &single)), [CheckStatus::Fail, CheckStatus::Pass]);
+        assert_eq!(connected_names(&single), [names.user]);
+
+        assert_eq!(statuses(&evaluate_status(&config(), "")), [CheckStatus::Fail, CheckStatus::Fail]);
+    }
+
+    #[test]
+    fn certificate_expiry_window() {
+        let today = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let json = |machine: &str, user: &str| {
+            serde_json::json!({ "Root CA": "Installed (CN=Contoso Root)", "Machine Certs": machine, "User Certs": user }).to_string()
+        };
+        let cert = |name: &str, expires: &str| format!("CN={} [Issuer: Contoso CA] (Thumbprint: AA) (Expires: {})", name, expires);
+
+        let valid = cert("pc1", "2026-06-01");
+        let findings = evaluate_certificates(&config(), &json(&valid, &valid), today);
+        assert_eq!(statuses(&findings), [CheckStatus::Pass; 3]);
+
+        // Day 0 and day 29 are inside the window, day 30 is not.
+        for (expires, status) in [("2026-01-01", CheckStatus::Warning), ("2026-01-30", CheckStatus::Warning), ("2026-01-31", CheckStatus::Pass)] {
+            let findings = evaluate_certificates(&config(), &json(&cert("pc1", expires), &valid), today);
+            assert_eq!(findings[1].status, status, "{}", expires);
+        }
+
+        let mixed = format!("{}\n  * {}", cert("old", "2025-01-01"), cert("new", "2027-01-01"));
+        let expired = format!("{}\n  * {}", cert("old", "2025-01-01"), cert("older", "2024-01-01"));
+        let findings = evaluate_certificates(&config(), &json(&mixed, &expired), today);
+        assert_eq!(statuses(&findings), [CheckStatus::Pass, CheckStatus::Pass, CheckStatus::Fail]);
+        assert_eq!(findings[2].detail, "All certificates have expired: CN=old, CN=older.");
+
+        let findings = evaluate_certificates(&config(), &json("Missing", ""), today);
+        assert_eq!(statuses(&findings), [CheckStatus::Pass, CheckStatus::Fail, CheckStatus::Fail]);
+
+        let no_root = serde_json::json!({ "Root CA": "Missing", "Machine Certs": valid, "User Certs": valid }).to_string();
+        assert_eq!(evaluate_certificates(&config(), &no_root, today)[0].status, CheckStatus::Fail);
+        let unconfigured = VpnConfig { root_ca_hash: String::new(), ..config() };
+        assert_eq!(evaluate_certificates(&unconfigured, &no_root, today)[0].status, CheckStatus::Skipped);
+    }
+
+    #[test]
+    fn routes_are_deduplicated_and_only_checked_while_connected() {
+        let single = r#"{ "DestinationPrefix": "10.0.0.0/8", "InterfaceAlias": "Contoso User Tunnel" }"#;
+        let findings = evaluate_routes(&config(), single, true);
+        assert_eq!(statuses(&findings), [CheckStatus::Fail]);
+        assert_eq!(findings[0].detail, "Missing while connected: 172.16.0.0/12.");
+
+        let both = r#"[{ "DestinationPrefix": "172.16.0.0/12" }, { "DestinationPrefix": "10.0.0.0/8" }]"#;
+        let findings = evaluate_routes(&config(), both, true);
+        assert_eq!(statuses(&findings), [CheckStatus::Pass]);
+        assert_eq!(findings[0].detail, "All 2 configured routes are present.");
+
+        assert_eq!(statuses(&evaluate_routes(&config(), "[]", false)), [CheckStatus::Skipped]);
+        let no_routes = VpnConfig { device_routes: Vec::new(), user_routes: Vec::new(), force_tunneling: true, ..config() };
+        assert_eq!(statuses(&evaluate_routes(&no_routes, "[]", true)), [CheckStatus::Skipped]);
+    }
+
+    fn answer(resolver: &str, addresses: &[&str], error: Option<&str>) -> DnsAnswer {
+        DnsAnswer {
+            resolver: resolver.into(),
+            records: addresses.iter().map(|a| DnsRecord { record_type: "A", value: a.to_string(), ttl: None }).collect(),
+            latency_ms: None,
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn dns_classification() {
+        let check = DnsCheck {
+            name: "vpn.contoso.com".into(),
+            answers: vec![
+                answer("system", &["203.0.113.10"], None),
+                answer("os", &[], Some("timed out")),
+                answer("10.0.0.10", &[], Some("timed out")),
+                answer("10.0.0.11", &["10.0.0.5"], None),
+            ],
+            split_brain: true,
+            summary: vec!["Split-brain: system and 10.0.0.11 differ.".into()],
+        };
+        let findings = evaluate_dns(&check);
+        assert_eq!(
+            statuses(&findings),
+            [CheckStatus::Pass, CheckStatus::Fail, CheckStatus::Warning, CheckStatus::Pass, CheckStatus::Warning]
+        );
+        assert_eq!(findings[1].detail, "timed out");
+        assert_eq!(findings[4].title, "Split-brain DNS");
+    }
+
+    fn port(port: u16, protocol: &'static str, state: PortState) -> PortResult {
+        PortResult { port, protocol, service: "", state, latency_ms: Some(5), detail: format!("{:?}", state) }
+    }
+
+    #[test]
+    fn port_classification() {
+        let check = PortCheck {
+            server: "vpn.contoso.com".into(),
+            address: "203.0.113.10".into(),
+            ports: vec![
+                port(443, "tcp", PortState::Open),
+                port(500, "tcp", PortState::Closed),
+                port(500, "udp", PortState::Filtered),
+                port(4500, "udp", PortState::Open),
+            ],
+            sstp: None,
+        };
+        let findings = evaluate_ports(&check);
+        assert_eq!(statuses(&findings), [CheckStatus::Pass, CheckStatus::Fail, CheckStatus::Pass]);
+        assert_eq!(findings[0].detail, "Open (5 ms)");
+        assert!(findings[1].remediation.as_deref().unwrap().contains("error 809"));
+    }
+
+    #[test]
+    fn headless_arguments() {
+        let parsed = parse_headless_args(&args(&["--report", "out.HTML", "--config", "a.json", "--config", "b.yaml"])).unwrap();
+        assert_eq!(parsed, ("out.HTML".to_string(), args(&["a.json", "b.yaml"])));
+
+        assert!(parse_headless_args(&args(&["--report"])).unwrap_err().starts_with("--report needs a file name."));
+        assert!(parse_headless_args(&args(&["--report", "--config", "a.json"])).unwrap_err().starts_with("--report needs a file name."));
+        assert!(parse_headless_args(&args(&["--report", "out.html", "--config"])).unwrap_err().starts_with("--config needs a file name."));
+        assert!(parse_headless_args(&args(&["--report", "out.html", "--verbose"])).unwrap_err().starts_with("Unknown argument '--verbose'."));
+        assert_eq!(parse_headless_args(&args(&["--config", "a.json"])).unwrap_err(), HEADLESS_USAGE);
+        assert_eq!(
+            parse_headless_args(&args(&["--report", "C:\\Windows\\x.exe"])).unwrap_err(),
+            "Only .html files are allowed for export."
+        );
+        assert_eq!(
+            error_file(&args(&["--report", "C:\\Reports\\vpn.html", "--bogus"])).as_deref(),
+            Some(Path::new("C:\\Reports\\vpn.error.txt"))
+        );
+        assert_eq!(error_file(&args(&["--report", "C:\\Windows\\x.exe"])), None);
+        assert_eq!(error_file(&args(&["--report", "--config", "a.html"])), None);
+        assert_eq!(error_file(&args(&["--report"])), None);
+    }
+
+    #[test]
+    fn html_escapes_everything_from_the_machine() {
+        let report = DiagnosticsReport {
+            generated: "2026-01-01T00:00:00+01:00".into(),
+            app_version: "1.0.0".into(),
+            machine: "PC<1>".into(),
+            user: "O'Brien & Co".into(),
+            server: String::new(),
+            status: CheckStatus::Fail,
+            sections: vec![section(
+                "Routes",
+                "check_routes",
+                "Routes.",
+                vec![finding(CheckStatus::Fail, "<b>Routes</b>", "Missing \"<script>alert(1)</script>\"", Some("Fix & redeploy"))],
+                "<raw>".into(),
+            )],
+        };
+        let html = render_html(&report);
+        assert!(!html.contains("<script>"));
+        assert!(!html.contains("<b>"));
+        assert!(html.contains("Missing &quot;&lt;script&gt;alert(1)&lt;/script&gt;&quot;"));
+        assert!(html.contains("Machine PC&lt;1&gt; &middot; User O&#39;Brien &amp; Co &middot; VPN server -"));
+        assert!(html.contains("Fix &amp; redeploy"));
+        assert!(html.contains("<pre>&lt;raw&gt;</pre>"));
+        assert_eq!(html.matches(r#"<span class="badge fail">FAIL</span>"#).count(), 3);
+    }
+}
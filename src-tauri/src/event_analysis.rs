@@ -0,0 +1,549 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Providers whose events are kept, from the Application and System logs.
+pub const PROVIDERS: [&str; 3] = ["RasClient", "RasMan", "IKEEXT"];
+/// IKEEXT audit events in the Security log: main mode and quick mode negotiation failed.
+pub const SECURITY_EVENTS: [u32; 2] = [4653, 4654];
+/// RasClient: dial failed (error code) and connection terminated (reason code).
+const RAS_DIAL_FAILED: u32 = 20227;
+const RAS_DISCONNECTED: u32 = 20226;
+/// Reason code of a disconnect made on the client.
+const USER_DISCONNECT: u32 = 631;
+/// Named `<Data>` fields that carry a Win32 or HRESULT code.
+const CODE_FIELDS: [&str; 4] = ["ErrorCode", "Status", "FailureCode", "ReasonCode"];
+/// Phrases after which rendered messages print the code, English and German.
+const CODE_PHRASES: [&str; 4] = ["error code", "reason code", "fehlercode", "ursachencode"];
+
+/// What an error code means for an Always On VPN client and what to do about it.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorCodeInfo {
+    /// Decimal for Win32/RAS codes, hex for HRESULTs, as Windows prints them.
+    pub code: &'static str,
+    pub title: &'static str,
+    pub cause: &'static str,
+    pub fix: &'static str,
+    /// Command of this app that checks the likely cause.
+    pub check: Option<&'static str>,
+}
+
+const fn info(
+    code: &'static str,
+    title: &'static str,
+    cause: &'static str,
+    fix: &'static str,
+    check: Option<&'static str>,
+) -> ErrorCodeInfo {
+    ErrorCodeInfo { code, title, cause, fix, check }
+}
+
+pub const ERROR_CODES: [ErrorCodeInfo; 16] = [
+    info(
+        "631",
+        "Disconnected by the user",
+        "The connection was closed on the client, by the user or by Windows on shutdown or network change.",
+        "None. Only relevant if users report disconnects they did not make.",
+        None,
+    ),
+    info(
+        "691",
+        "Access denied",
+        "NPS rejected the credentials: the account is disabled, not in the VPN users group, or the certificate does not map to an account.",
+        "Look up the NPS event 6273 for this user; its reason code names the failing network policy condition.",
+        None,
+    ),
+    info(
+        "720",
+        "No IP address assigned",
+        "RRAS could not assign an address to the client: the static address pool is exhausted or DHCP is unavailable.",
+        "Enlarge the RRAS address pool or check the DHCP relay, then restart RemoteAccess.",
+        None,
+    ),
+    info(
+        "798",
+        "No certificate for EAP",
+        "No user certificate matches the EAP filter: it is missing, expired, lacks Client Authentication or the configured EKU, or is not issued by the Root CA in the profile.",
+        "Check autoenrollment of the user template (certutil -pulse), the EKU filter and the Root CA hash.",
+        Some("check_certificates"),
+    ),
+    info(
+        "800",
+        "VPN server unreachable",
+        "No tunnel type reached the server: the name does not resolve, or TCP 443 and UDP 500/4500 are blocked.",
+        "Run the port check and make sure the client network allows SSTP or IKEv2.",
+        Some("check_ports"),
+    ),
+    info(
+        "809",
+        "No response from the VPN server",
+        "IKEv2 packets do not arrive: UDP 500/4500 blocked, or a firewall drops the fragmented IKE_AUTH packets.",
+        "Run the MTU diagnostics; enable IKEv2 fragmentation on the server or fall back to the SSTP user tunnel.",
+        Some("diagnose_mtu"),
+    ),
+    info(
+        "812",
+        "Blocked by server policy",
+        "The NPS network policy does not allow this connection: wrong authentication method (EAP type), tunnel type, or the user or computer is not in the policy's group.",
+        "Compare the NPS network policy conditions and EAP settings with the profile; NPS event 6273 names the policy.",
+        None,
+    ),
+    info(
+        "853",
+        "NPS certificate not accepted",
+        "EAP could not validate the NPS server certificate: its name is not in the profile's ServerNames or its root is not the configured TrustedRootCA. Windows 11 enforces this.",
+        "Set eapServerNames to the name on the NPS certificate and check the Root CA hash, then redeploy.",
+        Some("check_certificates"),
+    ),
+    info(
+        "868",
+        "Server name does not resolve",
+        "The VPN server name could not be resolved by the client's DNS.",
+        "Run the DNS check against the public name; check the public DNS record and NRPT rules.",
+        Some("check_dns"),
+    ),
+    info(
+        "13801",
+        "IKE credentials unacceptable",
+        "The client does not accept the RRAS certificate: untrusted root, missing Server Authentication or IP security IKE intermediate EKU, or a name that does not match the server address. Also raised when RRAS restricts accepted root CAs.",
+        "Inspect the server certificate chain and names; check the Root CA on the client and on RRAS.",
+        Some("probe_sstp"),
+    ),
+    info(
+        "13805",
+        "IKE negotiation timed out",
+        "IKE_SA_INIT got through but the exchange stalled, typically because fragmented IKE_AUTH packets are dropped on the way.",
+        "Run the MTU diagnostics and enable IKEv2 fragmentation on RRAS and in the profile.",
+        Some("diagnose_mtu"),
+    ),
+    info(
+        "13806",
+        "No machine certificate for IKE",
+        "The Device Tunnel found no valid machine certificate: missing from LocalMachine\\My, no private key, no Client Authentication EKU, or not chained to the trusted root.",
+        "Check the machine certificate and its autoenrollment (certutil -pulse as SYSTEM), then reconnect.",
+        Some("check_certificates"),
+    ),
+    info(
+        "13868",
+        "IKE policy mismatch",
+        "Client and server have no IPsec proposal in common, for example the iOS/Mac preset applied to only one side.",
+        "Run the IKEv2 probe; apply the same cryptography to the profile and to RRAS.",
+        Some("probe_ike"),
+    ),
+    info(
+        "0x80092013",
+        "Revocation server offline",
+        "The client could not download the CRL of the server certificate; SSTP checks revocation before connecting.",
+        "Publish the CDP on a URL reachable from the internet (IIS). As a stopgap, disable the SSTP revocation check.",
+        Some("probe_sstp"),
+    ),
+    info(
+        "0x800B0109",
+        "Untrusted root",
+        "A certificate chain ends at a root CA the client does not trust.",
+        "Deploy the Root CA via GPO and check the Root CA hash in the configuration.",
+        Some("check_certificates"),
+    ),
+    info(
+        "0x800B010F",
+        "Certificate name mismatch",
+        "The server certificate does not contain the name the client connects to.",
+        "Reissue the server certificate with the VPN server FQDN as a SAN, or correct the server address.",
+        Some("probe_sstp"),
+    ),
+];
+
+/// Parses `809`, `0x80092013` and negative HRESULTs like `-2146885613`.
+pub fn parse_code(text: &str) -> Option<u32> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        return u32::from_str_radix(hex, 16).ok();
+    }
+    if text.is_empty() || !text.trim_start_matches('-').bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    match text.parse::<i64>().ok()? {
+        n if n < 0 => i32::try_from(n).ok().map(|n| n as u32),
+        n => u32::try_from(n).ok(),
+    }
+}
+
+pub fn format_code(code: u32) -> String {
+    if code > 0xFFFF {
+        format!("0x{:08X}", code)
+    } else {
+        code.to_string()
+    }
+}
+
+pub fn lookup(code: u32) -> Option<ErrorCodeInfo> {
+    ERROR_CODES.iter().find(|info| parse_code(info.code) == Some(code)).copied()
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventEntry {
+    /// `SystemTime` as written by Windows (UTC, ISO 8601).
+    pub time: String,
+    pub log: String,
+    pub provider: String,
+    pub event_id: u32,
+    pub level: String,
+    pub connection: Option<String>,
+    pub error_code: Option<String>,
+    pub message: Option<String>,
+    pub explanation: Option<ErrorCodeInfo>,
+}
+
+impl EventEntry {
+    /// Failed dials and IKE negotiations, unexpected disconnects, and errors or warnings that
+    /// carry a non-zero code.
+    pub fn is_failure(&self) -> bool {
+        let code = self.error_code.as_deref().and_then(parse_code).filter(|c| *c != 0);
+        match self.event_id {
+            RAS_DIAL_FAILED => true,
+            RAS_DISCONNECTED => code.is_some_and(|c| c != USER_DISCONNECT),
+            id if SECURITY_EVENTS.contains(&id) => true,
+            _ => code.is_some() && matches!(self.level.as_str(), "critical" | "error" | "warning"),
+        }
+    }
+}
+
+fn level_name(level: &str) -> &'static str {
+    match level {
+        "1" => "critical",
+        "2" => "error",
+        "3" => "warning",
+        "5" => "verbose",
+        _ => "information",
+    }
+}
+
+fn code_in_message(message: &str) -> Option<u32> {
+    let lower = message.to_lowercase();
+    CODE_PHRASES.iter().find_map(|phrase| {
+        let rest = &lower[lower.find(phrase)? + phrase.len()..];
+        // "... error code returned on failure is 809." / "... Fehlercode ist 809."
+        rest.split_whitespace()
+            .take(8)
+            .find_map(|word| parse_code(word.trim_matches(|c: char| !c.is_ascii_alphanumeric() && c != '-')))
+    })
+}
+
+fn is_relevant(provider: &str, event_id: u32) -> bool {
+    PROVIDERS.iter().any(|p| p.eq_ignore_ascii_case(provider))
+        || (provider == "Microsoft-Windows-Security-Auditing" && SECURITY_EVENTS.contains(&event_id))
+}
+
+fn parse_event(event: roxmltree::Node) -> Option<EventEntry> {
+    let system = event.children().find(|n| n.has_tag_name("System"))?;
+    let child = |name: &str| system.children().find(|n| n.has_tag_name(name));
+    let provider = child("Provider")?.attribute("Name")?.to_string();
+    let event_id: u32 = child("EventID")?.text()?.trim().parse().ok()?;
+    if !is_relevant(&provider, event_id) {
+        return None;
+    }
+
+    let data: Vec<(Option<&str>, &str)> = event
+        .descendants()
+        .find(|n| n.has_tag_name("EventData"))
+        .map(|d| {
+            d.children()
+                .filter(|n| n.has_tag_name("Data"))
+                .map(|n| (n.attribute("Name"), n.text().unwrap_or_default().trim()))
+                .collect()
+        })
+        .unwrap_or_default();
+    let message = event
+        .descendants()
+        .find(|n| n.has_tag_name("Message"))
+        .and_then(|n| n.text())
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty())
+        .or_else(|| {
+            data.iter()
+                .find(|(name, _)| *name == Some("FailureReason"))
+                .map(|(_, value)| value.to_string())
+        });
+
+    // RasClient writes unnamed data: CoId, user, connection name, then the code.
+    let ras = data.first().is_some_and(|(_, v)| v.starts_with("CoId="));
+    let connection = ras.then(|| data.get(2)).flatten().map(|(_, v)| v.to_string()).filter(|v| parse_code(v).is_none());
+    let code = data
+        .iter()
+        .filter(|(name, _)| name.is_some_and(|n| CODE_FIELDS.contains(&n)))
+        .find_map(|(_, v)| parse_code(v))
+        .or_else(|| if ras && data.len() > 3 { data.last().and_then(|(_, v)| parse_code(v)) } else { None })
+        .or_else(|| message.as_deref().and_then(code_in_message));
+
+    Some(EventEntry {
+        time: child("TimeCreated").and_then(|n| n.attribute("SystemTime")).unwrap_or_default().to_string(),
+        log: child("Channel").and_then(|n| n.text()).unwrap_or_default().to_string(),
+        provider,
+        event_id,
+        level: level_name(child("Level").and_then(|n| n.text()).unwrap_or_default().trim()).to_string(),
+        connection,
+        error_code: code.map(format_code),
+        message,
+        explanation: code.and_then(lookup),
+    })
+}
+
+/// Parses event XML as written by `wevtutil qe /f:RenderedXml /e:Events` or `Get-WinEvent ... ToXml()`.
+/// A bare sequence of `<Event>` elements is accepted too. Events of other providers are skipped.
+pub fn parse_events(xml: &str) -> Result<Vec<EventEntry>, String> {
+    let xml = xml.trim_start_matches('\u{feff}').trim();
+    let wrapped;
+    let xml = if xml.starts_with("<Event ") || xml.starts_with("<Event>") {
+        wrapped = format!("<Events>{}</Events>", xml);
+        wrapped.as_str()
+    } else {
+        xml
+    };
+    let doc = roxmltree::Document::parse(xml).map_err(|e| format!("Invalid event XML: {}", e))?;
+    Ok(doc.descendants().filter(|n| n.has_tag_name("Event")).filter_map(parse_event).collect())
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailureSummary {
+    pub error_code: String,
+    pub count: usize,
+    pub last_seen: String,
+    pub connections: Vec<String>,
+    pub explanation: Option<ErrorCodeInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventAnalysis {
+    /// Oldest first.
+    pub timeline: Vec<EventEntry>,
+    /// Most frequent first.
+    pub failures: Vec<FailureSummary>,
+    /// Commands that check the likely causes, in order of the failures.
+    pub suggested_checks: Vec<&'static str>,
+    /// Logs that could not be read.
+    pub warnings: Vec<String>,
+    pub summary: String,
+}
+
+pub fn analyze(mut events: Vec<EventEntry>, warnings: Vec<String>) -> EventAnalysis {
+    events.sort_by(|a, b| a.time.cmp(&b.time));
+
+    let mut by_code: BTreeMap<String, FailureSummary> = BTreeMap::new();
+    for event in events.iter().filter(|e| e.is_failure()) {
+        // Without a code, failures are grouped by event, e.g. IKEEXT audit events.
+        let key = event.error_code.clone().unwrap_or_else(|| format!("{} {}", event.provider, event.event_id));
+        let summary = by_code.entry(key.clone()).or_insert_with(|| FailureSummary {
+            error_code: key,
+            count: 0,
+            last_seen: String::new(),
+            connections: Vec::new(),
+            explanation: event.explanation,
+        });
+        summary.count += 1;
+        summary.last_seen = event.time.clone();
+        if let Some(name) = &event.connection {
+            if !summary.connections.contains(name) {
+                summary.connections.push(name.clone());
+            }
+        }
+    }
+    let mut failures: Vec<FailureSummary> = by_code.into_values().collect();
+    failures.sort_by(|a, b| b.count.cmp(&a.count).then(b.last_seen.cmp(&a.last_seen)));
+
+    let mut suggested_checks = Vec::new();
+    for check in failures.iter().filter_map(|f| f.explanation.and_then(|e| e.check)) {
+        if !suggested_checks.contains(&check) {
+            suggested_checks.push(check);
+        }
+    }
+
+    let summary = match failures.first() {
+        None => format!("{} VPN events, no failures.", events.len()),
+        Some(top) => format!(
+            "{} VPN events, {} failures. Most frequent: {} ({}) x{}.",
+            events.len(),
+            failures.iter().map(|f| f.count).sum::<usize>(),
+            top.error_code,
+            top.explanation.map(|e| e.title).unwrap_or("unknown code"),
+            top.count
+        ),
+    };
+    EventAnalysis { timeline: events, failures, suggested_checks, warnings, summary }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NS: &str = "http://schemas.microsoft.com/win/2004/08/events/event";
+
+    fn system(provider: &str, id: u32, level: u8, channel: &str, time: &str) -> String {
+        format!(
+            "<System><Provider Name='{}'/><EventID Qualifiers='0'>{}</EventID><Version>0</Version><Level>{}</Level><Task>0</Task><Opcode>0</Opcode><Keywords>0x80000000000000</Keywords><TimeCreated SystemTime='{}'/><EventRecordID>4711</EventRecordID><Correlation/><Execution ProcessID='0' ThreadID='0'/><Channel>{}</Channel><Computer>PC01.corp.example.com</Computer><Security/></System>",
+            provider, id, level, time, channel
+        )
+    }
+
+    /// RasClient event as rendered by `wevtutil qe /f:RenderedXml`: unnamed data, message in `culture`.
+    fn ras_client(id: u32, time: &str, data: &[&str], culture: &str, message: &str) -> String {
+        let data: String = data.iter().map(|d| format!("<Data>{}</Data>", d)).collect();
+        format!(
+            "<Event xmlns='{}'>{}<EventData>{}</EventData><RenderingInfo Culture='{}'><Message>{}</Message><Level>Error</Level><Provider>RasClient</Provider></RenderingInfo></Event>",
+            NS, system("RasClient", id, if id == RAS_DIAL_FAILED { 2 } else { 4 }, "Application", time), data, culture, message
+        )
+    }
+
+    const COID: &str = "CoId={E1F0A0C4-1111-2222-3333-444455556666}";
+
+    fn english_ras() -> String {
+        [
+            ras_client(20227, "2024-05-06T07:08:09.0000000Z", &[COID, "CORP\\jdoe", "Contoso User Tunnel", "809"], "en-US",
+                "CoId={E1F0A0C4-1111-2222-3333-444455556666}: The user CORP\\jdoe dialed a connection named Contoso User Tunnel which has failed. The error code returned on failure is 809."),
+            ras_client(20226, "2024-05-06T08:00:00.0000000Z", &[COID, "CORP\\jdoe", "Contoso User Tunnel", "631"], "en-US",
+                "CoId={E1F0A0C4-1111-2222-3333-444455556666}: The user CORP\\jdoe dialed a connection named Contoso User Tunnel which has terminated. The reason code returned on termination is 631."),
+        ]
+        .concat()
+    }
+
+    /// Older clients write the code only into the message; the German ones as `Fehlercode`/`Ursachencode`.
+    fn german_ras() -> String {
+        [
+            ras_client(20227, "2024-05-07T07:08:09.0000000Z", &[COID, "CORP\\jdoe", "Contoso Device Tunnel"], "de-DE",
+                "CoId={E1F0A0C4-1111-2222-3333-444455556666}: Der Benutzer \"CORP\\jdoe\" hat eine Verbindung mit dem Namen \"Contoso Device Tunnel\" gewählt, die nicht hergestellt werden konnte. Der bei dem Fehler zurückgegebene Fehlercode ist 13806."),
+            ras_client(20226, "2024-05-07T09:00:00.0000000Z", &[COID, "CORP\\jdoe", "Contoso User Tunnel"], "de-DE",
+                "CoId={E1F0A0C4-1111-2222-3333-444455556666}: Der Benutzer \"CORP\\jdoe\" hat die Verbindung \"Contoso User Tunnel\" getrennt. Der bei der Trennung zurückgegebene Ursachencode ist 829."),
+        ]
+        .concat()
+    }
+
+    fn ikeext() -> String {
+        format!(
+            "<Event xmlns='{}'>{}<EventData><Data Name='RemoteAddress'>203.0.113.10</Data><Data Name='Status'>-2146762487</Data></EventData></Event>",
+            NS, system("IKEEXT", 1, 2, "System", "2024-05-06T07:08:08.0000000Z")
+        )
+    }
+
+    fn security_4653(time: &str, failure_reason: &str, rendering: Option<(&str, &str)>) -> String {
+        let rendering = rendering
+            .map(|(culture, message)| format!("<RenderingInfo Culture='{}'><Message>{}</Message></RenderingInfo>", culture, message))
+            .unwrap_or_default();
+        format!(
+            "<Event xmlns='{}'>{}<EventData><Data Name='LocalMMPrincipalName'>-</Data><Data Name='RemoteMMPrincipalName'>-</Data><Data Name='LocalAddress'>192.168.1.20</Data><Data Name='RemoteAddress'>203.0.113.10</Data><Data Name='KeyModName'>IKEv2</Data><Data Name='FailurePoint'>Local computer</Data><Data Name='FailureReason'>{}</Data><Data Name='MMAuthMethod'>Unknown authentication</Data><Data Name='Role'>Initiator</Data></EventData>{}</Event>",
+            NS, system("Microsoft-Windows-Security-Auditing", 4653, 0, "Security", time), failure_reason, rendering
+        )
+    }
+
+    fn events(xml: &str) -> Vec<EventEntry> {
+        parse_events(&format!("<Events>{}</Events>", xml)).unwrap()
+    }
+
+    #[test]
+    fn english_ras_client_events() {
+        let parsed = events(&english_ras());
+        assert_eq!(parsed.len(), 2);
+        let dial = &parsed[0];
+        assert_eq!((dial.provider.as_str(), dial.event_id, dial.level.as_str(), dial.log.as_str()), ("RasClient", 20227, "error", "Application"));
+        assert_eq!(dial.time, "2024-05-06T07:08:09.0000000Z");
+        assert_eq!(dial.connection.as_deref(), Some("Contoso User Tunnel"));
+        assert_eq!(dial.error_code.as_deref(), Some("809"));
+        assert_eq!(dial.explanation.map(|e| e.check), Some(Some("diagnose_mtu")));
+        assert!(dial.message.as_deref().is_some_and(|m| m.ends_with("is 809.")));
+        assert!(dial.is_failure());
+
+        let disconnect = &parsed[1];
+        assert_eq!(disconnect.error_code.as_deref(), Some("631"));
+        assert!(!disconnect.is_failure());
+    }
+
+    #[test]
+    fn german_ras_client_events_take_the_code_from_the_message() {
+        let parsed = events(&german_ras());
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].connection.as_deref(), Some("Contoso Device Tunnel"));
+        assert_eq!(parsed[0].error_code.as_deref(), Some("13806"));
+        assert_eq!(parsed[0].explanation.map(|e| e.title), Some("No machine certificate for IKE"));
+        assert!(parsed[0].is_failure());
+
+        // A disconnect with a reason other than 631 was not made by the user.
+        assert_eq!(parsed[1].error_code.as_deref(), Some("829"));
+        assert!(parsed[1].explanation.is_none());
+        assert!(parsed[1].is_failure());
+    }
+
+    #[test]
+    fn ikeext_status_is_a_negative_hresult() {
+        let parsed = events(&ikeext());
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].connection, None);
+        assert_eq!(parsed[0].error_code.as_deref(), Some("0x800B0109"));
+        assert_eq!(parsed[0].explanation.map(|e| e.title), Some("Untrusted root"));
+        assert!(parsed[0].is_failure());
+    }
+
+    #[test]
+    fn security_4653_in_english_and_german() {
+        let english = security_4653(
+            "2024-05-06T07:08:10.0000000Z",
+            "%%13801",
+            Some(("en-US", "An IPsec main mode negotiation failed.\n\nFailure Information:\n\tFailure Point:\t\tLocal computer\n\tFailure Reason:\t\tIKE authentication credentials are unacceptable")),
+        );
+        // Get-WinEvent ... ToXml() has no RenderingInfo; the failure reason stands in for the message.
+        let german = security_4653("2024-05-07T07:08:10.0000000Z", "Die IKE-Authentifizierungsanmeldeinformationen sind unzulässig.", None);
+        let parsed = events(&(english + &german));
+        assert_eq!(parsed.len(), 2);
+        assert!(parsed.iter().all(|e| e.event_id == 4653 && e.log == "Security" && e.is_failure() && e.error_code.is_none()));
+        assert!(parsed[0].message.as_deref().is_some_and(|m| m.starts_with("An IPsec main mode negotiation failed.")));
+        assert_eq!(parsed[1].message.as_deref(), Some("Die IKE-Authentifizierungsanmeldeinformationen sind unzulässig."));
+    }
+
+    #[test]
+    fn other_providers_and_bare_events() {
+        let other = format!("<Event xmlns='{}'>{}</Event>", NS, system("Microsoft-Windows-Kernel-Power", 41, 1, "System", "2024-05-06T07:00:00.0000000Z"));
+        let xml = format!("\u{feff}{}{}", other, english_ras());
+        let parsed = parse_events(&xml).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert!(parse_events("<Events><Event>").is_err());
+    }
+
+    #[test]
+    fn analysis_groups_failures_and_suggests_checks() {
+        let xml = [english_ras(), german_ras(), ikeext(), security_4653("2024-05-06T07:08:10.0000000Z", "%%13801", None)].concat();
+        let analysis = analyze(events(&xml), Vec::new());
+        assert_eq!(analysis.timeline.len(), 6);
+        assert!(analysis.timeline.windows(2).all(|w| w[0].time <= w[1].time));
+        let codes: Vec<(&str, usize)> = analysis.failures.iter().map(|f| (f.error_code.as_str(), f.count)).collect();
+        assert_eq!(codes.len(), 5);
+        assert!(codes.contains(&("Microsoft-Windows-Security-Auditing 4653", 1)));
+        assert!(!codes.iter().any(|(code, _)| *code == "631"));
+        assert_eq!(analysis.suggested_checks, ["check_certificates", "diagnose_mtu"]);
+        assert!(analysis.summary.starts_with("6 VPN events, 5 failures."), "{}", analysis.summary);
+    }
+
+    #[test]
+    fn parse_code_accepts_decimal_hex_and_negative_hresults() {
+        assert_eq!(parse_code("809"), Some(809));
+        assert_eq!(parse_code(" 13806 "), Some(13806));
+        assert_eq!(parse_code("0x80092013"), Some(0x80092013));
+        assert_eq!(parse_code("0X800b0109"), Some(0x800B0109));
+        assert_eq!(parse_code("-2146885613"), Some(0x80092013));
+        assert_eq!(parse_code("-2146762487"), Some(0x800B0109));
+        assert_eq!(parse_code("-2147483648"), Some(0x80000000));
+        assert_eq!(parse_code("-1"), Some(0xFFFFFFFF));
+        assert_eq!(parse_code("4294967295"), Some(u32::MAX));
+        for invalid in ["", "-", "--5", "12a", "0x", "0xFFFFFFFFF", "-2147483649", "4294967296", "8.09"] {
+            assert_eq!(parse_code(invalid), None, "{}", invalid);
+        }
+        assert_eq!(parse_code("-2146885613").map(format_code).as_deref(), Some("0x80092013"));
+        assert_eq!(parse_code("-2146885613").and_then(lookup).map(|e| e.title), Some("Revocation server offline"));
+    }
+
+    #[test]
+    fn catalog_codes_are_written_as_format_code_prints_them() {
+        for info in ERROR_CODES {
+            assert_eq!(parse_code(info.code).map(format_code).as_deref(), Some(info.code));
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use crate::event_analysis::{analyze, format_code, lookup, parse_code, parse_events, EventEntry, PROVIDERS, SECURITY_EVENTS};
+use crate::jobs;
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+#[cfg(windows)]
+use std::process::Command;
+use tauri::command;
+
+const DEFAULT_HOURS: u32 = 24;
+const MAX_HOURS: u32 = 24 * 30;
+const DEFAULT_MAX_EVENTS: u32 = 200;
+
+#[cfg(windows)]
+fn wevtutil(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("wevtutil")
+        .args(args)
+        .creation_flags(0x08000000)
+        .output()
+        .map_err(|e| format!("wevtutil error: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        return Err(if stderr.trim().is_empty() { stdout } else { stderr }.trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Event logs are only readable on Windows; elsewhere every log ends up under `warnings`.
+#[cfg(not(windows))]
+fn wevtutil(_args: &[&str]) -> Result<String, String> {
+    Err("Reading event logs requires Windows.".to_string())
+}
+
+/// Reads RasClient, RasMan and IKEEXT events of the last `hours` from the local logs.
+pub fn collect_events(hours: u32, max_events: u32) -> Result<(Vec<EventEntry>, Vec<String>), String> {
+    let age = format!("TimeCreated[timediff(@SystemTime) <= {}]", u64::from(hours) * 3_600_000);
+    let providers = PROVIDERS.iter().map(|p| format!("Provider[@Name='{}']", p)).collect::<Vec<_>>().join(" or ");
+    let ids = SECURITY_EVENTS.iter().map(|id| format!("EventID={}", id)).collect::<Vec<_>>().join(" or ");
+    let queries = [
+        ("Application", format!("*[System[({}) and {}]]", providers, age)),
+        ("System", format!("*[System[({}) and {}]]", providers, age)),
+        ("Security", format!("*[System[({}) and {}]]", ids, age)),
+    ];
+
+    let mut events = Vec::new();
+    let mut warnings = Vec::new();
+    for (log, query) in queries {
+        jobs::check_cancelled()?;
+        jobs::progress(&format!("Reading the {} log", log));
+        let query = format!("/q:{}", query);
+        let count = format!("/c:{}", max_events);
+        match wevtutil(&["qe", log, &query, "/f:RenderedXml", "/e:Events", "/rd:true", &count]).and_then(|xml| parse_events(&xml)) {
+            Ok(found) => events.extend(found),
+            Err(e) => warnings.push(format!("{} log: {}", log, e)),
+        }
+    }
+    Ok((events, warnings))
+}
+
+/// Timeline of VPN-related events from the local event logs, with explained error codes.
+#[command]
+pub async fn analyze_vpn_events(hours: Option<u32>, max_events: Option<u32>) -> Result<String, String> {
+    let hours = hours.unwrap_or(DEFAULT_HOURS).clamp(1, MAX_HOURS);
+    let (events, warnings) = collect_events(hours, max_events.unwrap_or(DEFAULT_MAX_EVENTS).max(1))?;
+    serde_json::to_string(&analyze(events, warnings)).map_err(|e| format!("Failed to serialize event analysis: {}", e))
+}
+
+/// Same analysis for an exported log: rendered event XML, or an `.evtx` file which is
+/// converted with wevtutil first.
+#[command]
+pub async fn analyze_event_log_file(path: String) -> Result<String, String> {
+    let xml = if path.to_lowercase().ends_with(".evtx") {
+        wevtutil(&["qe", &path, "/lf:true", "/f:RenderedXml", "/e:Events"])?
+    } else {
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?
+    };
+    let events = parse_events(&xml)?;
+    serde_json::to_string(&analyze(events, Vec::new())).map_err(|e| format!("Failed to serialize event analysis: {}", e))
+}
+
+/// Explanation and likely fix for a RasClient/IKE error code (decimal or hex).
+#[command]
+pub fn explain_error_code(code: String) -> Result<String, String> {
+    let value = parse_code(&code).ok_or(format!("'{}' is not an error code.", code.trim()))?;
+    let info = lookup(value).ok_or(format!("Error code {} is not in the catalog.", format_code(value)))?;
+    serde_json::to_string(&info).map_err(|e| format!("Failed to serialize error code: {}", e))
+}
@@ -0,0 +1,240 @@
+use crate::config::{stable_uuid, VpnConfig};
+use crate::ipsec::IOS_MAC_PRESET;
+use crate::profiles::resolve_config;
+use std::net::Ipv4Addr;
+use tauri::{command, AppHandle};
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Reverse-DNS payload identifier from the server address (`vpn.contoso.com` -> `com.contoso.vpn.aovpn`).
+fn payload_identifier(config: &VpnConfig) -> String {
+    let reversed: Vec<&str> = config.vpn_server_address.trim().split('.').rev().collect();
+    format!("{}.aovpn", reversed.join("."))
+}
+
+fn prefix_to_mask(prefix: u8) -> String {
+    let bits: u32 = if prefix == 0 { 0 } else { u32::MAX << (32 - prefix.min(32) as u32) };
+    let o = bits.to_be_bytes();
+    format!("{}.{}.{}.{}", o[0], o[1], o[2], o[3])
+}
+
+fn string_array(indent: &str, values: &[&str]) -> String {
+    let mut out = format!("{indent}<array>\n");
+    for v in values {
+        out.push_str(&format!("{indent}  <string>{}</string>\n", xml_escape(v)));
+    }
+    out.push_str(&format!("{indent}</array>\n"));
+    out
+}
+
+fn sa_parameters(key: &str, lifetime_minutes: u32, dh_group: u32) -> String {
+    format!(
+        r#"        <key>{key}</key>
+        <dict>
+          <key>EncryptionAlgorithm</key>
+          <string>{enc}</string>
+          <key>IntegrityAlgorithm</key>
+          <string>{integ}</string>
+          <key>DiffieHellmanGroup</key>
+          <integer>{dh_group}</integer>
+          <key>LifeTimeInMinutes</key>
+          <integer>{lifetime_minutes}</integer>
+        </dict>
+"#,
+        key = key,
+        enc = IOS_MAC_PRESET.apple_encryption_algorithm(),
+        integ = IOS_MAC_PRESET.apple_integrity_algorithm(),
+        dh_group = dh_group,
+        lifetime_minutes = lifetime_minutes,
+    )
+}
+
+/// On-demand rules: stay disconnected on the trusted network, connect everywhere else.
+fn on_demand_rules(config: &VpnConfig) -> String {
+    let trusted: Vec<&str> = config
+        .trusted_network
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    let mut out = String::from("        <key>OnDemandRules</key>\n        <array>\n");
+    if !trusted.is_empty() {
+        out.push_str("          <dict>\n            <key>Action</key>\n            <string>Disconnect</string>\n            <key>DNSDomainMatch</key>\n");
+        out.push_str(&string_array("            ", &trusted));
+        out.push_str("          </dict>\n");
+    }
+    out.push_str("          <dict>\n            <key>Action</key>\n            <string>Connect</string>\n          </dict>\n");
+    out.push_str("        </array>\n");
+    out
+}
+
+/// IPv4 routes only: the profile's `IncludedRoutes` take a dotted subnet mask. IPv6 routes are
+/// left out with a comment, so the device keeps its own IPv6 routing for them.
+fn included_routes(config: &VpnConfig) -> String {
+    let mut routes = String::new();
+    let mut skipped = String::new();
+    for route in &config.user_routes {
+        if let Some((addr, prefix)) = route.trim().split_once('/') {
+            if addr.parse::<Ipv4Addr>().is_err() {
+                log::warn!("Apple profile export: skipping non-IPv4 route {}", route.trim());
+                skipped.push_str(&format!("        <!-- Skipped route {}: only IPv4 routes are supported -->\n", xml_escape(route.trim()).replace("--", "-")));
+                continue;
+            }
+            if let Ok(prefix) = prefix.parse::<u8>() {
+                routes.push_str(&format!(
+                    "          <dict>\n            <key>Address</key>\n            <string>{}</string>\n            <key>SubnetMask</key>\n            <string>{}</string>\n          </dict>\n",
+                    xml_escape(addr),
+                    prefix_to_mask(prefix)
+                ));
+            }
+        }
+    }
+    if routes.is_empty() {
+        return skipped;
+    }
+    format!("{}        <key>IncludedRoutes</key>\n        <array>\n{}        </array>\n", skipped, routes)
+}
+
+/// Generates a `com.apple.vpn.managed` IKEv2 configuration profile with certificate
+/// authentication and the same IKE/Child SA parameters as the iOS/Mac IPsec preset.
+/// The identity certificate and `LocalIdentifier` are expected to be delivered
+/// separately (SCEP/MDM) and selected on the device.
+pub fn generate_mobileconfig(config: &VpnConfig) -> Result<String, String> {
+    if config.vpn_server_address.trim().is_empty() {
+        return Err("VPN server address is required for the Apple profile export.".to_string());
+    }
+
+    let identifier = payload_identifier(config);
+    let display_name = format!("{} VPN", config.company_prefix.trim());
+    let dns_servers = config.dns_server_list();
+
+    let mut dns_xml = String::new();
+    if !dns_servers.is_empty() {
+        dns_xml.push_str("      <key>DNS</key>\n      <dict>\n        <key>ServerAddresses</key>\n");
+        dns_xml.push_str(&string_array("        ", &dns_servers));
+        if !config.dns_suffix.trim().is_empty() {
+            let suffix = [config.dns_suffix.trim()];
+            dns_xml.push_str("        <key>SearchDomains</key>\n");
+            dns_xml.push_str(&string_array("        ", &suffix));
+            dns_xml.push_str("        <key>SupplementalMatchDomains</key>\n");
+            dns_xml.push_str(&string_array("        ", &suffix));
+        }
+        dns_xml.push_str("      </dict>\n");
+    }
+
+    Ok(format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>PayloadContent</key>
+  <array>
+    <dict>
+      <key>PayloadType</key>
+      <string>com.apple.vpn.managed</string>
+      <key>PayloadVersion</key>
+      <integer>1</integer>
+      <key>PayloadIdentifier</key>
+      <string>{identifier}.vpn</string>
+      <key>PayloadUUID</key>
+      <string>{vpn_uuid}</string>
+      <key>PayloadDisplayName</key>
+      <string>{display_name}</string>
+      <key>UserDefinedName</key>
+      <string>{display_name}</string>
+      <key>VPNType</key>
+      <string>IKEv2</string>
+      <key>IKEv2</key>
+      <dict>
+        <key>RemoteAddress</key>
+        <string>{server}</string>
+        <key>RemoteIdentifier</key>
+        <string>{remote_id}</string>
+        <key>AuthenticationMethod</key>
+        <string>Certificate</string>
+        <key>ExtendedAuthEnabled</key>
+        <integer>0</integer>
+        <key>EnablePFS</key>
+        <integer>1</integer>
+        <key>DisableMOBIKE</key>
+        <integer>0</integer>
+        <key>DisableRedirect</key>
+        <integer>0</integer>
+{ike_sa}{child_sa}        <key>OnDemandEnabled</key>
+        <integer>{on_demand}</integer>
+{on_demand_rules}      </dict>
+      <key>IPv4</key>
+      <dict>
+        <key>OverridePrimary</key>
+        <integer>{override_primary}</integer>
+{included_routes}      </dict>
+{dns_xml}    </dict>
+  </array>
+  <key>PayloadType</key>
+  <string>Configuration</string>
+  <key>PayloadVersion</key>
+  <integer>1</integer>
+  <key>PayloadIdentifier</key>
+  <string>{identifier}</string>
+  <key>PayloadUUID</key>
+  <string>{profile_uuid}</string>
+  <key>PayloadDisplayName</key>
+  <string>{display_name}</string>
+  <key>PayloadDescription</key>
+  <string>Always On VPN (IKEv2) generated by AOVPN Dashboard</string>
+</dict>
+</plist>
+"#,
+        identifier = xml_escape(&identifier),
+        vpn_uuid = stable_uuid(&format!("{}.vpn", identifier)),
+        profile_uuid = stable_uuid(&identifier),
+        display_name = xml_escape(&display_name),
+        server = xml_escape(config.vpn_server_address.trim()),
+        remote_id = xml_escape(config.remote_identity()),
+        ike_sa = sa_parameters("IKESecurityAssociationParameters", 1440, IOS_MAC_PRESET.dh_group_number()),
+        child_sa = sa_parameters("ChildSecurityAssociationParameters", 60, IOS_MAC_PRESET.pfs_group_number()),
+        on_demand = if config.user_tunnel_always_on { 1 } else { 0 },
+        on_demand_rules = on_demand_rules(config),
+        override_primary = if config.force_tunneling { 1 } else { 0 },
+        included_routes = if config.force_tunneling { String::new() } else { included_routes(config) },
+        dns_xml = dns_xml,
+    ))
+}
+
+#[command]
+pub async fn get_mobileconfig(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let config = resolve_config(&app, config, profile_id)?;
+    generate_mobileconfig(&config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ipv6_routes_are_not_given_a_subnet_mask() {
+        let config = VpnConfig {
+            vpn_server_address: "vpn.contoso.com".into(),
+            user_routes: vec!["10.0.0.0/8".into(), "fd00::/8".into()],
+            ..VpnConfig::default()
+        };
+        let profile = generate_mobileconfig(&config).unwrap();
+        assert!(profile.contains("<string>10.0.0.0</string>\n            <key>SubnetMask</key>\n            <string>255.0.0.0</string>"));
+        assert!(profile.contains("<!-- Skipped route fd00::/8"));
+        assert!(!profile.contains("<string>fd00::</string>"));
+
+        let v6_only = VpnConfig { user_routes: vec!["fd00::/8".into()], ..config };
+        assert!(!generate_mobileconfig(&v6_only).unwrap().contains("IncludedRoutes"));
+    }
+}
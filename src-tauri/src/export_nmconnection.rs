@@ -0,0 +1,203 @@
+use crate::config::{stable_uuid, VpnConfig};
+use crate::ipsec::IOS_MAC_PRESET;
+use crate::profiles::resolve_config;
+use std::net::Ipv6Addr;
+use tauri::{command, AppHandle};
+
+/// Directory the exported keyfile expects the certificates in.
+const CERT_DIR: &str = "/etc/aovpn";
+
+type Section = (&'static str, Vec<(String, String)>);
+
+fn kv(key: &str, value: impl Into<String>) -> (String, String) {
+    (key.to_string(), value.into())
+}
+
+fn render(sections: &[Section]) -> String {
+    let mut out = String::new();
+    for (i, (name, entries)) in sections.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(&format!("[{}]\n", name));
+        for (key, value) in entries {
+            out.push_str(&format!("{}={}\n", key, value));
+        }
+    }
+    out
+}
+
+/// Keyfile lists are `;`-separated with a trailing `;`.
+fn keyfile_list(values: &[&str]) -> String {
+    values.iter().map(|v| format!("{};", v)).collect()
+}
+
+/// Generates a NetworkManager `.nmconnection` keyfile for the strongswan VPN plugin
+/// mirroring the User Tunnel (IKEv2, EAP-TLS with the user certificate).
+pub fn generate_nmconnection(config: &VpnConfig) -> Result<String, String> {
+    if config.vpn_server_address.trim().is_empty() {
+        return Err("VPN server address is required for the NetworkManager export.".to_string());
+    }
+    if config.user_tunnel_protocol.eq_ignore_ascii_case("sstp") {
+        return Err("The NetworkManager export requires an IKEv2 or Automatic User Tunnel; SSTP is not supported by the strongswan plugin.".to_string());
+    }
+
+    let id = config.tunnel_names().user;
+    let user_stem = format!("{}/{}", CERT_DIR, config.slug("user"));
+
+    let connection = vec![
+        kv("id", id.clone()),
+        kv("uuid", stable_uuid(&format!("{}|{}", id, config.vpn_server_address.trim())).to_lowercase()),
+        kv("type", "vpn"),
+        kv("autoconnect", config.user_tunnel_always_on.to_string()),
+    ];
+
+    let vpn = vec![
+        kv("service-type", "org.freedesktop.NetworkManager.strongswan"),
+        kv("address", config.vpn_server_address.trim()),
+        kv("certificate", format!("{}/{}.pem", CERT_DIR, config.slug("root-ca"))),
+        kv("remote-identity", config.remote_identity()),
+        kv("method", "eap-tls"),
+        kv("usercert", format!("{}.pem", user_stem)),
+        kv("userkey", format!("{}.key", user_stem)),
+        kv("virtual", "yes"),
+        kv("encap", "no"),
+        kv("ipcomp", "no"),
+        kv("proposal", "yes"),
+        kv("ike", IOS_MAC_PRESET.strongswan_ike_proposal()),
+        kv("esp", IOS_MAC_PRESET.strongswan_esp_proposal()),
+    ];
+
+    // Keyfiles keep IPv4 and IPv6 settings apart; NetworkManager rejects an IPv6 route or DNS
+    // server under [ipv4].
+    let is_v6 = |value: &str| value.split('/').next().is_some_and(|a| a.parse::<Ipv6Addr>().is_ok());
+    let dns_servers = config.dns_server_list();
+    let (dns_v6, dns_v4): (Vec<&str>, Vec<&str>) = dns_servers.iter().partition(|d| is_v6(d));
+    let routes: Vec<&str> = if config.force_tunneling {
+        Vec::new()
+    } else {
+        config.user_routes.iter().map(|r| r.trim()).filter(|r| r.split_once('/').is_some()).collect()
+    };
+    let (routes_v6, routes_v4): (Vec<&str>, Vec<&str>) = routes.iter().partition(|r| is_v6(r));
+
+    let mut ipv4 = vec![kv("method", "auto")];
+    if !dns_v4.is_empty() {
+        ipv4.push(kv("dns", keyfile_list(&dns_v4)));
+        ipv4.push(kv("ignore-auto-dns", "true"));
+    }
+    if !config.dns_suffix.trim().is_empty() {
+        ipv4.push(kv("dns-search", keyfile_list(&[config.dns_suffix.trim()])));
+    }
+    if !config.force_tunneling {
+        ipv4.push(kv("never-default", "true"));
+    }
+    // Keyfile route keys are 1-based: route1, route2, ...
+    for (i, route) in routes_v4.iter().enumerate() {
+        ipv4.push(kv(&format!("route{}", i + 1), *route));
+    }
+
+    let mut ipv6 = Vec::new();
+    if dns_v6.is_empty() && routes_v6.is_empty() {
+        ipv6.push(kv("method", "ignore"));
+    } else {
+        ipv6.push(kv("method", "auto"));
+        if !dns_v6.is_empty() {
+            ipv6.push(kv("dns", keyfile_list(&dns_v6)));
+            ipv6.push(kv("ignore-auto-dns", "true"));
+        }
+        if !config.force_tunneling {
+            ipv6.push(kv("never-default", "true"));
+        }
+        for (i, route) in routes_v6.iter().enumerate() {
+            ipv6.push(kv(&format!("route{}", i + 1), *route));
+        }
+    }
+
+    let sections: Vec<Section> = vec![
+        ("connection", connection),
+        ("vpn", vpn),
+        ("ipv4", ipv4),
+        ("ipv6", ipv6),
+    ];
+
+    Ok(render(&sections))
+}
+
+#[command]
+pub async fn get_nmconnection(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let config = resolve_config(&app, config, profile_id)?;
+    generate_nmconnection(&config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    /// Minimal keyfile (INI) reader: section -> key -> value. Fails on anything NetworkManager
+    /// would not accept either, like keys outside a section or duplicate keys.
+    fn parse_keyfile(text: &str) -> BTreeMap<String, BTreeMap<String, String>> {
+        let mut out: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+        let mut current = None;
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                assert!(out.insert(name.to_string(), BTreeMap::new()).is_none(), "duplicate section {}", name);
+                current = Some(name.to_string());
+                continue;
+            }
+            let (key, value) = line.split_once('=').unwrap_or_else(|| panic!("not a key=value line: {}", line));
+            let section = out.get_mut(current.as_ref().expect("key outside a section")).unwrap();
+            assert!(section.insert(key.to_string(), value.to_string()).is_none(), "duplicate key {}", key);
+        }
+        out
+    }
+
+    #[test]
+    fn keyfile_parses_back_with_routes_split_by_family() {
+        let config = VpnConfig {
+            vpn_server_address: "vpn.contoso.com".into(),
+            user_tunnel_protocol: "IKEv2".into(),
+            dns_servers: "10.0.0.10, fd00::53".into(),
+            dns_suffix: "corp.contoso.com".into(),
+            user_routes: vec!["10.0.0.0/8".into(), "fd00::/8".into(), "192.168.10.0/24".into()],
+            ..VpnConfig::default()
+        };
+        let keyfile = parse_keyfile(&generate_nmconnection(&config).unwrap());
+
+        let vpn = &keyfile["vpn"];
+        assert_eq!(vpn["service-type"], "org.freedesktop.NetworkManager.strongswan");
+        assert_eq!(vpn["address"], "vpn.contoso.com");
+        assert_eq!(vpn["method"], "eap-tls");
+        assert_eq!(vpn["ike"], IOS_MAC_PRESET.strongswan_ike_proposal());
+
+        let ipv4 = &keyfile["ipv4"];
+        assert_eq!(ipv4["route1"], "10.0.0.0/8");
+        assert_eq!(ipv4["route2"], "192.168.10.0/24");
+        assert!(!ipv4.contains_key("route3"));
+        assert_eq!(ipv4["dns"], "10.0.0.10;");
+        assert_eq!(ipv4["dns-search"], "corp.contoso.com;");
+        assert_eq!(ipv4["never-default"], "true");
+
+        let ipv6 = &keyfile["ipv6"];
+        assert_eq!(ipv6["method"], "auto");
+        assert_eq!(ipv6["route1"], "fd00::/8");
+        assert_eq!(ipv6["dns"], "fd00::53;");
+    }
+
+    #[test]
+    fn ipv6_is_ignored_without_ipv6_settings() {
+        let config = VpnConfig {
+            vpn_server_address: "vpn.contoso.com".into(),
+            user_tunnel_protocol: "IKEv2".into(),
+            user_routes: vec!["10.0.0.0/8".into()],
+            ..VpnConfig::default()
+        };
+        let keyfile = parse_keyfile(&generate_nmconnection(&config).unwrap());
+        assert_eq!(keyfile["ipv6"].len(), 1);
+        assert_eq!(keyfile["ipv6"]["method"], "ignore");
+    }
+}
@@ -0,0 +1,241 @@
+use crate::config::VpnConfig;
+use crate::ipsec::IOS_MAC_PRESET;
+use crate::profiles::resolve_config;
+use tauri::{command, AppHandle};
+
+fn traffic_selectors(routes: &[String], force_tunneling: bool) -> String {
+    if force_tunneling {
+        return "0.0.0.0/0,::/0".to_string();
+    }
+    let selectors: Vec<&str> = routes
+        .iter()
+        .map(|r| r.trim())
+        .filter(|r| r.split_once('/').is_some())
+        .collect();
+    if selectors.is_empty() {
+        "0.0.0.0/0".to_string()
+    } else {
+        selectors.join(",")
+    }
+}
+
+/// swanctl values containing whitespace or separators must be quoted.
+fn quote(value: &str) -> String {
+    if value.chars().any(|c| c.is_whitespace() || "{}=#,\"".contains(c)) {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn connection_block(
+    config: &VpnConfig,
+    name: &str,
+    local_auth: &str,
+    routes: &[String],
+    force_tunneling: bool,
+) -> String {
+    let ca_file = format!("{}.pem", config.slug("root-ca"));
+    let ca_comment = if config.root_ca_hash.trim().is_empty() {
+        String::new()
+    } else {
+        format!("            # Root CA thumbprint: {}\n", config.root_ca_hash.trim())
+    };
+    let eap_id = if local_auth == "eap-tls" { "            eap_id = %any\n" } else { "" };
+
+    format!(
+        r#"    {name} {{
+        version = 2
+        remote_addrs = {server}
+        vips = 0.0.0.0
+        proposals = {ike_proposal}
+        fragmentation = yes
+        local {{
+            auth = {local_auth}
+            certs = {name}.pem
+{eap_id}        }}
+        remote {{
+            auth = pubkey
+            id = {remote_id}
+{ca_comment}            cacerts = {ca_file}
+        }}
+        children {{
+            {name} {{
+                remote_ts = {remote_ts}
+                esp_proposals = {esp_proposal}
+                dpd_action = restart
+                start_action = start
+            }}
+        }}
+    }}
+"#,
+        name = name,
+        server = quote(config.vpn_server_address.trim()),
+        ike_proposal = IOS_MAC_PRESET.strongswan_ike_proposal(),
+        local_auth = local_auth,
+        eap_id = eap_id,
+        remote_id = quote(config.remote_identity()),
+        ca_comment = ca_comment,
+        ca_file = ca_file,
+        remote_ts = traffic_selectors(routes, force_tunneling),
+        esp_proposal = IOS_MAC_PRESET.strongswan_esp_proposal(),
+    )
+}
+
+/// Generates a `swanctl.conf` mirroring the Device Tunnel (machine certificate) and,
+/// when it uses IKEv2, the User Tunnel (EAP-TLS) with the app's IPsec preset.
+pub fn generate_swanctl_conf(config: &VpnConfig) -> Result<String, String> {
+    if config.vpn_server_address.trim().is_empty() {
+        return Err("VPN server address is required for the strongSwan export.".to_string());
+    }
+
+    let mut out = String::new();
+    out.push_str(&format!(
+        "# swanctl.conf generated by AOVPN Dashboard for {}\n",
+        config.company_prefix.trim()
+    ));
+    out.push_str("# Place client certificates in x509/, private keys in private/ and the root CA in x509ca/.\n");
+    out.push_str("connections {\n");
+
+    let device_name = config.slug("device");
+    out.push_str(&connection_block(config, &device_name, "pubkey", &config.device_routes, false));
+
+    if config.user_tunnel_protocol.eq_ignore_ascii_case("sstp") {
+        out.push_str("    # User Tunnel skipped: SSTP is not supported by strongSwan.\n");
+    } else {
+        out.push('\n');
+        let user_name = config.slug("user");
+        out.push_str(&connection_block(
+            config,
+            &user_name,
+            "eap-tls",
+            &config.user_routes,
+            config.force_tunneling,
+        ));
+    }
+
+    out.push_str("}\n");
+    Ok(out)
+}
+
+#[command]
+pub async fn get_swanctl_conf(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let config = resolve_config(&app, config, profile_id)?;
+    generate_swanctl_conf(&config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, Default)]
+    struct Section {
+        values: BTreeMap<String, String>,
+        sections: BTreeMap<String, Section>,
+    }
+
+    /// Unquotes a swanctl value, undoing the `\\` and `\"` escapes.
+    fn unquote(value: &str) -> String {
+        let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) else {
+            assert!(!value.contains(char::is_whitespace), "unquoted value: {}", value);
+            return value.to_string();
+        };
+        let mut out = String::new();
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            out.push(if c == '\\' { chars.next().expect("dangling escape") } else { c });
+        }
+        out
+    }
+
+    /// Minimal swanctl.conf reader: `name {` opens a section, `}` closes it, `key = value`
+    /// sets a value. Fails on unbalanced braces and duplicate keys or sections.
+    fn parse_swanctl(text: &str) -> Section {
+        let mut stack = vec![(String::new(), Section::default())];
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            if let Some(name) = line.strip_suffix('{') {
+                stack.push((name.trim().to_string(), Section::default()));
+            } else if line == "}" {
+                let (name, section) = stack.pop().unwrap();
+                let parent = &mut stack.last_mut().expect("unbalanced '}'").1;
+                assert!(parent.sections.insert(name.clone(), section).is_none(), "duplicate section {}", name);
+            } else {
+                let (key, value) = line.split_once('=').unwrap_or_else(|| panic!("not a key = value line: {}", line));
+                let values = &mut stack.last_mut().unwrap().1.values;
+                assert!(values.insert(key.trim().to_string(), unquote(value.trim())).is_none(), "duplicate key {}", key);
+            }
+        }
+        assert_eq!(stack.len(), 1, "unclosed section");
+        stack.pop().unwrap().1
+    }
+
+    fn config() -> VpnConfig {
+        VpnConfig {
+            company_prefix: "Contoso AG".into(),
+            vpn_server_address: "vpn.contoso.com".into(),
+            user_tunnel_protocol: "IKEv2".into(),
+            device_routes: vec!["10.0.0.0/24".into()],
+            user_routes: vec!["10.0.0.0/8".into(), " 192.168.10.0/24 ".into(), "not-a-route".into()],
+            ..VpnConfig::default()
+        }
+    }
+
+    #[test]
+    fn conf_parses_back_with_preset_proposals() {
+        let conf = parse_swanctl(&generate_swanctl_conf(&config()).unwrap());
+        let connections = &conf.sections["connections"].sections;
+        assert_eq!(connections.keys().collect::<Vec<_>>(), ["contoso-ag-device", "contoso-ag-user"]);
+
+        let device = &connections["contoso-ag-device"];
+        assert_eq!(device.values["remote_addrs"], "vpn.contoso.com");
+        assert_eq!(device.values["proposals"], IOS_MAC_PRESET.strongswan_ike_proposal());
+        assert_eq!(device.sections["local"].values["auth"], "pubkey");
+        assert_eq!(device.sections["remote"].values["cacerts"], "contoso-ag-root-ca.pem");
+        let child = &device.sections["children"].sections["contoso-ag-device"];
+        assert_eq!(child.values["esp_proposals"], IOS_MAC_PRESET.strongswan_esp_proposal());
+        assert_eq!(child.values["remote_ts"], "10.0.0.0/24");
+
+        let user = &connections["contoso-ag-user"];
+        assert_eq!(user.sections["local"].values["auth"], "eap-tls");
+        assert_eq!(user.sections["local"].values["eap_id"], "%any");
+        let child = &user.sections["children"].sections["contoso-ag-user"];
+        assert_eq!(child.values["remote_ts"], "10.0.0.0/8,192.168.10.0/24");
+    }
+
+    #[test]
+    fn force_tunneling_routes_everything_through_the_user_tunnel() {
+        let conf = parse_swanctl(&generate_swanctl_conf(&VpnConfig { force_tunneling: true, ..config() }).unwrap());
+        let connections = &conf.sections["connections"].sections;
+        let user = &connections["contoso-ag-user"].sections["children"].sections["contoso-ag-user"];
+        assert_eq!(user.values["remote_ts"], "0.0.0.0/0,::/0");
+        // The Device Tunnel always stays split.
+        let device = &connections["contoso-ag-device"].sections["children"].sections["contoso-ag-device"];
+        assert_eq!(device.values["remote_ts"], "10.0.0.0/24");
+    }
+
+    #[test]
+    fn sstp_user_tunnel_is_skipped() {
+        let conf = parse_swanctl(&generate_swanctl_conf(&VpnConfig { user_tunnel_protocol: "SSTP".into(), ..config() }).unwrap());
+        assert_eq!(conf.sections["connections"].sections.keys().collect::<Vec<_>>(), ["contoso-ag-device"]);
+    }
+
+    #[test]
+    fn values_with_spaces_and_separators_are_quoted() {
+        let config = VpnConfig { eap_server_names: "CN=VPN Gateway, O=\"Contoso\"; other".into(), ..config() };
+        let text = generate_swanctl_conf(&config).unwrap();
+        assert!(text.contains(r#"id = "CN=VPN Gateway, O=\"Contoso\"""#));
+        let conf = parse_swanctl(&text);
+        let remote = &conf.sections["connections"].sections["contoso-ag-device"].sections["remote"];
+        assert_eq!(remote.values["id"], "CN=VPN Gateway, O=\"Contoso\"");
+    }
+
+    #[test]
+    fn server_address_is_required() {
+        assert!(generate_swanctl_conf(&VpnConfig { vpn_server_address: " ".into(), ..config() }).is_err());
+    }
+}
@@ -0,0 +1,922 @@
+use crate::ipsec::IOS_MAC_PRESET;
+use crate::jobs;
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::io::ErrorKind;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+use tauri::command;
+
+const PAYLOAD_NONE: u8 = 0;
+const PAYLOAD_SA: u8 = 33;
+const PAYLOAD_KE: u8 = 34;
+const PAYLOAD_CERTREQ: u8 = 38;
+const PAYLOAD_NONCE: u8 = 40;
+const PAYLOAD_NOTIFY: u8 = 41;
+const PAYLOAD_VENDOR: u8 = 43;
+
+const EXCHANGE_IKE_SA_INIT: u8 = 34;
+const FLAG_INITIATOR: u8 = 0x08;
+const FLAG_RESPONSE: u8 = 0x20;
+
+const TRANSFORM_ENCR: u8 = 1;
+const TRANSFORM_PRF: u8 = 2;
+const TRANSFORM_INTEG: u8 = 3;
+const TRANSFORM_DH: u8 = 4;
+const ATTR_KEY_LENGTH: u16 = 0x800E;
+
+const ENCR_3DES: u16 = 3;
+const ENCR_AES_CBC: u16 = 12;
+const ENCR_AES_GCM_16: u16 = 20;
+
+const NOTIFY_INVALID_SYNTAX: u16 = 7;
+const NOTIFY_NO_PROPOSAL_CHOSEN: u16 = 14;
+const NOTIFY_INVALID_KE_PAYLOAD: u16 = 17;
+const NOTIFY_NAT_DETECTION_SOURCE_IP: u16 = 16388;
+const NOTIFY_NAT_DETECTION_DESTINATION_IP: u16 = 16389;
+const NOTIFY_COOKIE: u16 = 16390;
+const NOTIFY_FRAGMENTATION_SUPPORTED: u16 = 16430;
+
+/// IKE over UDP 4500 is prefixed with four zero bytes to tell it apart from ESP.
+const NON_ESP_MARKER: [u8; 4] = [0; 4];
+/// Requests sent per probe: the first one plus retries after a COOKIE or INVALID_KE_PAYLOAD.
+const MAX_EXCHANGES: usize = 3;
+
+/// P-256 and P-384 generator points. A real key exchange is never completed, but responders
+/// validate that the public value lies on the curve, so the probe sends a known-good point.
+const P256_GENERATOR: &str = "6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c2964fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5";
+const P384_GENERATOR: &str = "aa87ca22be8b05378eb1c71ef320ad746e1d3b628ba79b9859f741e082542a385502f25dbf55296c3a545e3872760ab73617de4a96262c6f5d9e98bf9292dc29f8f41dbd289a147ce9da3113b5f0b8c00a60b1ce1d7e819d7a431d7c90ea0e5f";
+
+/// An IKE proposal by IANA transform IDs, written in strongSwan notation (`aes256-sha256-modp2048`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct IkeProposal {
+    pub encryption: u16,
+    pub key_bits: Option<u16>,
+    pub prf: u16,
+    /// None for combined-mode ciphers (AES-GCM).
+    pub integrity: Option<u16>,
+    pub dh_group: u16,
+}
+
+fn hash_name(prf_id: u16) -> Option<&'static str> {
+    match prf_id {
+        2 => Some("sha1"),
+        5 => Some("sha256"),
+        6 => Some("sha384"),
+        7 => Some("sha512"),
+        _ => None,
+    }
+}
+
+fn prf_id(name: &str) -> Option<u16> {
+    [2, 5, 6, 7].into_iter().find(|&id| hash_name(id) == Some(name))
+}
+
+fn integrity_name(id: u16) -> Option<&'static str> {
+    match id {
+        2 => Some("sha1"),
+        12 => Some("sha256"),
+        13 => Some("sha384"),
+        14 => Some("sha512"),
+        _ => None,
+    }
+}
+
+fn integrity_id(name: &str) -> Option<u16> {
+    [2, 12, 13, 14].into_iter().find(|&id| integrity_name(id) == Some(name))
+}
+
+fn dh_name(group: u16) -> Option<&'static str> {
+    match group {
+        1 => Some("modp768"),
+        2 => Some("modp1024"),
+        5 => Some("modp1536"),
+        14 => Some("modp2048"),
+        15 => Some("modp3072"),
+        16 => Some("modp4096"),
+        19 => Some("ecp256"),
+        20 => Some("ecp384"),
+        24 => Some("modp2048s256"),
+        _ => None,
+    }
+}
+
+fn dh_group_id(name: &str) -> Option<u16> {
+    [1, 2, 5, 14, 15, 16, 19, 20, 24].into_iter().find(|&g| dh_name(g) == Some(name))
+}
+
+/// Key exchange payload for `group`: a curve generator for ECP, otherwise a value below the
+/// (all-ones prefixed) MODP prime.
+fn key_exchange_data(group: u16) -> Result<Vec<u8>, String> {
+    let len = match group {
+        1 => 96,
+        2 => 128,
+        5 => 192,
+        14 | 24 => 256,
+        15 => 384,
+        16 => 512,
+        19 => return Ok(from_hex(P256_GENERATOR)),
+        20 => return Ok(from_hex(P384_GENERATOR)),
+        other => return Err(format!("Unsupported DH group {}.", other)),
+    };
+    let mut data = random_bytes(len)?;
+    data[0] &= 0x7F;
+    data[len - 1] |= 0x01;
+    Ok(data)
+}
+
+impl IkeProposal {
+    pub fn parse(text: &str) -> Result<IkeProposal, String> {
+        let lower = text.trim().to_ascii_lowercase();
+        let parts: Vec<&str> = lower.split('-').collect();
+        let invalid = || format!("Invalid IKE proposal '{}' (expected e.g. aes256-sha256-modp2048).", text);
+        if parts.len() < 3 {
+            return Err(invalid());
+        }
+
+        let cipher = parts[0];
+        let (encryption, key_bits) = if cipher == "3des" {
+            (ENCR_3DES, None)
+        } else if let Some(bits) = cipher.strip_prefix("aes").and_then(|c| c.strip_suffix("gcm16")) {
+            (ENCR_AES_GCM_16, Some(bits.parse::<u16>().map_err(|_| invalid())?))
+        } else if let Some(bits) = cipher.strip_prefix("aes") {
+            (ENCR_AES_CBC, Some(bits.parse::<u16>().map_err(|_| invalid())?))
+        } else {
+            return Err(invalid());
+        };
+        let dh_group = dh_group_id(parts[parts.len() - 1]).ok_or_else(invalid)?;
+
+        let mut prf = None;
+        let mut hash = None;
+        for part in &parts[1..parts.len() - 1] {
+            match part.strip_prefix("prf") {
+                Some(name) => prf = Some(prf_id(name).ok_or_else(invalid)?),
+                None => hash = Some(*part),
+            }
+        }
+        let aead = encryption == ENCR_AES_GCM_16;
+        let integrity = match hash {
+            Some(name) if !aead => Some(integrity_id(name).ok_or_else(invalid)?),
+            _ => None,
+        };
+        // Without an explicit `prf...` the PRF uses the same hash as the integrity algorithm.
+        let prf = match (prf, hash) {
+            (Some(id), _) => id,
+            (None, Some(name)) => prf_id(name).ok_or_else(invalid)?,
+            (None, None) => return Err(invalid()),
+        };
+        if !aead && integrity.is_none() {
+            return Err(invalid());
+        }
+        Ok(IkeProposal { encryption, key_bits, prf, integrity, dh_group })
+    }
+
+    fn cipher_name(&self) -> String {
+        let bits = self.key_bits.unwrap_or(0);
+        match self.encryption {
+            ENCR_3DES => "3des".to_string(),
+            ENCR_AES_CBC => format!("aes{}", bits),
+            ENCR_AES_GCM_16 => format!("aes{}gcm16", bits),
+            other => format!("encr{}", other),
+        }
+    }
+
+    fn to_bytes(&self, number: u8, last: bool) -> Vec<u8> {
+        let mut transforms: Vec<(u8, u16, Option<u16>)> = vec![(TRANSFORM_ENCR, self.encryption, self.key_bits)];
+        transforms.push((TRANSFORM_PRF, self.prf, None));
+        if let Some(integrity) = self.integrity {
+            transforms.push((TRANSFORM_INTEG, integrity, None));
+        }
+        transforms.push((TRANSFORM_DH, self.dh_group, None));
+
+        let mut body = Vec::new();
+        let count = transforms.len();
+        for (i, (kind, id, key_bits)) in transforms.into_iter().enumerate() {
+            let length: u16 = if key_bits.is_some() { 12 } else { 8 };
+            body.push(if i + 1 == count { 0 } else { 3 });
+            body.push(0);
+            body.extend(length.to_be_bytes());
+            body.push(kind);
+            body.push(0);
+            body.extend(id.to_be_bytes());
+            if let Some(bits) = key_bits {
+                body.extend(ATTR_KEY_LENGTH.to_be_bytes());
+                body.extend(bits.to_be_bytes());
+            }
+        }
+
+        let mut out = vec![if last { 0 } else { 2 }, 0];
+        out.extend((8 + body.len() as u16).to_be_bytes());
+        out.extend([number, 1, 0, count as u8]);
+        out.extend(body);
+        out
+    }
+}
+
+impl std::fmt::Display for IkeProposal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let prf = hash_name(self.prf).map(str::to_string).unwrap_or_else(|| format!("prf{}", self.prf));
+        let dh = dh_name(self.dh_group).map(str::to_string).unwrap_or_else(|| format!("dh{}", self.dh_group));
+        match self.integrity {
+            Some(id) => {
+                let integrity = integrity_name(id).map(str::to_string).unwrap_or_else(|| format!("integ{}", id));
+                if integrity == prf {
+                    write!(f, "{}-{}-{}", self.cipher_name(), integrity, dh)
+                } else {
+                    write!(f, "{}-{}-prf{}-{}", self.cipher_name(), integrity, prf, dh)
+                }
+            }
+            None => write!(f, "{}-prf{}-{}", self.cipher_name(), prf, dh),
+        }
+    }
+}
+
+/// Offered when the caller does not pass proposals: the iOS/Mac preset followed by the
+/// Windows client defaults.
+pub fn default_proposals() -> Vec<IkeProposal> {
+    [
+        IOS_MAC_PRESET.strongswan_ike_proposal().as_str(),
+        "aes256-sha256-modp1024",
+        "aes128-sha1-modp1024",
+        "3des-sha1-modp1024",
+    ]
+    .iter()
+    .filter_map(|p| IkeProposal::parse(p).ok())
+    .collect()
+}
+
+fn from_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .filter_map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn random_bytes(len: usize) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![0u8; len];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Failed to generate random bytes: {}", e))?;
+    Ok(bytes)
+}
+
+/// NAT_DETECTION_*_IP data: SHA-1 over both SPIs, the address and the port.
+fn nat_hash(spi_i: &[u8; 8], spi_r: &[u8; 8], addr: SocketAddr) -> Vec<u8> {
+    let mut hasher = Sha1::new();
+    hasher.update(spi_i);
+    hasher.update(spi_r);
+    match addr.ip() {
+        IpAddr::V4(ip) => hasher.update(ip.octets()),
+        IpAddr::V6(ip) => hasher.update(ip.octets()),
+    }
+    hasher.update(addr.port().to_be_bytes());
+    hasher.finalize().to_vec()
+}
+
+fn notify_body(notify_type: u16, data: &[u8]) -> Vec<u8> {
+    let mut body = vec![0, 0];
+    body.extend(notify_type.to_be_bytes());
+    body.extend(data);
+    body
+}
+
+/// Everything needed to build one IKE_SA_INIT request.
+pub struct InitRequest<'a> {
+    pub spi_i: [u8; 8],
+    pub proposals: &'a [IkeProposal],
+    pub ke_group: u16,
+    pub nonce: Vec<u8>,
+    pub cookie: Option<Vec<u8>>,
+    pub local: SocketAddr,
+    pub remote: SocketAddr,
+    /// Grow the message to this many bytes with a Vendor ID payload, which responders ignore.
+    pub pad_to: usize,
+}
+
+impl InitRequest<'_> {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut chunks: Vec<(u8, Vec<u8>)> = Vec::new();
+        if let Some(cookie) = &self.cookie {
+            chunks.push((PAYLOAD_NOTIFY, notify_body(NOTIFY_COOKIE, cookie)));
+        }
+        let mut sa = Vec::new();
+        for (i, proposal) in self.proposals.iter().enumerate() {
+            sa.extend(proposal.to_bytes(i as u8 + 1, i + 1 == self.proposals.len()));
+        }
+        chunks.push((PAYLOAD_SA, sa));
+        let mut ke = self.ke_group.to_be_bytes().to_vec();
+        ke.extend([0, 0]);
+        ke.extend(key_exchange_data(self.ke_group)?);
+        chunks.push((PAYLOAD_KE, ke));
+        chunks.push((PAYLOAD_NONCE, self.nonce.clone()));
+        let zero = [0u8; 8];
+        chunks.push((PAYLOAD_NOTIFY, notify_body(NOTIFY_NAT_DETECTION_SOURCE_IP, &nat_hash(&self.spi_i, &zero, self.local))));
+        chunks.push((PAYLOAD_NOTIFY, notify_body(NOTIFY_NAT_DETECTION_DESTINATION_IP, &nat_hash(&self.spi_i, &zero, self.remote))));
+        chunks.push((PAYLOAD_NOTIFY, notify_body(NOTIFY_FRAGMENTATION_SUPPORTED, &[])));
+        let unpadded = 28 + chunks.iter().map(|(_, body)| 4 + body.len()).sum::<usize>();
+        if self.pad_to > unpadded + 4 {
+            chunks.push((PAYLOAD_VENDOR, vec![0; self.pad_to - unpadded - 4]));
+        }
+
+        // Each generic payload header names the type of the payload that follows it.
+        let mut payloads = Vec::new();
+        for (i, (_, body)) in chunks.iter().enumerate() {
+            payloads.push(chunks.get(i + 1).map_or(PAYLOAD_NONE, |(kind, _)| *kind));
+            payloads.push(0);
+            payloads.extend((4 + body.len() as u16).to_be_bytes());
+            payloads.extend(body);
+        }
+
+        let mut message = self.spi_i.to_vec();
+        message.extend([0u8; 8]);
+        message.extend([chunks[0].0, 0x20, EXCHANGE_IKE_SA_INIT, FLAG_INITIATOR]);
+        message.extend(0u32.to_be_bytes());
+        message.extend((28 + payloads.len() as u32).to_be_bytes());
+        message.extend(payloads);
+        Ok(message)
+    }
+}
+
+/// The parts of an IKE_SA_INIT response the probe reports on.
+#[derive(Debug, Default)]
+pub struct InitResponse {
+    pub spi_r: [u8; 8],
+    pub chosen: Option<IkeProposal>,
+    /// Notify type and data, in order.
+    pub notifies: Vec<(u16, Vec<u8>)>,
+    pub certificate_requested: bool,
+    pub vendor_ids: Vec<Vec<u8>>,
+}
+
+impl InitResponse {
+    pub fn notify(&self, notify_type: u16) -> Option<&[u8]> {
+        self.notifies.iter().find(|(t, _)| *t == notify_type).map(|(_, d)| d.as_slice())
+    }
+}
+
+fn be16(data: &[u8], at: usize) -> u16 {
+    u16::from_be_bytes([data[at], data[at + 1]])
+}
+
+fn parse_chosen_proposal(sa: &[u8]) -> Result<IkeProposal, String> {
+    if sa.len() < 8 {
+        return Err("SA payload too short.".to_string());
+    }
+    let length = (be16(sa, 2) as usize).min(sa.len());
+    let spi_size = sa[6] as usize;
+    let count = sa[7] as usize;
+    let mut proposal = IkeProposal { encryption: 0, key_bits: None, prf: 0, integrity: None, dh_group: 0 };
+    let mut at = 8 + spi_size;
+    for _ in 0..count {
+        if at + 8 > length {
+            return Err("Truncated transform in SA payload.".to_string());
+        }
+        let transform_len = be16(sa, at + 2) as usize;
+        if transform_len < 8 || at + transform_len > length {
+            return Err("Invalid transform length in SA payload.".to_string());
+        }
+        let id = be16(sa, at + 6);
+        match sa[at + 4] {
+            TRANSFORM_ENCR => {
+                proposal.encryption = id;
+                if transform_len >= 12 && be16(sa, at + 8) == ATTR_KEY_LENGTH {
+                    proposal.key_bits = Some(be16(sa, at + 10));
+                }
+            }
+            TRANSFORM_PRF => proposal.prf = id,
+            TRANSFORM_INTEG => proposal.integrity = Some(id).filter(|&i| i != 0),
+            TRANSFORM_DH => proposal.dh_group = id,
+            _ => {}
+        }
+        at += transform_len;
+    }
+    Ok(proposal)
+}
+
+/// Parses a response to the request with initiator SPI `spi_i`.
+pub fn parse_response(data: &[u8], spi_i: &[u8; 8]) -> Result<InitResponse, String> {
+    if data.len() < 28 {
+        return Err(format!("Response too short for an IKE header ({} bytes).", data.len()));
+    }
+    if &data[..8] != spi_i {
+        return Err("Response carries a different initiator SPI.".to_string());
+    }
+    if data[17] >> 4 != 2 {
+        return Err(format!("Responder uses IKE major version {}.", data[17] >> 4));
+    }
+    if data[18] != EXCHANGE_IKE_SA_INIT || data[19] & FLAG_RESPONSE == 0 {
+        return Err(format!("Not an IKE_SA_INIT response (exchange {}, flags {:#04x}).", data[18], data[19]));
+    }
+    let length = (u32::from_be_bytes([data[24], data[25], data[26], data[27]]) as usize).min(data.len());
+
+    let mut response = InitResponse::default();
+    response.spi_r.copy_from_slice(&data[8..16]);
+    let mut next = data[16];
+    let mut at = 28;
+    while next != PAYLOAD_NONE {
+        if at + 4 > length {
+            return Err("Truncated payload header.".to_string());
+        }
+        let payload_len = be16(data, at + 2) as usize;
+        if payload_len < 4 || at + payload_len > length {
+            return Err(format!("Invalid length {} for payload type {}.", payload_len, next));
+        }
+        let body = &data[at + 4..at + payload_len];
+        match next {
+            PAYLOAD_SA => response.chosen = Some(parse_chosen_proposal(body)?),
+            PAYLOAD_NOTIFY if body.len() >= 4 => {
+                let spi_size = body[1] as usize;
+                let data_start = (4 + spi_size).min(body.len());
+                response.notifies.push((be16(body, 2), body[data_start..].to_vec()));
+            }
+            PAYLOAD_CERTREQ => response.certificate_requested = true,
+            PAYLOAD_VENDOR => response.vendor_ids.push(body.to_vec()),
+            _ => {}
+        }
+        next = data[at];
+        at += payload_len;
+    }
+    Ok(response)
+}
+
+fn notify_name(notify_type: u16) -> String {
+    let name = match notify_type {
+        1 => "UNSUPPORTED_CRITICAL_PAYLOAD",
+        4 => "INVALID_IKE_SPI",
+        5 => "INVALID_MAJOR_VERSION",
+        NOTIFY_INVALID_SYNTAX => "INVALID_SYNTAX",
+        9 => "INVALID_MESSAGE_ID",
+        NOTIFY_NO_PROPOSAL_CHOSEN => "NO_PROPOSAL_CHOSEN",
+        NOTIFY_INVALID_KE_PAYLOAD => "INVALID_KE_PAYLOAD",
+        24 => "AUTHENTICATION_FAILED",
+        43 => "TEMPORARY_FAILURE",
+        _ => "",
+    };
+    if name.is_empty() {
+        format!("notify {}", notify_type)
+    } else {
+        format!("{} ({})", name, notify_type)
+    }
+}
+
+fn vendor_name(id: &[u8]) -> String {
+    // "MS NT5 ISAKMPOAKLEY" followed by the Windows version, as sent by RRAS.
+    match to_hex(id).as_str() {
+        v if v.starts_with("1e2b516905991c7d7c96fcbfb587e461") => "Microsoft Windows".to_string(),
+        "4048b7d56ebce88525e7de7f00d6c2d3" => "IKE fragmentation (RFC 7383 draft)".to_string(),
+        "fb1de3cdf341b7ea16b7e5be0855f120" => "Windows IKE CGA".to_string(),
+        "8299031757a36082c6a621de00000000" => "Microsoft NAT-T".to_string(),
+        v => v.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProbeStatus {
+    /// The responder picked one of the offered proposals.
+    Accepted,
+    NoProposalChosen,
+    /// The responder wants a different DH group that was not offered.
+    InvalidKePayload,
+    /// Any other error notify.
+    Rejected,
+    NoResponse,
+    /// ICMP port unreachable.
+    Unreachable,
+    InvalidResponse,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IkeProbeResult {
+    pub server: String,
+    pub address: String,
+    pub port: u16,
+    pub status: ProbeStatus,
+    pub summary: String,
+    pub offered: Vec<String>,
+    pub chosen_proposal: Option<String>,
+    /// DH group named in an INVALID_KE_PAYLOAD notify.
+    pub requested_dh_group: Option<u16>,
+    pub notify_errors: Vec<String>,
+    pub cookie_required: bool,
+    /// None when the responder sent no NAT detection notifies.
+    pub local_behind_nat: Option<bool>,
+    pub responder_behind_nat: Option<bool>,
+    pub fragmentation_supported: bool,
+    pub certificate_requested: bool,
+    pub vendor_ids: Vec<String>,
+    pub round_trip_ms: Option<u64>,
+}
+
+impl IkeProbeResult {
+    fn new(server: &str, remote: SocketAddr, proposals: &[IkeProposal]) -> IkeProbeResult {
+        IkeProbeResult {
+            server: server.to_string(),
+            address: remote.ip().to_string(),
+            port: remote.port(),
+            status: ProbeStatus::NoResponse,
+            summary: String::new(),
+            offered: proposals.iter().map(|p| p.to_string()).collect(),
+            chosen_proposal: None,
+            requested_dh_group: None,
+            notify_errors: Vec::new(),
+            cookie_required: false,
+            local_behind_nat: None,
+            responder_behind_nat: None,
+            fragmentation_supported: false,
+            certificate_requested: false,
+            vendor_ids: Vec::new(),
+            round_trip_ms: None,
+        }
+    }
+
+    fn finish(mut self, status: ProbeStatus, summary: String) -> IkeProbeResult {
+        self.status = status;
+        self.summary = summary;
+        self
+    }
+}
+
+fn exchange(socket: &UdpSocket, marker: bool, request: &[u8], timeout: Duration) -> std::io::Result<Vec<u8>> {
+    let mut packet = if marker { NON_ESP_MARKER.to_vec() } else { Vec::new() };
+    packet.extend(request);
+    socket.set_read_timeout(Some(timeout))?;
+    socket.send(&packet)?;
+    let mut buf = vec![0u8; 65535];
+    let len = socket.recv(&mut buf)?;
+    buf.truncate(len);
+    if marker && buf.starts_with(&NON_ESP_MARKER) {
+        buf.drain(..4);
+    }
+    Ok(buf)
+}
+
+/// Sends IKE_SA_INIT to `remote` and reports how the responder answers. Port 4500 uses the
+/// non-ESP marker. A COOKIE or an INVALID_KE_PAYLOAD for an offered group is answered with
+/// a new request, like a real initiator would.
+pub fn probe_addr(server: &str, remote: SocketAddr, proposals: &[IkeProposal], timeout: Duration) -> IkeProbeResult {
+    probe_addr_padded(server, remote, proposals, timeout, 0)
+}
+
+/// `probe_addr` with every request padded to `pad_to` bytes, to test whether large datagrams arrive.
+pub fn probe_addr_padded(
+    server: &str,
+    remote: SocketAddr,
+    proposals: &[IkeProposal],
+    timeout: Duration,
+    pad_to: usize,
+) -> IkeProbeResult {
+    let result = IkeProbeResult::new(server, remote, proposals);
+    let Some(first) = proposals.first() else {
+        return result.finish(ProbeStatus::InvalidResponse, "No proposals to offer.".to_string());
+    };
+    let bind: SocketAddr = if remote.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { (std::net::Ipv6Addr::UNSPECIFIED, 0).into() };
+    let socket = match UdpSocket::bind(bind).and_then(|s| s.connect(remote).map(|_| s)) {
+        Ok(socket) => socket,
+        Err(e) => return result.finish(ProbeStatus::NoResponse, format!("Failed to open UDP socket: {}", e)),
+    };
+    let local = socket.local_addr().unwrap_or(bind);
+    let marker = remote.port() == 4500;
+
+    let (spi_i, nonce) = match (random_bytes(8), random_bytes(32)) {
+        (Ok(spi), Ok(nonce)) => {
+            let mut spi_i = [0u8; 8];
+            spi_i.copy_from_slice(&spi);
+            (spi_i, nonce)
+        }
+        (Err(e), _) | (_, Err(e)) => return result.finish(ProbeStatus::InvalidResponse, e),
+    };
+
+    let mut result = result;
+    let mut request = InitRequest { spi_i, proposals, ke_group: first.dh_group, nonce, cookie: None, local, remote, pad_to };
+    for _ in 0..MAX_EXCHANGES {
+        let bytes = match request.to_bytes() {
+            Ok(bytes) => bytes,
+            Err(e) => return result.finish(ProbeStatus::InvalidResponse, e),
+        };
+        let started = Instant::now();
+        let data = match exchange(&socket, marker, &bytes, timeout) {
+            Ok(data) => data,
+            Err(e) if matches!(e.kind(), ErrorKind::ConnectionReset | ErrorKind::ConnectionRefused) => {
+                return result.finish(ProbeStatus::Unreachable, "Closed (ICMP port unreachable).".to_string());
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                let summary = format!("No IKE response within {} ms (filtered or no IKE service).", timeout.as_millis());
+                return result.finish(ProbeStatus::NoResponse, summary);
+            }
+            Err(e) => return result.finish(ProbeStatus::NoResponse, format!("UDP error: {}", e)),
+        };
+        result.round_trip_ms = Some(started.elapsed().as_millis() as u64);
+
+        let response = match parse_response(&data, &spi_i) {
+            Ok(response) => response,
+            Err(e) => return result.finish(ProbeStatus::InvalidResponse, e),
+        };
+        result.certificate_requested |= response.certificate_requested;
+        result.fragmentation_supported |= response.notify(NOTIFY_FRAGMENTATION_SUPPORTED).is_some();
+        result.vendor_ids.extend(response.vendor_ids.iter().map(|v| vendor_name(v)));
+        if let Some(hash) = response.notify(NOTIFY_NAT_DETECTION_DESTINATION_IP) {
+            result.local_behind_nat = Some(hash != nat_hash(&spi_i, &response.spi_r, local).as_slice());
+        }
+        if let Some(hash) = response.notify(NOTIFY_NAT_DETECTION_SOURCE_IP) {
+            result.responder_behind_nat = Some(hash != nat_hash(&spi_i, &response.spi_r, remote).as_slice());
+        }
+        for (notify_type, _) in response.notifies.iter().filter(|(t, _)| *t < 16384) {
+            result.notify_errors.push(notify_name(*notify_type));
+        }
+
+        if let Some(cookie) = response.notify(NOTIFY_COOKIE) {
+            result.cookie_required = true;
+            request.cookie = Some(cookie.to_vec());
+            continue;
+        }
+        if let Some(data) = response.notify(NOTIFY_INVALID_KE_PAYLOAD) {
+            let group = if data.len() >= 2 { be16(data, 0) } else { 0 };
+            result.requested_dh_group = Some(group);
+            let group_name = dh_name(group).map(str::to_string).unwrap_or_else(|| format!("group {}", group));
+            if group != request.ke_group && proposals.iter().any(|p| p.dh_group == group) {
+                request.ke_group = group;
+                continue;
+            }
+            let summary = format!("Responder requires DH {}, which none of the offered proposals use.", group_name);
+            return result.finish(ProbeStatus::InvalidKePayload, summary);
+        }
+        if response.notify(NOTIFY_NO_PROPOSAL_CHOSEN).is_some() {
+            let summary = format!("Responder accepted none of: {}.", result.offered.join(", "));
+            return result.finish(ProbeStatus::NoProposalChosen, summary);
+        }
+        if let Some(chosen) = response.chosen {
+            let name = chosen.to_string();
+            result.chosen_proposal = Some(name.clone());
+            let summary = format!("IKE responder answering, chose {}.", name);
+            return result.finish(ProbeStatus::Accepted, summary);
+        }
+        if let Some(error) = result.notify_errors.last().cloned() {
+            return result.finish(ProbeStatus::Rejected, format!("Responder returned {}.", error));
+        }
+        return result.finish(ProbeStatus::InvalidResponse, "Response carries neither an SA nor an error notify.".to_string());
+    }
+    result.finish(ProbeStatus::InvalidResponse, format!("No final answer after {} requests.", MAX_EXCHANGES))
+}
+
+/// Resolves `server` and probes it on `port`.
+pub fn probe(server: &str, port: u16, proposals: &[IkeProposal], timeout: Duration) -> Result<IkeProbeResult, String> {
+    let remote = (server, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve {}: {}", server, e))?
+        .next()
+        .ok_or(format!("No address found for {}.", server))?;
+    Ok(probe_addr(server, remote, proposals, timeout))
+}
+
+/// Probes UDP 500 and 4500 with an IKE_SA_INIT and returns the results as JSON.
+#[command]
+pub async fn probe_ike(
+    server: String,
+    ports: Option<Vec<u16>>,
+    proposals: Option<Vec<String>>,
+    timeout_ms: Option<u64>,
+) -> Result<String, String> {
+    if server.trim().is_empty() {
+        return Err("No VPN server address configured.".to_string());
+    }
+    let proposals = match proposals.filter(|p| !p.is_empty()) {
+        Some(list) => list.iter().map(|p| IkeProposal::parse(p)).collect::<Result<Vec<_>, _>>()?,
+        None => default_proposals(),
+    };
+    if proposals.len() > u8::MAX as usize {
+        return Err("Too many IKE proposals.".to_string());
+    }
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(3000).max(100));
+    let ports = ports.unwrap_or_else(|| vec![500, 4500]);
+    let server = server.trim().to_string();
+    // Resolving and waiting for UDP answers blocks, so keep it off the async runtime's workers.
+    let results = jobs::spawn_blocking(move || {
+        ports
+            .into_iter()
+            .map(|port| probe(&server, port, &proposals, timeout))
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .await
+    .map_err(|e| format!("IKE probe failed: {}", e))??;
+    serde_json::to_string(&results).map_err(|e| format!("Failed to serialize IKE probe results: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPI_R: [u8; 8] = [0x52; 8];
+
+    fn proposals(names: &[&str]) -> Vec<IkeProposal> {
+        names.iter().map(|name| IkeProposal::parse(name).unwrap()).collect()
+    }
+
+    /// Payload types and bodies of an IKE message, in order.
+    fn payloads(message: &[u8]) -> Vec<(u8, Vec<u8>)> {
+        let mut out = Vec::new();
+        let mut next = message[16];
+        let mut at = 28;
+        while next != PAYLOAD_NONE {
+            let len = be16(message, at + 2) as usize;
+            out.push((next, message[at + 4..at + len].to_vec()));
+            next = message[at];
+            at += len;
+        }
+        out
+    }
+
+    fn ke_group(request: &[u8]) -> u16 {
+        let (_, body) = payloads(request).into_iter().find(|(kind, _)| *kind == PAYLOAD_KE).unwrap();
+        be16(&body, 0)
+    }
+
+    /// An IKE_SA_INIT response to `request` carrying `chunks`.
+    fn response(request: &[u8], chunks: &[(u8, Vec<u8>)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (i, (_, data)) in chunks.iter().enumerate() {
+            body.push(chunks.get(i + 1).map_or(PAYLOAD_NONE, |(kind, _)| *kind));
+            body.push(0);
+            body.extend((4 + data.len() as u16).to_be_bytes());
+            body.extend(data);
+        }
+        let mut message = request[..8].to_vec();
+        message.extend(SPI_R);
+        message.extend([chunks.first().map_or(PAYLOAD_NONE, |(kind, _)| *kind), 0x20, EXCHANGE_IKE_SA_INIT, FLAG_RESPONSE]);
+        message.extend(0u32.to_be_bytes());
+        message.extend((28 + body.len() as u32).to_be_bytes());
+        message.extend(body);
+        message
+    }
+
+    fn sa(proposal: &str) -> (u8, Vec<u8>) {
+        (PAYLOAD_SA, IkeProposal::parse(proposal).unwrap().to_bytes(1, true))
+    }
+
+    fn notify(notify_type: u16, data: &[u8]) -> (u8, Vec<u8>) {
+        (PAYLOAD_NOTIFY, notify_body(notify_type, data))
+    }
+
+    /// Builds the answer to one request from the request and the address it came from.
+    type Answer = Box<dyn FnOnce(&[u8], SocketAddr) -> Vec<u8> + Send>;
+
+    /// A responder on 127.0.0.1 that answers one request per entry of `rounds`.
+    fn responder(rounds: Vec<Answer>) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut buf = vec![0u8; 65535];
+            for answer in rounds {
+                let (len, from) = socket.recv_from(&mut buf).unwrap();
+                socket.send_to(&answer(&buf[..len], from), from).unwrap();
+            }
+        });
+        addr
+    }
+
+    fn probe_local(remote: SocketAddr, offered: &[&str]) -> IkeProbeResult {
+        probe_addr("test", remote, &proposals(offered), Duration::from_secs(2))
+    }
+
+    #[test]
+    fn proposals_round_trip_through_display() {
+        for name in [
+            "aes256-sha256-modp2048",
+            "aes128-sha1-modp1024",
+            "3des-sha1-modp1024",
+            "aes256-sha384-ecp384",
+            "aes256gcm16-prfsha384-ecp384",
+            "aes128-sha256-prfsha384-ecp256",
+            "aes256-sha512-modp2048s256",
+        ] {
+            let proposal = IkeProposal::parse(name).unwrap();
+            assert_eq!(proposal.to_string(), name);
+            assert_eq!(IkeProposal::parse(&proposal.to_string()).unwrap(), proposal);
+            assert_eq!(parse_chosen_proposal(&proposal.to_bytes(1, true)).unwrap(), proposal);
+        }
+        assert_eq!(IkeProposal::parse(" AES256-SHA256-MODP2048 ").unwrap().to_string(), "aes256-sha256-modp2048");
+        assert_eq!(IkeProposal::parse("aes256gcm16-sha384-ecp384").unwrap().to_string(), "aes256gcm16-prfsha384-ecp384");
+    }
+
+    #[test]
+    fn invalid_proposals_are_rejected() {
+        for name in ["", "aes256-modp2048", "aes256-sha256", "blowfish-sha1-modp1024", "aesx-sha1-modp1024", "aes256-sha256-modp999", "aes256-md5-modp2048", "aes256gcm16-modp2048", "aes256-sha256-prfmd5-modp2048"] {
+            assert!(IkeProposal::parse(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn accepted_proposal_and_nat_detection() {
+        let remote = responder(vec![Box::new(|request, from| {
+            let spi_i: [u8; 8] = request[..8].try_into().unwrap();
+            let server: SocketAddr = "127.0.0.1:0".parse().unwrap();
+            response(request, &[
+                sa("aes256-sha256-modp2048"),
+                notify(NOTIFY_NAT_DETECTION_SOURCE_IP, &nat_hash(&spi_i, &SPI_R, server)),
+                notify(NOTIFY_NAT_DETECTION_DESTINATION_IP, &nat_hash(&spi_i, &SPI_R, from)),
+                notify(NOTIFY_FRAGMENTATION_SUPPORTED, &[]),
+                (PAYLOAD_CERTREQ, vec![4]),
+                (PAYLOAD_VENDOR, from_hex("fb1de3cdf341b7ea16b7e5be0855f120")),
+            ])
+        })]);
+        let result = probe_local(remote, &["aes128-sha1-modp1024", "aes256-sha256-modp2048"]);
+        assert_eq!(result.status, ProbeStatus::Accepted, "{}", result.summary);
+        assert_eq!(result.chosen_proposal.as_deref(), Some("aes256-sha256-modp2048"));
+        assert_eq!(result.local_behind_nat, Some(false));
+        // The responder hashed port 0 instead of the port it answers from.
+        assert_eq!(result.responder_behind_nat, Some(true));
+        assert!(result.fragmentation_supported);
+        assert!(result.certificate_requested);
+        assert_eq!(result.vendor_ids, ["Windows IKE CGA"]);
+        assert!(result.round_trip_ms.is_some());
+    }
+
+    #[test]
+    fn no_proposal_chosen() {
+        let remote = responder(vec![Box::new(|request, _| response(request, &[notify(NOTIFY_NO_PROPOSAL_CHOSEN, &[])]))]);
+        let result = probe_local(remote, &["3des-sha1-modp1024"]);
+        assert_eq!(result.status, ProbeStatus::NoProposalChosen);
+        assert_eq!(result.notify_errors, ["NO_PROPOSAL_CHOSEN (14)"]);
+        assert!(result.summary.contains("3des-sha1-modp1024"));
+    }
+
+    #[test]
+    fn invalid_ke_payload_switches_to_an_offered_group() {
+        let remote = responder(vec![
+            Box::new(|request, _| {
+                assert_eq!(ke_group(request), 2);
+                response(request, &[notify(NOTIFY_INVALID_KE_PAYLOAD, &14u16.to_be_bytes())])
+            }),
+            Box::new(|request, _| {
+                assert_eq!(ke_group(request), 14);
+                response(request, &[sa("aes256-sha256-modp2048")])
+            }),
+        ]);
+        let result = probe_local(remote, &["aes128-sha1-modp1024", "aes256-sha256-modp2048"]);
+        assert_eq!(result.status, ProbeStatus::Accepted, "{}", result.summary);
+        assert_eq!(result.requested_dh_group, Some(14));
+        assert_eq!(result.chosen_proposal.as_deref(), Some("aes256-sha256-modp2048"));
+    }
+
+    #[test]
+    fn invalid_ke_payload_for_a_group_not_offered() {
+        let remote = responder(vec![Box::new(|request, _| {
+            response(request, &[notify(NOTIFY_INVALID_KE_PAYLOAD, &19u16.to_be_bytes())])
+        })]);
+        let result = probe_local(remote, &["aes256-sha256-modp2048"]);
+        assert_eq!(result.status, ProbeStatus::InvalidKePayload);
+        assert_eq!(result.requested_dh_group, Some(19));
+        assert!(result.summary.contains("ecp256"), "{}", result.summary);
+    }
+
+    #[test]
+    fn cookie_is_echoed_in_the_next_request() {
+        let remote = responder(vec![
+            Box::new(|request, _| {
+                assert!(payloads(request).iter().all(|(kind, body)| *kind != PAYLOAD_NOTIFY || be16(body, 2) != NOTIFY_COOKIE));
+                response(request, &[notify(NOTIFY_COOKIE, b"cookie-data")])
+            }),
+            Box::new(|request, _| {
+                assert_eq!(payloads(request)[0], notify(NOTIFY_COOKIE, b"cookie-data"));
+                response(request, &[sa("aes256-sha256-modp2048")])
+            }),
+        ]);
+        let result = probe_local(remote, &["aes256-sha256-modp2048"]);
+        assert_eq!(result.status, ProbeStatus::Accepted, "{}", result.summary);
+        assert!(result.cookie_required);
+    }
+
+    #[test]
+    fn truncated_and_garbage_responses_are_invalid() {
+        let remote = responder(vec![Box::new(|request, _| response(request, &[sa("aes256-sha256-modp2048")])[..20].to_vec())]);
+        let result = probe_local(remote, &["aes256-sha256-modp2048"]);
+        assert_eq!(result.status, ProbeStatus::InvalidResponse);
+        assert!(result.summary.contains("too short"), "{}", result.summary);
+
+        let remote = responder(vec![Box::new(|request, _| {
+            let mut message = response(request, &[sa("aes256-sha256-modp2048")]);
+            let len = message.len();
+            // Claim a payload longer than the datagram.
+            message[30..32].copy_from_slice(&(len as u16).to_be_bytes());
+            message
+        })]);
+        let result = probe_local(remote, &["aes256-sha256-modp2048"]);
+        assert_eq!(result.status, ProbeStatus::InvalidResponse);
+        assert!(result.summary.contains("Invalid length"), "{}", result.summary);
+
+        let remote = responder(vec![Box::new(|_, _| b"garbage that is not an IKE message at all".to_vec())]);
+        let result = probe_local(remote, &["aes256-sha256-modp2048"]);
+        assert_eq!(result.status, ProbeStatus::InvalidResponse);
+        assert!(result.summary.contains("different initiator SPI"), "{}", result.summary);
+    }
+
+    #[test]
+    fn silent_responder_times_out() {
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let result = probe_addr("test", silent.local_addr().unwrap(), &proposals(&["aes256-sha256-modp2048"]), Duration::from_millis(200));
+        assert_eq!(result.status, ProbeStatus::NoResponse);
+    }
+}
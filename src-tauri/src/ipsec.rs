@@ -0,0 +1,115 @@
+/// The custom IPsec cryptography set applied by the iOS/Mac compatibility buttons.
+/// Windows defaults to weaker proposals than Apple and strongSwan clients accept,
+/// so every exporter and `Set-VpnConnectionIPsecConfiguration` call uses this one set.
+pub struct IpsecPreset {
+    pub authentication_transform: &'static str,
+    pub cipher_transform: &'static str,
+    pub dh_group: &'static str,
+    pub encryption_method: &'static str,
+    pub integrity_check_method: &'static str,
+    pub pfs_group: &'static str,
+}
+
+pub const IOS_MAC_PRESET: IpsecPreset = IpsecPreset {
+    authentication_transform: "SHA256128",
+    cipher_transform: "AES256",
+    dh_group: "Group14",
+    encryption_method: "AES256",
+    integrity_check_method: "SHA256",
+    pfs_group: "PFS2048",
+};
+
+impl IpsecPreset {
+    /// Parameters for `Set-VpnConnectionIPsecConfiguration`.
+    pub fn powershell_args(&self) -> String {
+        format!(
+            "-AuthenticationTransformConstants {} -CipherTransformConstants {} -DHGroup {} -EncryptionMethod {} -IntegrityCheckMethod {} -PFSgroup {}",
+            self.authentication_transform,
+            self.cipher_transform,
+            self.dh_group,
+            self.encryption_method,
+            self.integrity_check_method,
+            self.pfs_group
+        )
+    }
+
+    /// Key size in bits of the IKE encryption algorithm (AES256 -> 256).
+    pub fn encryption_key_bits(&self) -> u32 {
+        self.encryption_method.trim_start_matches("AES").parse().unwrap_or(256)
+    }
+
+    /// Hash size in bits of the IKE integrity algorithm (SHA256 -> 256).
+    pub fn integrity_bits(&self) -> u32 {
+        self.integrity_check_method.trim_start_matches("SHA").parse().unwrap_or(256)
+    }
+
+    /// IANA Diffie-Hellman group number for IKE (Group14 -> 14).
+    pub fn dh_group_number(&self) -> u32 {
+        match self.dh_group {
+            "Group1" => 1,
+            "Group2" => 2,
+            "Group14" => 14,
+            "ECP256" => 19,
+            "ECP384" => 20,
+            "Group24" => 24,
+            _ => 14,
+        }
+    }
+
+    /// IANA Diffie-Hellman group number used for Child SA PFS (PFS2048 -> 14).
+    pub fn pfs_group_number(&self) -> u32 {
+        match self.pfs_group {
+            "PFS1" => 1,
+            "PFS2" => 2,
+            "PFS2048" => 14,
+            "ECP256" => 19,
+            "ECP384" => 20,
+            "PFS24" => 24,
+            _ => 14,
+        }
+    }
+
+    /// Apple `EncryptionAlgorithm` value for IKE and Child SA parameters.
+    pub fn apple_encryption_algorithm(&self) -> String {
+        format!("AES-{}", self.encryption_key_bits())
+    }
+
+    /// Apple `IntegrityAlgorithm` value for IKE and Child SA parameters.
+    pub fn apple_integrity_algorithm(&self) -> String {
+        format!("SHA2-{}", self.integrity_bits())
+    }
+
+    /// strongSwan keyword for a DH group number.
+    fn strongswan_dh(group: u32) -> &'static str {
+        match group {
+            1 => "modp768",
+            2 => "modp1024",
+            19 => "ecp256",
+            20 => "ecp384",
+            24 => "modp2048s256",
+            _ => "modp2048",
+        }
+    }
+
+    /// strongSwan IKE proposal string (e.g. `aes256-sha256-modp2048`).
+    pub fn strongswan_ike_proposal(&self) -> String {
+        format!(
+            "aes{}-sha{}-{}",
+            self.encryption_key_bits(),
+            self.integrity_bits(),
+            Self::strongswan_dh(self.dh_group_number())
+        )
+    }
+
+    /// strongSwan ESP proposal string including the PFS group.
+    pub fn strongswan_esp_proposal(&self) -> String {
+        let cipher_bits = self.cipher_transform.trim_start_matches("AES").parse::<u32>().unwrap_or(256);
+        let auth_bits = self
+            .authentication_transform
+            .trim_start_matches("SHA")
+            .get(..3)
+            .and_then(|b| b.parse::<u32>().ok())
+            .unwrap_or(256);
+        format!("aes{}-sha{}-{}", cipher_bits, auth_bits, Self::strongswan_dh(self.pfs_group_number()))
+    }
+}
@@ -0,0 +1,595 @@
+use crate::config::VpnConfig;
+use crate::support_bundle::RedactionOptions;
+use crate::vpn_deploy::DeployMode;
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{command, AppHandle, Emitter};
+
+pub const PROGRESS_EVENT: &str = "job://progress";
+/// Finished jobs kept for `list_jobs`.
+const HISTORY_LIMIT: usize = 50;
+const DEFAULT_TIMEOUT_SECS: u64 = 600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JobState {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+    TimedOut,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: String,
+    pub operation: String,
+    pub state: JobState,
+    pub started: String,
+    pub finished: Option<String>,
+    pub result: Option<String>,
+    pub error: Option<String>,
+    /// Set by `cancel_job` or the timeout; the job stays `Running` until its worker has stopped.
+    pub cancel_requested: bool,
+    /// Progress lines reported while the job ran.
+    pub log: Vec<String>,
+}
+
+/// Payload of `job://progress`: a log line while running, or the final state with its result.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgressEvent<'a> {
+    job_id: &'a str,
+    operation: &'a str,
+    state: JobState,
+    cancel_requested: bool,
+    message: Option<&'a str>,
+    result: Option<&'a str>,
+    error: Option<&'a str>,
+}
+
+struct Entry {
+    job: Job,
+    cancel: Arc<AtomicBool>,
+}
+
+static JOBS: Mutex<VecDeque<Entry>> = Mutex::new(VecDeque::new());
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Publishes a job change, with the progress line if there is one. `job://progress` in the app.
+type Sink = Arc<dyn Fn(&Job, Option<&str>) + Send + Sync>;
+
+/// The job running on the current thread, so deep helpers can report progress without extra parameters.
+#[derive(Clone)]
+struct JobHandle {
+    id: String,
+    emit: Sink,
+    cancel: Arc<AtomicBool>,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<JobHandle>> = const { RefCell::new(None) };
+    /// Depth of `uncancellable` scopes on this thread.
+    static UNCANCELLABLE: Cell<u32> = const { Cell::new(0) };
+}
+
+fn now() -> String {
+    chrono::Local::now().to_rfc3339()
+}
+
+fn sink(app: &AppHandle) -> Sink {
+    let app = app.clone();
+    Arc::new(move |job, message| emit(&app, job, message))
+}
+
+fn emit(app: &AppHandle, job: &Job, message: Option<&str>) {
+    let _ = app.emit(
+        PROGRESS_EVENT,
+        ProgressEvent {
+            job_id: &job.id,
+            operation: &job.operation,
+            state: job.state,
+            cancel_requested: job.cancel_requested,
+            message,
+            result: job.result.as_deref(),
+            error: job.error.as_deref(),
+        },
+    );
+}
+
+/// Reports a progress line for the job running on this thread. Does nothing outside a job.
+pub fn progress(message: &str) {
+    let Some(handle) = CURRENT.with(|c| c.borrow().clone()) else { return };
+    let mut jobs = JOBS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(entry) = jobs.iter_mut().find(|e| e.job.id == handle.id) {
+        entry.job.log.push(message.to_string());
+        (handle.emit)(&entry.job, Some(message));
+    }
+}
+
+/// Fails once the job running on this thread was cancelled or timed out, except inside `uncancellable`.
+pub fn check_cancelled() -> Result<(), String> {
+    if UNCANCELLABLE.with(|depth| depth.get()) > 0 {
+        return Ok(());
+    }
+    match CURRENT.with(|c| c.borrow().as_ref().map(|h| h.cancel.load(Ordering::Relaxed))) {
+        Some(true) => Err("Job cancelled.".to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// `thread::sleep` that wakes up early when the current job is cancelled.
+pub fn sleep(duration: Duration) -> Result<(), String> {
+    let step = Duration::from_millis(250);
+    let mut left = duration;
+    while !left.is_zero() {
+        check_cancelled()?;
+        let chunk = left.min(step);
+        std::thread::sleep(chunk);
+        left -= chunk;
+    }
+    check_cancelled()
+}
+
+/// Runs `work` with cancellation ignored, for steps that must not be abandoned halfway, such as a
+/// rollback or waiting for a SYSTEM task that has already been started.
+pub fn uncancellable<T>(work: impl FnOnce() -> T) -> T {
+    struct Scope;
+    impl Drop for Scope {
+        fn drop(&mut self) {
+            UNCANCELLABLE.with(|depth| depth.set(depth.get() - 1));
+        }
+    }
+    UNCANCELLABLE.with(|depth| depth.set(depth.get() + 1));
+    let _scope = Scope;
+    work()
+}
+
+/// The job and `uncancellable` scope of the current thread. Both are thread-local, so work moved
+/// to another thread has to take them along with `enter`, or `progress` and `check_cancelled`
+/// do nothing there.
+#[derive(Clone)]
+pub struct JobContext {
+    handle: Option<JobHandle>,
+    uncancellable: u32,
+}
+
+pub fn context() -> JobContext {
+    JobContext {
+        handle: CURRENT.with(|c| c.borrow().clone()),
+        uncancellable: UNCANCELLABLE.with(|depth| depth.get()),
+    }
+}
+
+impl JobContext {
+    /// Runs `work` on the current thread as part of this context's job.
+    pub fn enter<T>(self, work: impl FnOnce() -> T) -> T {
+        struct Restore(JobContext);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT.with(|c| *c.borrow_mut() = self.0.handle.take());
+                UNCANCELLABLE.with(|depth| depth.set(self.0.uncancellable));
+            }
+        }
+        let previous = JobContext {
+            handle: CURRENT.with(|c| c.replace(self.handle)),
+            uncancellable: UNCANCELLABLE.with(|depth| depth.replace(self.uncancellable)),
+        };
+        let _restore = Restore(previous);
+        work()
+    }
+}
+
+/// `tauri::async_runtime::spawn_blocking` that keeps the current job, so the blocking work can
+/// still report progress and be cancelled.
+pub fn spawn_blocking<F, T>(work: F) -> tauri::async_runtime::JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let context = context();
+    tauri::async_runtime::spawn_blocking(move || context.enter(work))
+}
+
+/// Marks the job as cancel requested and raises its cancel flag. The worker stops at its next
+/// `check_cancelled`; the job is only finished once it has.
+fn request_cancel(emit: &Sink, id: &str, message: &str) {
+    let mut jobs = JOBS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(entry) = jobs.iter_mut().find(|e| e.job.id == id) {
+        entry.cancel.store(true, Ordering::Relaxed);
+        if entry.job.state == JobState::Running && !entry.job.cancel_requested {
+            entry.job.cancel_requested = true;
+            entry.job.log.push(message.to_string());
+            emit(&entry.job, Some(message));
+        }
+    }
+}
+
+fn finish(emit: &Sink, id: &str, state: JobState, outcome: Result<String, String>) {
+    let mut jobs = JOBS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(entry) = jobs.iter_mut().find(|e| e.job.id == id) {
+        if entry.job.state == JobState::Running {
+            entry.job.state = state;
+            entry.job.finished = Some(now());
+            match outcome {
+                Ok(result) => entry.job.result = Some(result),
+                Err(error) => entry.job.error = Some(error),
+            }
+            emit(&entry.job, None);
+        }
+    }
+
+    let finished = jobs.iter().filter(|e| e.job.state != JobState::Running).count();
+    if finished > HISTORY_LIMIT {
+        let mut excess = finished - HISTORY_LIMIT;
+        jobs.retain(|e| {
+            let drop = excess > 0 && e.job.state != JobState::Running;
+            if drop {
+                excess -= 1;
+            }
+            !drop
+        });
+    }
+}
+
+/// Runs `work` on its own thread and returns the job id immediately.
+pub fn spawn_job<F>(app: &AppHandle, operation: &str, timeout: Duration, work: F) -> String
+where
+    F: FnOnce() -> Result<String, String> + Send + 'static,
+{
+    spawn_job_with(sink(app), operation, timeout, work)
+}
+
+fn spawn_job_with<F>(emit: Sink, operation: &str, timeout: Duration, work: F) -> String
+where
+    F: FnOnce() -> Result<String, String> + Send + 'static,
+{
+    let id = format!(
+        "{}-{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    let cancel = Arc::new(AtomicBool::new(false));
+    let job = Job {
+        id: id.clone(),
+        operation: operation.to_string(),
+        state: JobState::Running,
+        started: now(),
+        finished: None,
+        result: None,
+        error: None,
+        cancel_requested: false,
+        log: Vec::new(),
+    };
+    emit(&job, None);
+    JOBS.lock().unwrap_or_else(|e| e.into_inner()).push_back(Entry { job, cancel: cancel.clone() });
+
+    let (tx, rx) = mpsc::channel();
+    let handle = JobHandle { id: id.clone(), emit: emit.clone(), cancel: cancel.clone() };
+    std::thread::spawn(move || {
+        CURRENT.with(|c| *c.borrow_mut() = Some(handle));
+        let _ = tx.send(work());
+    });
+
+    let job_id = id.clone();
+    std::thread::spawn(move || {
+        let mut timed_out = false;
+        let received = match rx.recv_timeout(timeout) {
+            Err(RecvTimeoutError::Timeout) => {
+                timed_out = true;
+                request_cancel(&emit, &job_id, &format!("Timed out after {} seconds, stopping", timeout.as_secs()));
+                rx.recv().map_err(|_| ())
+            }
+            other => other.map_err(|_| ()),
+        };
+        let (state, outcome) = match received {
+            Ok(Ok(result)) => (JobState::Succeeded, Ok(result)),
+            Ok(Err(_)) if timed_out => (JobState::TimedOut, Err(format!("Job timed out after {} seconds.", timeout.as_secs()))),
+            Ok(Err(_)) if cancel.load(Ordering::Relaxed) => (JobState::Cancelled, Err("Job cancelled.".to_string())),
+            Ok(Err(error)) => (JobState::Failed, Err(error)),
+            Err(()) => (JobState::Failed, Err("Job terminated unexpectedly.".to_string())),
+        };
+        finish(&emit, &job_id, state, outcome);
+    });
+    id
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct JobArgs {
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+    mode: Option<DeployMode>,
+    remove_task: Option<bool>,
+    test_connection: Option<bool>,
+    id: String,
+    root_hash: String,
+    server: String,
+    port: Option<u16>,
+    ports: Option<Vec<u16>>,
+    proposals: Option<Vec<String>>,
+    timeout_ms: Option<u64>,
+    hours: Option<u32>,
+    max_events: Option<u32>,
+    path: String,
+    redaction: Option<RedactionOptions>,
+    disable: bool,
+}
+
+/// Generates `OPERATIONS` and `run_operation` from one list, so every operation accepted by
+/// `start_job` has exactly one dispatch arm. Arguments use the same camelCase keys as `invoke`.
+macro_rules! operations {
+    ($app:ident, $a:ident; $($name:literal => $call:expr,)*) => {
+        /// Commands that can run as a job.
+        const OPERATIONS: &[&str] = &[$($name),*];
+
+        async fn run_operation($app: AppHandle, operation: &str, $a: JobArgs) -> Result<String, String> {
+            use crate::{backups, diagnostics_report, event_log, ike_probe, mtu_probe, net_checks, profile_drift, sstp_probe, support_bundle, verification, vpn_commands, vpn_deploy};
+            match operation {
+                $($name => $call.await,)*
+                other => Err(format!("Unknown job operation '{}'.", other)),
+            }
+        }
+    };
+}
+
+operations! { app, a;
+    "deploy_device_tunnel" => vpn_deploy::deploy_device_tunnel(app, a.config, a.profile_id, a.mode),
+    "deploy_user_tunnel" => vpn_deploy::deploy_user_tunnel(app, a.config, a.profile_id, a.mode),
+    "deploy_user_tunnel_all" => vpn_deploy::deploy_user_tunnel_all(app, a.config, a.profile_id),
+    "remove_device_tunnel" => vpn_deploy::remove_device_tunnel(app, a.config, a.profile_id, a.remove_task),
+    "remove_user_tunnel" => vpn_deploy::remove_user_tunnel(app, a.config, a.profile_id),
+    "configure_device_ipsec" => vpn_deploy::configure_device_ipsec(app, a.config, a.profile_id),
+    "configure_user_ipsec" => vpn_deploy::configure_user_ipsec(app, a.config, a.profile_id),
+    "enable_task_scheduler_trigger" => vpn_deploy::enable_task_scheduler_trigger(app, a.config, a.profile_id),
+    "restore_backup" => backups::restore_backup(app, a.id),
+    "verify_tunnels" => verification::verify_tunnels(app, a.config, a.profile_id, a.test_connection),
+    "check_profile_drift" => profile_drift::check_profile_drift(app, a.config, a.profile_id),
+    "check_certificates" => vpn_commands::check_certificates(&a.root_hash),
+    "check_routes" => vpn_commands::check_routes(),
+    "check_dns" => net_checks::check_dns(app, a.server, a.config, a.profile_id, a.timeout_ms),
+    "check_ports" => net_checks::check_ports(a.server, a.timeout_ms),
+    "probe_ike" => ike_probe::probe_ike(a.server, a.ports, a.proposals, a.timeout_ms),
+    "probe_sstp" => sstp_probe::probe_sstp(app, a.config, a.profile_id, a.port, a.timeout_ms),
+    "diagnose_mtu" => mtu_probe::diagnose_mtu(app, a.config, a.profile_id, a.timeout_ms),
+    "analyze_vpn_events" => event_log::analyze_vpn_events(a.hours, a.max_events),
+    "create_support_bundle" => support_bundle::create_support_bundle(app, a.path, a.config, a.profile_id, a.redaction),
+    "generate_diagnostics_report" => diagnostics_report::generate_diagnostics_report(app, a.path, a.config, a.profile_id),
+    "audit_templates" => vpn_commands::audit_templates(),
+    "install_rras_role" => vpn_commands::install_rras_role(app),
+    "install_nps_role" => vpn_commands::install_nps_role(app),
+    "install_iis_role" => vpn_commands::install_iis_role(app),
+    "set_sstp_revocation" => vpn_deploy::set_sstp_revocation(app, a.disable),
+}
+
+#[command]
+pub async fn start_job(
+    app: AppHandle,
+    operation: String,
+    args: Option<serde_json::Value>,
+    timeout_secs: Option<u64>,
+) -> Result<String, String> {
+    if !OPERATIONS.contains(&operation.as_str()) {
+        return Err(format!("Unknown job operation '{}'.", operation));
+    }
+    let args: JobArgs = match args {
+        Some(value) => serde_json::from_value(value).map_err(|e| format!("Invalid job arguments: {}", e))?,
+        None => JobArgs::default(),
+    };
+    let timeout = Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS).max(1));
+
+    let worker_app = app.clone();
+    let op = operation.clone();
+    Ok(spawn_job(&app, &operation, timeout, move || {
+        tauri::async_runtime::block_on(run_operation(worker_app, &op, args))
+    }))
+}
+
+#[command]
+pub async fn cancel_job(app: AppHandle, id: String) -> Result<String, String> {
+    {
+        let jobs = JOBS.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = jobs.iter().find(|e| e.job.id == id).ok_or(format!("Job '{}' not found.", id))?;
+        if entry.job.state != JobState::Running {
+            return Ok(format!("Job '{}' already finished ({:?}).", id, entry.job.state));
+        }
+    }
+    request_cancel(&sink(&app), &id, "Cancellation requested, stopping at the next safe point");
+    Ok(format!("Cancellation of job '{}' requested.", id))
+}
+
+#[command]
+pub async fn get_job(id: String) -> Result<String, String> {
+    let jobs = JOBS.lock().unwrap_or_else(|e| e.into_inner());
+    let entry = jobs.iter().find(|e| e.job.id == id).ok_or(format!("Job '{}' not found.", id))?;
+    serde_json::to_string(&entry.job).map_err(|e| format!("Failed to serialize job: {}", e))
+}
+
+/// All running and recent jobs, newest first.
+#[command]
+pub async fn list_jobs() -> Result<String, String> {
+    let jobs = JOBS.lock().unwrap_or_else(|e| e.into_inner());
+    let list: Vec<&Job> = jobs.iter().rev().map(|e| &e.job).collect();
+    serde_json::to_string(&list).map_err(|e| format!("Failed to serialize jobs: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// Every event a job published: state, cancel flag and progress line.
+    type Events = Arc<Mutex<Vec<(JobState, bool, Option<String>)>>>;
+
+    /// `JOBS` is global and `history_is_capped` trims it, so the tests take turns.
+    fn serial() -> std::sync::MutexGuard<'static, ()> {
+        static SERIAL: Mutex<()> = Mutex::new(());
+        SERIAL.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn recorder() -> (Sink, Events) {
+        let events: Events = Arc::default();
+        let sink_events = events.clone();
+        let sink: Sink = Arc::new(move |job, message| {
+            sink_events.lock().unwrap().push((job.state, job.cancel_requested, message.map(str::to_string)));
+        });
+        (sink, events)
+    }
+
+    /// Waits for the final event and returns the state it reports.
+    fn wait(events: &Events) -> JobState {
+        let started = Instant::now();
+        loop {
+            if let Some((state, _, _)) = events.lock().unwrap().iter().find(|(s, _, _)| *s != JobState::Running) {
+                return *state;
+            }
+            assert!(started.elapsed() < Duration::from_secs(10), "job did not finish");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn job(id: &str) -> Option<Job> {
+        JOBS.lock().unwrap().iter().find(|e| e.job.id == id).map(|e| e.job.clone())
+    }
+
+    fn lines(events: &Events) -> Vec<String> {
+        events.lock().unwrap().iter().filter_map(|(_, _, m)| m.clone()).collect()
+    }
+
+    #[test]
+    fn results_errors_and_progress_are_recorded() {
+        let _serial = serial();
+        let (sink, events) = recorder();
+        let id = spawn_job_with(sink, "test", Duration::from_secs(10), || {
+            progress("step 1");
+            Ok("done".to_string())
+        });
+        assert_eq!(wait(&events), JobState::Succeeded);
+        let finished = job(&id).unwrap();
+        assert_eq!(finished.result.as_deref(), Some("done"));
+        assert_eq!(finished.log, ["step 1"]);
+        assert!(finished.finished.is_some());
+
+        let (sink, events) = recorder();
+        let id = spawn_job_with(sink, "test", Duration::from_secs(10), || Err("broken".to_string()));
+        assert_eq!(wait(&events), JobState::Failed);
+        assert_eq!(job(&id).unwrap().error.as_deref(), Some("broken"));
+
+        // Outside a job these do nothing.
+        progress("ignored");
+        assert!(check_cancelled().is_ok());
+    }
+
+    #[test]
+    fn timeout_stops_the_worker_at_its_next_check() {
+        let _serial = serial();
+        let (sink, events) = recorder();
+        let started = Instant::now();
+        let id = spawn_job_with(sink, "test", Duration::from_millis(100), || {
+            sleep(Duration::from_secs(30))?;
+            Ok("too late".to_string())
+        });
+        assert_eq!(wait(&events), JobState::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        let finished = job(&id).unwrap();
+        assert!(finished.cancel_requested);
+        assert!(finished.error.unwrap().starts_with("Job timed out"));
+        // Cancel was requested while the job was still running, before it finished.
+        assert!(events.lock().unwrap().iter().any(|(s, cancel, _)| *s == JobState::Running && *cancel));
+    }
+
+    #[test]
+    fn cancellation_waits_for_uncancellable_steps() {
+        let _serial = serial();
+        let (sink, events) = recorder();
+        let (started_tx, started_rx) = mpsc::channel();
+        let id = spawn_job_with(sink.clone(), "test", Duration::from_secs(10), move || {
+            let completed = uncancellable(|| {
+                started_tx.send(()).unwrap();
+                sleep(Duration::from_millis(400)).map(|_| check_cancelled().is_ok())
+            })?;
+            progress(&format!("rollback completed: {}", completed));
+            check_cancelled()?;
+            Ok("not cancelled".to_string())
+        });
+        started_rx.recv().unwrap();
+        request_cancel(&sink, &id, "cancel");
+        assert_eq!(job(&id).unwrap().state, JobState::Running);
+        assert_eq!(wait(&events), JobState::Cancelled);
+        assert_eq!(lines(&events), ["cancel", "rollback completed: true"]);
+        assert_eq!(job(&id).unwrap().error.as_deref(), Some("Job cancelled."));
+
+        // Work that completes despite the request still succeeds.
+        let (sink, events) = recorder();
+        let (started_tx, started_rx) = mpsc::channel();
+        let id = spawn_job_with(sink.clone(), "test", Duration::from_secs(10), move || {
+            uncancellable(|| {
+                started_tx.send(()).unwrap();
+                std::thread::sleep(Duration::from_millis(200));
+            });
+            Ok("finished anyway".to_string())
+        });
+        started_rx.recv().unwrap();
+        request_cancel(&sink, &id, "cancel");
+        assert_eq!(wait(&events), JobState::Succeeded);
+    }
+
+    #[test]
+    fn blocking_work_keeps_the_job() {
+        let _serial = serial();
+        let (sink, events) = recorder();
+        let (cancel_tx, cancel_rx) = mpsc::channel::<()>();
+        let id = spawn_job_with(sink.clone(), "test", Duration::from_secs(10), move || {
+            tauri::async_runtime::block_on(spawn_blocking(move || {
+                progress("on a blocking thread");
+                cancel_rx.recv().unwrap();
+                check_cancelled()
+            }))
+            .map_err(|e| e.to_string())??;
+            Ok("not cancelled".to_string())
+        });
+        let started = Instant::now();
+        while lines(&events).is_empty() {
+            assert!(started.elapsed() < Duration::from_secs(10), "no progress from the blocking thread");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        request_cancel(&sink, &id, "cancel");
+        cancel_tx.send(()).unwrap();
+        assert_eq!(wait(&events), JobState::Cancelled);
+        assert_eq!(job(&id).unwrap().log, ["on a blocking thread", "cancel"]);
+    }
+
+    #[test]
+    fn history_is_capped() {
+        let _serial = serial();
+        let mut all = Vec::new();
+        for _ in 0..HISTORY_LIMIT + 10 {
+            let (sink, events) = recorder();
+            spawn_job_with(sink, "test", Duration::from_secs(10), || Ok(String::new()));
+            all.push(events);
+        }
+        for events in &all {
+            wait(events);
+        }
+        // A finished job is recorded before the history is trimmed; wait for the last trim.
+        std::thread::sleep(Duration::from_millis(50));
+        let jobs = JOBS.lock().unwrap();
+        assert!(jobs.iter().filter(|e| e.job.state != JobState::Running).count() <= HISTORY_LIMIT);
+    }
+
+    #[test]
+    fn operations_are_unique() {
+        let _serial = serial();
+        let mut names = OPERATIONS.to_vec();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), OPERATIONS.len());
+        assert!(OPERATIONS.contains(&"install_iis_role") && OPERATIONS.contains(&"set_sstp_revocation"));
+    }
+}
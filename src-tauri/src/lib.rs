@@ -1,9 +1,36 @@
+mod audit;
+mod auto_connect_task;
+mod backups;
 mod config;
+mod config_file;
+mod config_layers;
+mod diagnostics_report;
+mod event_analysis;
+mod event_log;
+mod export_mobileconfig;
+mod export_nmconnection;
+mod export_strongswan;
+mod ike_probe;
+mod ipsec;
+mod jobs;
+mod mtu_probe;
+mod net_checks;
+mod phonebook;
+mod profile_drift;
+mod profiles;
+mod sstp_probe;
+mod support_bundle;
+mod task_xml;
+mod temp_task;
+mod verification;
 mod vpn_commands;
 mod vpn_deploy;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+  if let Some(code) = diagnostics_report::run_headless(&std::env::args().skip(1).collect::<Vec<_>>()) {
+    std::process::exit(code);
+  }
   tauri::Builder::default()
     .invoke_handler(tauri::generate_handler![
       vpn_deploy::deploy_device_tunnel,
@@ -27,8 +54,17 @@ pub fn run() {
       vpn_commands::restart_vpn_service,
       vpn_commands::check_certificates,
       vpn_commands::check_routes,
-      vpn_commands::check_dns,
-      vpn_commands::check_ports,
+      net_checks::check_dns,
+      net_checks::check_ports,
+      ike_probe::probe_ike,
+      sstp_probe::probe_sstp,
+      mtu_probe::diagnose_mtu,
+      event_log::analyze_vpn_events,
+      event_log::analyze_event_log_file,
+      event_log::explain_error_code,
+      support_bundle::create_support_bundle,
+      support_bundle::read_support_bundle,
+      diagnostics_report::generate_diagnostics_report,
       vpn_commands::check_rras_role,
       vpn_commands::install_rras_role,
       vpn_commands::check_nps_role,
@@ -36,6 +72,37 @@ pub fn run() {
       vpn_commands::check_iis_role,
       vpn_commands::install_iis_role,
       vpn_commands::audit_templates,
+      export_strongswan::get_swanctl_conf,
+      export_mobileconfig::get_mobileconfig,
+      export_nmconnection::get_nmconnection,
+      phonebook::list_phonebook_entries,
+      phonebook::export_user_tunnel_all_pbk,
+      config_file::export_config_file,
+      config_file::import_config_file,
+      config_file::get_config_schema,
+      config_layers::load_layered_config,
+      config_layers::explain_layered_config,
+      profiles::list_profiles,
+      profiles::get_profile,
+      profiles::save_profile,
+      profiles::delete_profile,
+      profiles::clone_profile,
+      profiles::diff_profiles,
+      profiles::select_profile,
+      profiles::get_selected_profile,
+      profile_drift::check_profile_drift,
+      backups::list_backups,
+      backups::restore_backup,
+      verification::verify_tunnels,
+      auto_connect_task::get_auto_connect_task_status,
+      auto_connect_task::set_auto_connect_task_enabled,
+      auto_connect_task::remove_auto_connect_task,
+      jobs::start_job,
+      jobs::cancel_job,
+      jobs::get_job,
+      jobs::list_jobs,
+      audit::get_audit_log,
+      audit::export_audit_log,
     ])
     .setup(|app| {
       #[cfg(debug_assertions)]
@@ -46,6 +113,10 @@ pub fn run() {
             .build(),
         )?;
       }
+      std::thread::spawn(|| match temp_task::sweep_leftovers() {
+        Ok(summary) => log::info!("{}", summary),
+        Err(e) => log::warn!("Temp task sweep failed: {}", e),
+      });
       Ok(())
     })
     .plugin(tauri_plugin_dialog::init())
@@ -0,0 +1,343 @@
+use crate::config::VpnConfig;
+use crate::ike_probe::{self, ProbeStatus};
+use crate::jobs;
+use crate::profiles::resolve_config;
+use serde::Serialize;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs};
+use std::os::windows::process::CommandExt;
+use std::process::Command;
+use std::time::Duration;
+use tauri::{command, AppHandle};
+
+/// IPv4 plus ICMP header, added to the ping payload size.
+const ICMP_OVERHEAD: u16 = 28;
+/// IPv4 plus UDP header, plus the non-ESP marker on 4500.
+const UDP_4500_OVERHEAD: usize = 32;
+const MIN_MTU: u16 = 576;
+const MAX_MTU: u16 = 1500;
+/// IKE_SA_INIT sizes sent to UDP 4500: just above the unpadded request (below any MTU), close
+/// to 1500, and two that must be fragmented like an IKE_AUTH carrying a certificate chain.
+const UDP_SIZES: [usize; 4] = [600, 1400, 2200, 3500];
+/// Below this the tunnel's own overhead leaves too little room; worth a note.
+const LOW_MTU: u16 = 1400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MtuSource {
+    /// DF pings to the VPN server.
+    Server,
+    /// DF pings to `mtu_reference_host`; covers the path to that host only.
+    Reference,
+    /// MTU of the interface routing to the server; an upper bound.
+    Interface,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UdpSizeProbe {
+    /// IKE message size in bytes.
+    pub size: usize,
+    /// IP packet size on the wire.
+    pub packet_size: usize,
+    /// Larger than the path MTU, so the datagram travelled as IP fragments.
+    pub fragmented: bool,
+    pub answered: bool,
+    pub round_trip_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MtuDiagnosis {
+    pub server: String,
+    pub address: String,
+    pub path_mtu: Option<u16>,
+    pub mtu_source: MtuSource,
+    pub icmp_to_server: bool,
+    pub udp_probes: Vec<UdpSizeProbe>,
+    /// None when even the smallest IKE_SA_INIT got no answer.
+    pub fragmented_udp_passes: Option<bool>,
+    pub server_fragmentation_supported: Option<bool>,
+    pub profile_disables_fragmentation: bool,
+    pub recommendations: Vec<String>,
+}
+
+/// One ping with the don't-fragment bit set. Replies are recognised by `TTL=`, which every
+/// language version of ping.exe prints.
+fn ping_df(target: Ipv4Addr, mtu: u16) -> bool {
+    let payload = (mtu - ICMP_OVERHEAD).to_string();
+    Command::new("ping")
+        .args(["-n", "1", "-w", "1000", "-f", "-l", &payload, &target.to_string()])
+        .creation_flags(0x08000000)
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).to_ascii_uppercase().contains("TTL="))
+        .unwrap_or(false)
+}
+
+/// Largest MTU in `MIN_MTU..=MAX_MTU` that passes with DF set, or None if ICMP gets no reply at all.
+fn path_mtu(target: Ipv4Addr) -> Result<Option<u16>, String> {
+    if !ping_df(target, MIN_MTU) && !ping_df(target, MIN_MTU) {
+        return Ok(None);
+    }
+    if ping_df(target, MAX_MTU) {
+        return Ok(Some(MAX_MTU));
+    }
+    let (mut low, mut high) = (MIN_MTU, MAX_MTU);
+    while high - low > 1 {
+        jobs::check_cancelled()?;
+        let mid = low + (high - low) / 2;
+        if ping_df(target, mid) {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(Some(low))
+}
+
+fn interface_mtu(target: Ipv4Addr) -> Option<u16> {
+    let script = format!(
+        "$r = Find-NetRoute -RemoteIPAddress '{}' -ErrorAction SilentlyContinue | Select-Object -First 1; if ($r) {{ (Get-NetIPInterface -InterfaceIndex $r.InterfaceIndex -AddressFamily IPv4).NlMtu }}",
+        target
+    );
+    let out = Command::new("powershell")
+        .args(["-NoProfile", "-Command", &script])
+        .creation_flags(0x08000000)
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&out.stdout).trim().parse().ok()
+}
+
+/// Sends IKE_SA_INIT padded to `size` to UDP 4500, with one retry against packet loss.
+fn probe_size(server: &str, addr: Ipv4Addr, size: usize, timeout: Duration) -> ike_probe::IkeProbeResult {
+    let remote = SocketAddr::new(IpAddr::V4(addr), 4500);
+    let proposals = ike_probe::default_proposals();
+    let first = ike_probe::probe_addr_padded(server, remote, &proposals, timeout, size);
+    if first.status != ProbeStatus::NoResponse {
+        return first;
+    }
+    ike_probe::probe_addr_padded(server, remote, &proposals, timeout, size)
+}
+
+fn recommendations(d: &MtuDiagnosis) -> Vec<String> {
+    let mut out = Vec::new();
+    match d.fragmented_udp_passes {
+        None => out.push(
+            "The server did not answer IKE on UDP 4500, so fragmentation could not be tested. Run the IKEv2 probe first."
+                .to_string(),
+        ),
+        Some(false) => {
+            out.push("Fragmented UDP datagrams are dropped on this path. IKE_AUTH with certificates will fail unless IKEv2 fragmentation (RFC 7383) is used.".to_string());
+            if d.profile_disables_fragmentation {
+                out.push("The profile disables IKEv2 fragmentation: clear 'disableIkev2Fragmentation' and redeploy both tunnels.".to_string());
+            }
+            if d.server_fragmentation_supported != Some(true) {
+                out.push("The server does not advertise IKEv2 fragmentation: set EnableServerFragmentation = 1 under HKLM\\SYSTEM\\CurrentControlSet\\Services\\RemoteAccess\\Parameters\\Ikev2 and restart RemoteAccess.".to_string());
+            }
+            if !d.profile_disables_fragmentation && d.server_fragmentation_supported == Some(true) {
+                out.push("IKEv2 fragmentation is enabled on both sides, so IKE_AUTH will be split before it reaches the network.".to_string());
+            }
+            out.push("Where IKEv2 cannot work on this network, the SSTP user tunnel (TCP 443) is not affected.".to_string());
+        }
+        Some(true) => {
+            out.push("Fragmented UDP datagrams reach the server; IKE_AUTH does not depend on IKEv2 fragmentation on this network.".to_string());
+            if d.profile_disables_fragmentation {
+                out.push("The profile disables IKEv2 fragmentation. That works here, but hotel and mobile networks often drop fragments; consider clearing 'disableIkev2Fragmentation'.".to_string());
+            }
+        }
+    }
+    if let Some(mtu) = d.path_mtu.filter(|mtu| *mtu < LOW_MTU) {
+        out.push(format!(
+            "Path MTU is only {} bytes. Keep IKEv2 fragmentation enabled and expect reduced throughput through the tunnel.",
+            mtu
+        ));
+    }
+    if d.mtu_source == MtuSource::Interface {
+        out.push("ICMP is blocked, so the path MTU is the local interface MTU and may be smaller in practice.".to_string());
+    }
+    out
+}
+
+/// First IPv4 address of `host`.
+fn resolve_ipv4(host: &str) -> Result<Ipv4Addr, String> {
+    (host, 0)
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve {}: {}", host, e))?
+        .find_map(|a| match a.ip() {
+            IpAddr::V4(ip) => Some(ip),
+            IpAddr::V6(_) => None,
+        })
+        .ok_or(format!("{} has no IPv4 address; DF probing needs IPv4.", host))
+}
+
+/// `reference_host` is measured instead when the server drops ICMP, so at least the access
+/// network's MTU is known. Without one that step is skipped.
+pub fn diagnose(
+    server: &str,
+    reference_host: Option<&str>,
+    profile_disables_fragmentation: bool,
+    timeout: Duration,
+) -> Result<MtuDiagnosis, String> {
+    let addr = resolve_ipv4(server)?;
+
+    jobs::progress(&format!("Measuring path MTU to {} with DF pings", addr));
+    let mut mtu_source = MtuSource::Server;
+    let mut path_mtu = path_mtu(addr)?;
+    let icmp_to_server = path_mtu.is_some();
+    if let (None, Some(host)) = (path_mtu, reference_host) {
+        match resolve_ipv4(host) {
+            Ok(reference) => {
+                jobs::progress(&format!("{} does not answer ICMP, measuring the access network via {}", addr, host));
+                mtu_source = MtuSource::Reference;
+                path_mtu = self::path_mtu(reference)?;
+            }
+            Err(e) => jobs::progress(&format!("Skipping the reference host: {}", e)),
+        }
+    }
+    if path_mtu.is_none() {
+        jobs::progress("ICMP is blocked, falling back to the interface MTU");
+        mtu_source = MtuSource::Interface;
+        path_mtu = interface_mtu(addr);
+    }
+    if path_mtu.is_none() {
+        mtu_source = MtuSource::Unknown;
+    }
+
+    let mut udp_probes = Vec::new();
+    let mut server_fragmentation_supported = None;
+    for size in UDP_SIZES {
+        jobs::check_cancelled()?;
+        jobs::progress(&format!("Sending a {} byte IKE_SA_INIT to UDP 4500", size));
+        let result = probe_size(server, addr, size, timeout);
+        let answered = !matches!(result.status, ProbeStatus::NoResponse | ProbeStatus::Unreachable);
+        if udp_probes.is_empty() && answered {
+            server_fragmentation_supported = Some(result.fragmentation_supported);
+        }
+        let packet_size = size + UDP_4500_OVERHEAD;
+        udp_probes.push(UdpSizeProbe {
+            size,
+            packet_size,
+            fragmented: packet_size > path_mtu.unwrap_or(MAX_MTU) as usize,
+            answered,
+            round_trip_ms: result.round_trip_ms,
+        });
+    }
+
+    let baseline = udp_probes.first().is_some_and(|p| p.answered);
+    let fragmented: Vec<&UdpSizeProbe> = udp_probes.iter().filter(|p| p.fragmented).collect();
+    let fragmented_udp_passes = baseline.then(|| fragmented.iter().all(|p| p.answered));
+
+    let mut diagnosis = MtuDiagnosis {
+        server: server.to_string(),
+        address: addr.to_string(),
+        path_mtu,
+        mtu_source,
+        icmp_to_server,
+        udp_probes,
+        fragmented_udp_passes,
+        server_fragmentation_supported,
+        profile_disables_fragmentation,
+        recommendations: Vec::new(),
+    };
+    diagnosis.recommendations = recommendations(&diagnosis);
+    Ok(diagnosis)
+}
+
+/// Path MTU and IKE fragmentation diagnostics for the configured server, as JSON.
+#[command]
+pub async fn diagnose_mtu(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<String, String> {
+    let config = resolve_config(&app, config, profile_id)?;
+    let server = config.vpn_server_address.trim();
+    if server.is_empty() {
+        return Err("No VPN server address configured.".to_string());
+    }
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(3000).max(100));
+    let server = server.to_string();
+    let reference_host = Some(config.mtu_reference_host.trim().to_string()).filter(|h| !h.is_empty());
+    // Pings, PowerShell and UDP probes all block, so keep them off the async runtime's workers.
+    let diagnosis = jobs::spawn_blocking(move || {
+        diagnose(&server, reference_host.as_deref(), config.disable_ikev2_fragmentation, timeout)
+    })
+    .await
+    .map_err(|e| format!("MTU diagnosis failed: {}", e))??;
+    serde_json::to_string(&diagnosis).map_err(|e| format!("Failed to serialize MTU diagnosis: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnosis(passes: Option<bool>, profile_disables: bool, server_supports: Option<bool>) -> MtuDiagnosis {
+        MtuDiagnosis {
+            server: "vpn.contoso.com".into(),
+            address: "203.0.113.10".into(),
+            path_mtu: Some(1500),
+            mtu_source: MtuSource::Server,
+            icmp_to_server: true,
+            udp_probes: Vec::new(),
+            fragmented_udp_passes: passes,
+            server_fragmentation_supported: server_supports,
+            profile_disables_fragmentation: profile_disables,
+            recommendations: Vec::new(),
+        }
+    }
+
+    fn mentions(recommendations: &[String], text: &str) -> bool {
+        recommendations.iter().any(|r| r.contains(text))
+    }
+
+    #[test]
+    fn no_ike_answer_cannot_test_fragmentation() {
+        let out = recommendations(&diagnosis(None, true, None));
+        assert_eq!(out.len(), 1);
+        assert!(out[0].contains("could not be tested"));
+    }
+
+    #[test]
+    fn dropped_fragments_point_at_the_side_without_ikev2_fragmentation() {
+        let out = recommendations(&diagnosis(Some(false), true, Some(false)));
+        assert!(mentions(&out, "Fragmented UDP datagrams are dropped"));
+        assert!(mentions(&out, "clear 'disableIkev2Fragmentation'"));
+        assert!(mentions(&out, "EnableServerFragmentation = 1"));
+        assert!(!mentions(&out, "enabled on both sides"));
+        assert!(mentions(&out, "SSTP user tunnel"));
+
+        // An unknown server capability is treated like a missing one.
+        let out = recommendations(&diagnosis(Some(false), false, None));
+        assert!(!mentions(&out, "clear 'disableIkev2Fragmentation'"));
+        assert!(mentions(&out, "EnableServerFragmentation = 1"));
+
+        let out = recommendations(&diagnosis(Some(false), false, Some(true)));
+        assert!(!mentions(&out, "EnableServerFragmentation"));
+        assert!(mentions(&out, "enabled on both sides"));
+    }
+
+    #[test]
+    fn passing_fragments_only_warn_about_a_disabled_profile() {
+        let out = recommendations(&diagnosis(Some(true), false, Some(false)));
+        assert_eq!(out.len(), 1);
+        assert!(out[0].contains("reach the server"));
+
+        let out = recommendations(&diagnosis(Some(true), true, Some(true)));
+        assert_eq!(out.len(), 2);
+        assert!(mentions(&out, "consider clearing 'disableIkev2Fragmentation'"));
+    }
+
+    #[test]
+    fn low_and_interface_mtu_add_notes() {
+        let d = MtuDiagnosis { path_mtu: Some(1399), mtu_source: MtuSource::Interface, ..diagnosis(Some(true), false, Some(true)) };
+        let out = recommendations(&d);
+        assert!(mentions(&out, "Path MTU is only 1399 bytes"));
+        assert!(mentions(&out, "local interface MTU"));
+
+        let d = MtuDiagnosis { path_mtu: Some(LOW_MTU), mtu_source: MtuSource::Reference, ..diagnosis(Some(true), false, Some(true)) };
+        let out = recommendations(&d);
+        assert!(!mentions(&out, "Path MTU is only"));
+        assert!(!mentions(&out, "local interface MTU"));
+    }
+}
@@ -0,0 +1,447 @@
+use crate::config::VpnConfig;
+use crate::ike_probe::{self, ProbeStatus};
+use crate::jobs::{self, JobContext};
+use crate::profiles::resolve_config;
+use crate::sstp_probe::{self, SstpProbeResult};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::io::ErrorKind;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle};
+use tokio::net::TcpStream;
+use trust_dns_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use trust_dns_resolver::error::ResolveErrorKind;
+use trust_dns_resolver::proto::rr::{RData, RecordType};
+use trust_dns_resolver::{system_conf, TokioAsyncResolver};
+
+const DEFAULT_TIMEOUT_MS: u64 = 2000;
+/// TCP ports checked by `check_ports`, with the service expected behind them.
+const TCP_PORTS: [(u16, &str); 3] = [(443, "SSTP"), (500, "IKE"), (4500, "IKE NAT-T")];
+/// UDP ports checked with an IKE_SA_INIT.
+const UDP_PORTS: [(u16, &str); 2] = [(500, "IKE"), (4500, "IKE NAT-T")];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PortState {
+    Open,
+    Closed,
+    /// No answer within the timeout.
+    Filtered,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortResult {
+    pub port: u16,
+    pub protocol: &'static str,
+    pub service: &'static str,
+    pub state: PortState,
+    pub latency_ms: Option<u64>,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortCheck {
+    pub server: String,
+    pub address: String,
+    pub ports: Vec<PortResult>,
+    /// TLS and `SSTP_DUPLEX_POST` details for 443.
+    pub sstp: Option<SstpProbeResult>,
+}
+
+fn millis(started: Instant) -> Option<u64> {
+    Some(started.elapsed().as_millis() as u64)
+}
+
+async fn probe_tcp(addr: SocketAddr, service: &'static str, timeout: Duration) -> PortResult {
+    let started = Instant::now();
+    let (state, latency_ms, detail) = match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+        Ok(Ok(_)) => (PortState::Open, millis(started), "Connection accepted.".to_string()),
+        Ok(Err(e)) if e.kind() == ErrorKind::ConnectionRefused => {
+            (PortState::Closed, millis(started), "Connection refused.".to_string())
+        }
+        Ok(Err(e)) => (PortState::Filtered, None, format!("Connect failed: {}", e)),
+        Err(_) => (PortState::Filtered, None, format!("No answer within {} ms.", timeout.as_millis())),
+    };
+    PortResult { port: addr.port(), protocol: "tcp", service, state, latency_ms, detail }
+}
+
+/// UDP has no handshake, so the port is probed with an IKE_SA_INIT; any IKE answer means open.
+/// Runs in its own task, so the job context is passed in rather than taken from the thread.
+async fn probe_udp(job: JobContext, server: String, addr: SocketAddr, service: &'static str, timeout: Duration) -> PortResult {
+    let probe = tauri::async_runtime::spawn_blocking(move || {
+        job.enter(|| ike_probe::probe_addr(&server, addr, &ike_probe::default_proposals(), timeout))
+    })
+    .await;
+    let (state, latency_ms, detail) = match probe {
+        Ok(result) => {
+            let state = match result.status {
+                ProbeStatus::NoResponse => PortState::Filtered,
+                ProbeStatus::Unreachable => PortState::Closed,
+                _ => PortState::Open,
+            };
+            (state, result.round_trip_ms, result.summary)
+        }
+        Err(e) => (PortState::Filtered, None, format!("Probe failed: {}", e)),
+    };
+    PortResult { port: addr.port(), protocol: "udp", service, state, latency_ms, detail }
+}
+
+/// Resolves `server` once, then probes every port in parallel.
+pub async fn check_ports_native(server: &str, timeout: Duration) -> Result<PortCheck, String> {
+    let ip = tokio::net::lookup_host((server, 0))
+        .await
+        .map_err(|e| format!("Failed to resolve {}: {}", server, e))?
+        .next()
+        .ok_or(format!("No address found for {}.", server))?
+        .ip();
+
+    let mut tasks = Vec::new();
+    for (port, service) in TCP_PORTS {
+        tasks.push(tauri::async_runtime::spawn(probe_tcp(SocketAddr::new(ip, port), service, timeout)));
+    }
+    for (port, service) in UDP_PORTS {
+        let task = probe_udp(jobs::context(), server.to_string(), SocketAddr::new(ip, port), service, timeout);
+        tasks.push(tauri::async_runtime::spawn(task));
+    }
+    let sstp_server = server.to_string();
+    let sstp = jobs::spawn_blocking(move || {
+        let names = [sstp_server.clone()];
+        sstp_probe::probe_addr(&sstp_server, SocketAddr::new(ip, 443), &names, sstp_probe::native_roots(), timeout)
+    });
+
+    let mut ports = Vec::new();
+    for task in tasks {
+        ports.push(task.await.map_err(|e| format!("Port probe failed: {}", e))?);
+    }
+    Ok(PortCheck {
+        server: server.to_string(),
+        address: ip.to_string(),
+        ports,
+        sstp: sstp.await.ok(),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsRecord {
+    pub record_type: &'static str,
+    pub value: String,
+    /// None for the OS resolver, which does not report TTLs.
+    pub ttl: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsAnswer {
+    /// `system` (DNS servers of the adapters), `os` (the Windows resolver including the hosts
+    /// file and NRPT rules) or the address of an internal DNS server.
+    pub resolver: String,
+    pub records: Vec<DnsRecord>,
+    pub latency_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+impl DnsAnswer {
+    /// A and AAAA values, which is what split-brain detection compares.
+    pub fn addresses(&self) -> BTreeSet<&str> {
+        self.records
+            .iter()
+            .filter(|r| r.record_type != "CNAME")
+            .map(|r| r.value.as_str())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsCheck {
+    pub name: String,
+    pub answers: Vec<DnsAnswer>,
+    /// An internal server returned different addresses than system DNS (or the OS resolver).
+    pub split_brain: bool,
+    pub summary: Vec<String>,
+}
+
+fn tune(mut opts: ResolverOpts, timeout: Duration) -> ResolverOpts {
+    opts.timeout = timeout;
+    opts.attempts = 1;
+    // Every check should reach the servers, not a previous answer.
+    opts.cache_size = 0;
+    opts
+}
+
+fn record(data: &RData, ttl: u32) -> Option<DnsRecord> {
+    let (record_type, value) = match data {
+        RData::A(a) => ("A", a.to_string()),
+        RData::AAAA(aaaa) => ("AAAA", aaaa.to_string()),
+        RData::CNAME(cname) => ("CNAME", cname.to_string()),
+        _ => return None,
+    };
+    Some(DnsRecord { record_type, value, ttl: Some(ttl) })
+}
+
+/// A and AAAA lookups (CNAMEs come with them). A type without records is not an error.
+async fn query(resolver: &TokioAsyncResolver, label: String, name: &str) -> DnsAnswer {
+    let started = Instant::now();
+    let (a, aaaa) = tokio::join!(resolver.lookup(name, RecordType::A), resolver.lookup(name, RecordType::AAAA));
+    let mut answer = DnsAnswer { resolver: label, records: Vec::new(), latency_ms: millis(started), error: None };
+    let mut errors = Vec::new();
+    for (record_type, lookup) in [("A", a), ("AAAA", aaaa)] {
+        match lookup {
+            Ok(lookup) => {
+                for r in lookup.record_iter() {
+                    if let Some(entry) = r.data().and_then(|data| record(data, r.ttl())) {
+                        if !answer.records.contains(&entry) {
+                            answer.records.push(entry);
+                        }
+                    }
+                }
+            }
+            Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {}
+            Err(e) => errors.push(format!("{}: {}", record_type, e)),
+        }
+    }
+    if !errors.is_empty() {
+        answer.error = Some(errors.join("; "));
+    }
+    if answer.records.is_empty() && answer.error.is_none() {
+        answer.error = Some("No A or AAAA records.".to_string());
+    }
+    answer
+}
+
+async fn query_os(name: &str) -> DnsAnswer {
+    let started = Instant::now();
+    let mut answer = DnsAnswer { resolver: "os".to_string(), records: Vec::new(), latency_ms: None, error: None };
+    match tokio::net::lookup_host((name, 0)).await {
+        Ok(addrs) => {
+            answer.latency_ms = millis(started);
+            for addr in addrs {
+                let record_type = if addr.is_ipv4() { "A" } else { "AAAA" };
+                let entry = DnsRecord { record_type, value: addr.ip().to_string(), ttl: None };
+                if !answer.records.contains(&entry) {
+                    answer.records.push(entry);
+                }
+            }
+        }
+        Err(e) => answer.error = Some(e.to_string()),
+    }
+    answer
+}
+
+/// Resolves `name` with the system DNS servers, the OS resolver and each internal server, in parallel.
+pub async fn check_dns_native(name: &str, internal_servers: &[String], timeout: Duration) -> DnsCheck {
+    let mut tasks = Vec::new();
+    let system = system_conf::read_system_conf()
+        .map(|(config, opts)| TokioAsyncResolver::tokio(config, tune(opts, timeout)))
+        .map_err(|e| format!("Failed to read system DNS configuration: {}", e));
+    tasks.push(("system".to_string(), system));
+    for server in internal_servers {
+        let resolver = server
+            .parse::<IpAddr>()
+            .map(|ip| {
+                let servers = NameServerConfigGroup::from_ips_clear(&[ip], 53, true);
+                let mut opts = tune(ResolverOpts::default(), timeout);
+                opts.use_hosts_file = false;
+                TokioAsyncResolver::tokio(ResolverConfig::from_parts(None, Vec::new(), servers), opts)
+            })
+            .map_err(|_| format!("'{}' is not an IP address.", server));
+        tasks.push((server.clone(), resolver));
+    }
+
+    let os_name = name.to_string();
+    let mut handles = vec![tauri::async_runtime::spawn(async move { query_os(&os_name).await })];
+    for (label, resolver) in tasks {
+        let name = name.to_string();
+        handles.push(tauri::async_runtime::spawn(async move {
+            match resolver {
+                Ok(resolver) => query(&resolver, label, &name).await,
+                Err(e) => DnsAnswer { resolver: label, records: Vec::new(), latency_ms: None, error: Some(e) },
+            }
+        }));
+    }
+    let mut answers = Vec::new();
+    for handle in handles {
+        if let Ok(answer) = handle.await {
+            answers.push(answer);
+        }
+    }
+    summarize_dns(name, answers)
+}
+
+/// Summary lines and split-brain detection over the answers of every resolver. Internal servers
+/// are compared with system DNS, or with the OS resolver if DNS gave nothing.
+fn summarize_dns(name: &str, answers: Vec<DnsAnswer>) -> DnsCheck {
+    let mut summary = Vec::new();
+    for answer in &answers {
+        match &answer.error {
+            Some(e) if answer.records.is_empty() => summary.push(format!("{}: failed ({})", answer.resolver, e)),
+            _ => {
+                let values: Vec<String> = answer
+                    .records
+                    .iter()
+                    .map(|r| match r.ttl {
+                        Some(ttl) => format!("{} {} (TTL {})", r.record_type, r.value, ttl),
+                        None => format!("{} {}", r.record_type, r.value),
+                    })
+                    .collect();
+                summary.push(format!("{}: {}", answer.resolver, values.join(", ")));
+            }
+        }
+    }
+    let mut split_brain = false;
+    let reference = ["system", "os"]
+        .iter()
+        .find_map(|label| answers.iter().find(|a| a.resolver == *label && !a.records.is_empty()));
+    if let Some(reference) = reference {
+        let internal = answers.iter().filter(|a| !matches!(a.resolver.as_str(), "system" | "os"));
+        for answer in internal.filter(|a| !a.records.is_empty()) {
+            if answer.addresses() != reference.addresses() {
+                split_brain = true;
+                summary.push(format!(
+                    "Split-brain: {} and {} return different addresses for {}.",
+                    reference.resolver, answer.resolver, name
+                ));
+            }
+        }
+    }
+    DnsCheck { name: name.to_string(), answers, split_brain, summary }
+}
+
+/// Parallel TCP and UDP (IKE) probes of the VPN ports with per-port latency, plus the SSTP probe.
+#[command]
+pub async fn check_ports(server: String, timeout_ms: Option<u64>) -> Result<String, String> {
+    let server = server.trim();
+    if server.is_empty() {
+        return Err("No VPN server address configured.".to_string());
+    }
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS).max(100));
+    let result = check_ports_native(server, timeout).await?;
+    serde_json::to_string(&result).map_err(|e| format!("Failed to serialize port check: {}", e))
+}
+
+/// A/AAAA/CNAME answers with TTLs from system DNS and each `dns_servers` entry of the
+/// configuration, resolved like every other read-only command.
+#[command]
+pub async fn check_dns(
+    app: AppHandle,
+    server: String,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<String, String> {
+    let server = server.trim();
+    if server.is_empty() {
+        return Err("No VPN server address configured.".to_string());
+    }
+    let internal: Vec<String> = resolve_config(&app, config, profile_id)?
+        .dns_server_list()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS).max(100));
+    let result = check_dns_native(server, &internal, timeout).await;
+    serde_json::to_string(&result).map_err(|e| format!("Failed to serialize DNS check: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    fn answer(resolver: &str, records: &[(&'static str, &str, Option<u32>)], error: Option<&str>) -> DnsAnswer {
+        DnsAnswer {
+            resolver: resolver.into(),
+            records: records.iter().map(|(t, v, ttl)| DnsRecord { record_type: t, value: v.to_string(), ttl: *ttl }).collect(),
+            latency_ms: Some(3),
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn matching_answers_are_not_split_brain() {
+        let check = summarize_dns(
+            "vpn.contoso.com",
+            vec![
+                answer("system", &[("CNAME", "edge.contoso.net.", Some(60)), ("A", "203.0.113.10", Some(300))], None),
+                answer("os", &[("A", "203.0.113.10", None)], None),
+                answer("10.0.0.10", &[("A", "203.0.113.10", Some(300))], None),
+                answer("10.0.0.11", &[], Some("timed out")),
+            ],
+        );
+        assert!(!check.split_brain);
+        assert_eq!(
+            check.summary,
+            [
+                "system: CNAME edge.contoso.net. (TTL 60), A 203.0.113.10 (TTL 300)",
+                "os: A 203.0.113.10",
+                "10.0.0.10: A 203.0.113.10 (TTL 300)",
+                "10.0.0.11: failed (timed out)",
+            ]
+        );
+    }
+
+    #[test]
+    fn internal_servers_are_compared_with_system_dns() {
+        let check = summarize_dns(
+            "vpn.contoso.com",
+            vec![
+                answer("system", &[("A", "203.0.113.10", Some(300))], None),
+                answer("os", &[("A", "10.0.0.5", None)], None),
+                answer("10.0.0.10", &[("A", "10.0.0.5", Some(300))], None),
+            ],
+        );
+        assert!(check.split_brain);
+        assert_eq!(check.summary.last().unwrap(), "Split-brain: system and 10.0.0.10 return different addresses for vpn.contoso.com.");
+        // The OS resolver is a reference, not a server under test.
+        assert_eq!(check.summary.iter().filter(|l| l.starts_with("Split-brain")).count(), 1);
+    }
+
+    #[test]
+    fn os_resolver_is_the_fallback_reference() {
+        let check = summarize_dns(
+            "vpn.contoso.com",
+            vec![
+                answer("system", &[], Some("no servers")),
+                answer("os", &[("A", "203.0.113.10", None)], None),
+                answer("10.0.0.10", &[("AAAA", "fd00::5", Some(300))], None),
+            ],
+        );
+        assert!(check.split_brain);
+        assert!(check.summary.contains(&"Split-brain: os and 10.0.0.10 return different addresses for vpn.contoso.com.".to_string()));
+
+        let nothing = summarize_dns("vpn.contoso.com", vec![answer("system", &[], Some("no servers")), answer("10.0.0.10", &[("A", "10.0.0.5", None)], None)]);
+        assert!(!nothing.split_brain);
+    }
+
+    #[test]
+    fn tcp_probe_states() {
+        let timeout = Duration::from_millis(500);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let open = listener.local_addr().unwrap();
+        let result = tauri::async_runtime::block_on(probe_tcp(open, "SSTP", timeout));
+        assert_eq!(result.state, PortState::Open);
+        assert_eq!((result.port, result.protocol, result.service), (open.port(), "tcp", "SSTP"));
+        assert!(result.latency_ms.is_some());
+
+        drop(listener);
+        let result = tauri::async_runtime::block_on(probe_tcp(open, "SSTP", timeout));
+        assert_eq!(result.state, PortState::Closed);
+        assert_eq!(result.detail, "Connection refused.");
+
+        // A listener that never accepts stops answering SYNs once its backlog is full; each probe's
+        // connection stays queued after it is dropped, so the probes fill it themselves.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let full = listener.local_addr().unwrap();
+        let result = (0..4096)
+            .map(|_| tauri::async_runtime::block_on(probe_tcp(full, "SSTP", Duration::from_millis(100))))
+            .find(|r| r.state != PortState::Open)
+            .unwrap();
+        assert_eq!(result.state, PortState::Filtered);
+        assert_eq!(result.detail, "No answer within 100 ms.");
+        assert_eq!(result.latency_ms, None);
+    }
+}
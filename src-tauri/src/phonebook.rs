@@ -0,0 +1,378 @@
+use crate::config::VpnConfig;
+use crate::profiles::resolve_config;
+use serde::Serialize;
+use std::path::PathBuf;
+use tauri::{command, AppHandle};
+
+/// One `[Entry Name]` section of a rasphone.pbk file. Keys may repeat (every link
+/// section repeats `MEDIA=`, `Port=`, `Device=`), so the order is preserved as-is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhonebookEntry {
+    pub name: String,
+    pub fields: Vec<(String, String)>,
+}
+
+impl PhonebookEntry {
+    /// First value for `key` (keys are case-insensitive in RAS).
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Phonebook {
+    pub entries: Vec<PhonebookEntry>,
+}
+
+impl Phonebook {
+    pub fn parse(text: &str) -> Phonebook {
+        let mut entries: Vec<PhonebookEntry> = Vec::new();
+        for raw in text.lines() {
+            let line = raw.trim_end_matches('\r');
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with(';') {
+                continue;
+            }
+            if trimmed.starts_with('[') && trimmed.ends_with(']') {
+                entries.push(PhonebookEntry {
+                    name: trimmed[1..trimmed.len() - 1].to_string(),
+                    fields: Vec::new(),
+                });
+                continue;
+            }
+            // Lines before the first section header or without `=` are not valid RAS data.
+            if let (Some(entry), Some((k, v))) = (entries.last_mut(), line.split_once('=')) {
+                entry.fields.push((k.trim().to_string(), v.to_string()));
+            }
+        }
+        Phonebook { entries }
+    }
+
+    /// Decodes a phonebook read from disk: UTF-16LE (with BOM), UTF-8 (with or without BOM) or
+    /// ANSI (Windows-1252).
+    pub fn from_bytes(bytes: &[u8]) -> Phonebook {
+        Phonebook::parse(&decode(bytes).0)
+    }
+
+    pub fn entry(&self, name: &str) -> Option<&PhonebookEntry> {
+        self.entries.iter().find(|e| e.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Serializes in the layout RAS writes: CRLF line endings and a blank line between entries.
+    pub fn to_pbk_string(&self) -> String {
+        let mut out = String::new();
+        for entry in &self.entries {
+            out.push_str(&format!("[{}]\r\n", entry.name));
+            for (k, v) in &entry.fields {
+                out.push_str(&format!("{}={}\r\n", k, v));
+            }
+            out.push_str("\r\n");
+        }
+        out
+    }
+}
+
+/// Byte layout a phonebook was read in, so it can be written back the same way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PbkEncoding {
+    Utf16Le,
+    Utf8Bom,
+    Plain,
+    /// Not valid UTF-8, read as Windows-1252. Every byte maps to its own character, so unrelated
+    /// entries survive a rewrite unchanged whatever the machine's actual ANSI code page is.
+    Ansi,
+}
+
+/// Windows-1252 characters for the bytes 0x80-0x9F. The five unassigned bytes map to the C1
+/// control of the same value, like in the WHATWG table. All other bytes equal their code point.
+const CP1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+pub fn decode(bytes: &[u8]) -> (String, PbkEncoding) {
+    if bytes.starts_with(&[0xFF, 0xFE]) {
+        let units: Vec<u16> = bytes[2..]
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        (String::from_utf16_lossy(&units), PbkEncoding::Utf16Le)
+    } else if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        (String::from_utf8_lossy(&bytes[3..]).to_string(), PbkEncoding::Utf8Bom)
+    } else if let Ok(text) = std::str::from_utf8(bytes) {
+        (text.to_string(), PbkEncoding::Plain)
+    } else {
+        let text = bytes
+            .iter()
+            .map(|&b| match b {
+                0x80..=0x9F => CP1252_HIGH[usize::from(b - 0x80)],
+                _ => char::from(b),
+            })
+            .collect();
+        (text, PbkEncoding::Ansi)
+    }
+}
+
+/// Fails for an ANSI phonebook if `text` contains a character Windows-1252 cannot represent.
+pub fn encode(text: &str, encoding: PbkEncoding) -> Result<Vec<u8>, String> {
+    Ok(match encoding {
+        PbkEncoding::Utf16Le => [0xFF, 0xFE].into_iter().chain(text.encode_utf16().flat_map(u16::to_le_bytes)).collect(),
+        PbkEncoding::Utf8Bom => [0xEF, 0xBB, 0xBF].iter().chain(text.as_bytes()).copied().collect(),
+        PbkEncoding::Plain => text.as_bytes().to_vec(),
+        PbkEncoding::Ansi => text
+            .chars()
+            .map(|c| match u32::from(c) {
+                0..=0x7F | 0xA0..=0xFF => Ok(c as u8),
+                _ => CP1252_HIGH
+                    .iter()
+                    .position(|&h| h == c)
+                    .map(|i| 0x80 + i as u8)
+                    .ok_or(format!("'{}' cannot be written to an ANSI phonebook.", c)),
+            })
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+/// Replaces the `[name]` section of `text` with `section` (a complete `[name]` block), or appends
+/// it when there is none. `None` removes the section. Every other line, comments and other
+/// entries included, is kept byte for byte.
+pub fn splice_entry(text: &str, name: &str, section: Option<&str>) -> String {
+    let header = |line: &str| {
+        let t = line.trim();
+        (t.starts_with('[') && t.ends_with(']')).then(|| t[1..t.len() - 1].to_string())
+    };
+    let mut out = String::new();
+    let mut inside = false;
+    let mut replaced = false;
+    for line in text.split_inclusive('\n') {
+        if let Some(current) = header(line) {
+            inside = current.eq_ignore_ascii_case(name);
+            if inside && !replaced {
+                out.push_str(section.unwrap_or_default());
+                replaced = true;
+            }
+        }
+        if !inside {
+            out.push_str(line);
+        }
+    }
+    if !replaced {
+        if let Some(section) = section {
+            if !out.is_empty() && !out.ends_with("\n\r\n") && !out.ends_with("\n\n") {
+                out.push_str("\r\n");
+            }
+            out.push_str(section);
+        }
+    }
+    out
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub enum PhonebookScope {
+    AllUsers,
+    CurrentUser,
+}
+
+impl PhonebookScope {
+    pub fn path(self) -> Option<PathBuf> {
+        let base = match self {
+            PhonebookScope::AllUsers => std::env::var_os("ProgramData")?,
+            PhonebookScope::CurrentUser => std::env::var_os("APPDATA")?,
+        };
+        Some(
+            PathBuf::from(base)
+                .join("Microsoft")
+                .join("Network")
+                .join("Connections")
+                .join("Pbk")
+                .join("rasphone.pbk"),
+        )
+    }
+
+    pub fn read(self) -> Result<Phonebook, String> {
+        let path = self.path().ok_or("Phonebook location could not be determined.")?;
+        match std::fs::read(&path) {
+            Ok(bytes) => Ok(Phonebook::from_bytes(&bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Phonebook::default()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Writes one entry's section (see `splice_entry`) and leaves the rest of the file, including
+    /// its encoding, as it is on disk now.
+    pub fn write_entry(self, name: &str, section: Option<&str>) -> Result<(), String> {
+        let path = self.path().ok_or("Phonebook location could not be determined.")?;
+        let (text, encoding) = match std::fs::read(&path) {
+            Ok(bytes) => decode(&bytes),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (String::new(), PbkEncoding::Plain),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        let updated = splice_entry(&text, name, section);
+        if updated == text {
+            return Ok(());
+        }
+        std::fs::write(&path, encode(&updated, encoding)?).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+/// Human-readable tunnel type for the `VpnStrategy` field.
+pub fn vpn_strategy_name(value: &str) -> &'static str {
+    match value.trim() {
+        "0" => "Automatic",
+        "1" => "PPTP",
+        "2" => "PPTP first",
+        "3" => "L2TP",
+        "4" => "L2TP first",
+        "5" => "SSTP",
+        "6" => "SSTP first",
+        "7" => "IKEv2",
+        "8" => "IKEv2 first",
+        "14" => "IKEv2 first, then SSTP",
+        _ => "Unknown",
+    }
+}
+
+/// Names of the connections this app creates for `config`.
+pub fn managed_names(config: &VpnConfig) -> Vec<String> {
+    let names = config.tunnel_names();
+    vec![names.device, names.user, names.user_all]
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhonebookEntrySummary {
+    pub name: String,
+    pub scope: PhonebookScope,
+    pub server: String,
+    pub tunnel_type: String,
+    pub managed: bool,
+}
+
+pub fn summarize(phonebook: &Phonebook, scope: PhonebookScope, managed: &[String]) -> Vec<PhonebookEntrySummary> {
+    phonebook
+        .entries
+        .iter()
+        .map(|e| PhonebookEntrySummary {
+            name: e.name.clone(),
+            scope,
+            server: e.get("PhoneNumber").unwrap_or_default().to_string(),
+            tunnel_type: vpn_strategy_name(e.get("VpnStrategy").unwrap_or_default()).to_string(),
+            managed: managed.iter().any(|m| m.eq_ignore_ascii_case(&e.name)),
+        })
+        .collect()
+}
+
+/// Checks both phonebooks for `name` after a removal and returns a warning line per
+/// entry that survived it (e.g. because `Remove-VpnConnection` failed silently).
+pub fn orphan_warnings(name: &str) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for scope in [PhonebookScope::AllUsers, PhonebookScope::CurrentUser] {
+        if let (Ok(pb), Some(path)) = (scope.read(), scope.path()) {
+            if pb.entry(name).is_some() {
+                warnings.push(format!(
+                    "WARNING: Orphaned phonebook entry '{}' is still present in {}",
+                    name,
+                    path.display()
+                ));
+            }
+        }
+    }
+    warnings
+}
+
+/// Appends `orphan_warnings` for `name` to the output of a removal command.
+pub fn with_orphan_warnings(mut output: String, name: &str) -> String {
+    for warning in orphan_warnings(name) {
+        if !output.ends_with('\n') {
+            output.push('\n');
+        }
+        output.push_str(&warning);
+    }
+    output
+}
+
+#[command]
+pub async fn list_phonebook_entries(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let config = resolve_config(&app, config, profile_id)?;
+    let managed = managed_names(&config);
+    let mut summaries = Vec::new();
+    for scope in [PhonebookScope::AllUsers, PhonebookScope::CurrentUser] {
+        summaries.extend(summarize(&scope.read()?, scope, &managed));
+    }
+    serde_json::to_string(&summaries).map_err(|e| format!("Failed to serialize phonebook: {}", e))
+}
+
+#[command]
+pub async fn export_user_tunnel_all_pbk(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let config = resolve_config(&app, config, profile_id)?;
+    let name = config.tunnel_names().user_all;
+    let phonebook = PhonebookScope::AllUsers.read()?;
+    let entry = phonebook
+        .entry(&name)
+        .ok_or(format!("'{}' was not found in the All Users phonebook. Deploy the User Tunnel (All) first.", name))?;
+    Ok(Phonebook { entries: vec![entry.clone()] }.to_pbk_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PBK: &str = "; managed by IT\r\n[Other VPN]\r\nMEDIA=rastapi\r\n\r\n[Contoso User Tunnel (All)]\r\nVpnStrategy=5\r\nMEDIA=rastapi\r\n\r\n[Third]\r\nVpnStrategy=6\r\n";
+
+    #[test]
+    fn splice_replaces_only_the_named_entry() {
+        let section = "[Contoso User Tunnel (All)]\r\nVpnStrategy=8\r\n\r\n";
+        let out = splice_entry(PBK, "contoso user tunnel (all)", Some(section));
+        assert_eq!(
+            out,
+            "; managed by IT\r\n[Other VPN]\r\nMEDIA=rastapi\r\n\r\n[Contoso User Tunnel (All)]\r\nVpnStrategy=8\r\n\r\n[Third]\r\nVpnStrategy=6\r\n"
+        );
+
+        let removed = splice_entry(PBK, "Contoso User Tunnel (All)", None);
+        assert!(removed.starts_with("; managed by IT\r\n[Other VPN]"));
+        assert!(!removed.contains("VpnStrategy=5"));
+        assert!(removed.contains("[Third]"));
+
+        let appended = splice_entry(PBK, "New", Some("[New]\r\nVpnStrategy=5\r\n"));
+        assert!(appended.starts_with(PBK));
+        assert_eq!(Phonebook::parse(&appended).entries.len(), 4);
+    }
+
+    #[test]
+    fn encoding_round_trips() {
+        for encoding in [PbkEncoding::Utf16Le, PbkEncoding::Utf8Bom, PbkEncoding::Plain] {
+            assert_eq!(decode(&encode(PBK, encoding).unwrap()), (PBK.to_string(), encoding));
+        }
+    }
+
+    #[test]
+    fn ansi_phonebook_keeps_other_entries_byte_for_byte() {
+        // "Büro VPN" and "Gebühr €" in Windows-1252, plus the unassigned byte 0x81.
+        let mut bytes = b"[B\xFCro VPN]\r\nComment=Geb\xFChr \x80 \x81\r\n\r\n".to_vec();
+        bytes.extend_from_slice(PBK.as_bytes());
+        let (text, encoding) = decode(&bytes);
+        assert_eq!(encoding, PbkEncoding::Ansi);
+        assert!(text.starts_with("[Büro VPN]\r\nComment=Gebühr € \u{81}\r\n"));
+        assert_eq!(encode(&text, encoding).unwrap(), bytes);
+
+        let section = "[Contoso User Tunnel (All)]\r\nVpnStrategy=8\r\n\r\n";
+        let written = encode(&splice_entry(&text, "Contoso User Tunnel (All)", Some(section)), encoding).unwrap();
+        assert!(written.starts_with(b"[B\xFCro VPN]\r\nComment=Geb\xFChr \x80 \x81\r\n"));
+        assert!(written.windows(13).any(|w| w == b"VpnStrategy=8"));
+
+        assert!(encode("[\u{4E2D}]\r\n", PbkEncoding::Ansi).is_err());
+    }
+}
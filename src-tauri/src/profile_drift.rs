@@ -0,0 +1,365 @@
+use crate::config::VpnConfig;
+use crate::profiles::resolve_config;
+use crate::vpn_deploy::{generate_device_profile_xml, generate_user_profile_xml, run_as_system_task_with_output};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::os::windows::process::CommandExt;
+use std::process::Command;
+use tauri::{command, AppHandle};
+
+/// Leaves whose values are compared case-insensitively (host names, domains, hashes).
+const CASE_INSENSITIVE: [&str; 7] = [
+    "Servers",
+    "DnsSuffix",
+    "DomainName",
+    "TrustedNetworkDetection",
+    "ServerNames",
+    "TrustedRootCA",
+    "DnsServers",
+];
+
+/// Leaves holding comma-separated lists, compared without whitespace.
+const COMMA_LISTS: [&str; 3] = ["DnsServers", "TrustedNetworkDetection", "Servers"];
+
+/// Flattened ProfileXML: element path (local names, joined with `/`) to the sorted values
+/// found at that path. Element order and formatting do not matter; repeated elements do.
+pub type NormalizedProfile = BTreeMap<String, Vec<String>>;
+
+fn normalize_value(leaf: &str, value: &str) -> String {
+    let mut value = value.trim().to_string();
+    if COMMA_LISTS.contains(&leaf) {
+        value = value.split(',').map(str::trim).filter(|v| !v.is_empty()).collect::<Vec<_>>().join(",");
+    }
+    if leaf == "TrustedRootCA" {
+        value.retain(|c| !c.is_whitespace());
+    }
+    if CASE_INSENSITIVE.contains(&leaf) || value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") {
+        value = value.to_lowercase();
+    }
+    value
+}
+
+fn child_text<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> &'a str {
+    node.children()
+        .find(|c| c.has_tag_name(name))
+        .and_then(|c| c.text())
+        .map(str::trim)
+        .unwrap_or_default()
+}
+
+fn flatten(node: roxmltree::Node, path: &str, out: &mut NormalizedProfile) -> Result<(), String> {
+    let name = node.tag_name().name();
+    let path = if path.is_empty() { name.to_string() } else { format!("{}/{}", path, name) };
+    let push = |out: &mut NormalizedProfile, key: String, value: String| out.entry(key).or_default().push(value);
+
+    for attr in node.attributes() {
+        push(out, format!("{}/@{}", path, attr.name()), attr.value().trim().to_string());
+    }
+
+    // Routes have no identity besides their content, so one route is one value.
+    if name == "Route" {
+        let route = format!("{}/{}", child_text(node, "Address"), child_text(node, "PrefixSize"));
+        push(out, path, route);
+        return Ok(());
+    }
+    // Several DomainNameInformation blocks are told apart by their domain.
+    let path = if name == "DomainNameInformation" {
+        format!("{}[{}]", path, normalize_value("DomainName", child_text(node, "DomainName")))
+    } else {
+        path
+    };
+
+    let elements: Vec<_> = node.children().filter(|c| c.is_element()).collect();
+    if !elements.is_empty() {
+        for child in elements {
+            flatten(child, &path, out)?;
+        }
+        return Ok(());
+    }
+
+    let text = node.text().unwrap_or_default();
+    // The EAP configuration is XML embedded as escaped text.
+    if name == "Configuration" && text.trim_start().starts_with('<') {
+        let doc = roxmltree::Document::parse(text.trim()).map_err(|e| format!("Invalid EAP configuration: {}", e))?;
+        return flatten(doc.root_element(), &path, out);
+    }
+    push(out, path, normalize_value(name, text));
+    Ok(())
+}
+
+pub fn normalize_profile_xml(xml: &str) -> Result<NormalizedProfile, String> {
+    let doc = roxmltree::Document::parse(xml.trim()).map_err(|e| format!("Invalid ProfileXML: {}", e))?;
+    let mut out = NormalizedProfile::new();
+    flatten(doc.root_element(), "", &mut out)?;
+    for values in out.values_mut() {
+        values.sort();
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum DriftKind {
+    /// Expected by the config but not deployed.
+    Missing,
+    /// Deployed but not part of the config.
+    Unexpected,
+    Changed,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileDifference {
+    pub path: String,
+    pub kind: DriftKind,
+    pub expected: Option<String>,
+    pub deployed: Option<String>,
+}
+
+/// Structured differences between the expected and the deployed profile.
+pub fn diff_profiles(expected: &NormalizedProfile, deployed: &NormalizedProfile) -> Vec<ProfileDifference> {
+    let empty = Vec::new();
+    let mut paths: Vec<&String> = expected.keys().chain(deployed.keys()).collect();
+    paths.sort();
+    paths.dedup();
+
+    let mut differences = Vec::new();
+    for path in paths {
+        let want = expected.get(path).unwrap_or(&empty);
+        let have = deployed.get(path).unwrap_or(&empty);
+        if want == have {
+            continue;
+        }
+        if want.len() == 1 && have.len() == 1 {
+            differences.push(ProfileDifference {
+                path: path.clone(),
+                kind: DriftKind::Changed,
+                expected: Some(want[0].clone()),
+                deployed: Some(have[0].clone()),
+            });
+            continue;
+        }
+        for value in want.iter().filter(|v| !have.contains(v)) {
+            differences.push(ProfileDifference {
+                path: path.clone(),
+                kind: DriftKind::Missing,
+                expected: Some(value.clone()),
+                deployed: None,
+            });
+        }
+        for value in have.iter().filter(|v| !want.contains(v)) {
+            differences.push(ProfileDifference {
+                path: path.clone(),
+                kind: DriftKind::Unexpected,
+                expected: None,
+                deployed: Some(value.clone()),
+            });
+        }
+    }
+    differences
+}
+
+/// Compares two ProfileXML documents after normalization.
+pub fn diff_profile_xml(expected: &str, deployed: &str) -> Result<Vec<ProfileDifference>, String> {
+    Ok(diff_profiles(&normalize_profile_xml(expected)?, &normalize_profile_xml(deployed)?))
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ProfileContext {
+    /// Device Tunnel, only visible to SYSTEM.
+    Device,
+    /// User Tunnel of the logged-on user.
+    User,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeployedProfile {
+    found: bool,
+    profile_xml: Option<String>,
+}
+
+fn parse_deployed(output: &str) -> Result<Option<String>, String> {
+    // Anything printed before the JSON (warnings, progress) is ignored.
+    let json = output.get(output.find('{').unwrap_or(0)..).unwrap_or_default();
+    let deployed: DeployedProfile =
+        serde_json::from_str(json).map_err(|e| format!("Unexpected WMI output: {} ({})", e, output.trim()))?;
+    Ok(if deployed.found { deployed.profile_xml } else { None })
+}
+
+/// Reads the ProfileXML of the `MDM_VPNv2_01` instance for `name`, or `None` if it is not deployed.
+pub fn read_deployed_profile_xml(context: ProfileContext, name: &str) -> Result<Option<String>, String> {
+    let script = format!(r#"
+$ErrorActionPreference = "Stop"
+$profileNameEscaped = "{name}".Replace(' ', '%20')
+$namespaceName = "root\cimv2\mdm\dmmap"
+$className = "MDM_VPNv2_01"
+
+$currentUser = [System.Security.Principal.WindowsIdentity]::GetCurrent()
+$options = $null
+
+if ("{context}" -eq "User" -and $currentUser.IsSystem) {{
+    $explorerProc = Get-CimInstance Win32_Process -Filter "name='explorer.exe'" | Select-Object -First 1
+    if ($explorerProc) {{
+        $owner = Invoke-CimMethod -InputObject $explorerProc -MethodName GetOwnerSid
+        $options = New-Object Microsoft.Management.Infrastructure.Options.CimOperationOptions
+        $options.SetCustomOption("PolicyPlatformContext_PrincipalContext_Type", "PolicyPlatform_UserContext", $false)
+        $options.SetCustomOption("PolicyPlatformContext_PrincipalContext_Id", "$($owner.Sid)", $false)
+    }} else {{ throw "No user logged in." }}
+}}
+
+$session = New-CimSession
+try {{
+    if ($options) {{ $existing = $session.EnumerateInstances($namespaceName, $className, $options) }}
+    else {{ $existing = $session.EnumerateInstances($namespaceName, $className) }}
+}} catch {{ $existing = $null }}
+
+$instance = $existing | Where-Object {{ $_.InstanceID -eq $profileNameEscaped }} | Select-Object -First 1
+if ($instance) {{
+    @{{ found = $true; profileXml = $instance.ProfileXML }} | ConvertTo-Json -Compress
+}} else {{
+    '{{"found":false}}'
+}}
+"#, name = name, context = if context == ProfileContext::User { "User" } else { "Device" });
+
+    let output = match context {
+        // Device Tunnel instances only exist in the SYSTEM context.
+        ProfileContext::Device => run_as_system_task_with_output("TempReadDeviceTunnel", &script)?,
+        ProfileContext::User => {
+            let output = Command::new("powershell")
+                .args(["-Command", &script])
+                .creation_flags(0x08000000)
+                .output()
+                .map_err(|e| format!("PowerShell error: {}", e))?;
+            if !output.status.success() {
+                return Err(String::from_utf8_lossy(&output.stderr).to_string());
+            }
+            String::from_utf8_lossy(&output.stdout).to_string()
+        }
+    };
+    parse_deployed(&output)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DriftReport {
+    pub name: String,
+    pub context: ProfileContext,
+    pub deployed: bool,
+    pub in_sync: bool,
+    pub differences: Vec<ProfileDifference>,
+    /// Set when the deployed profile could not be read or parsed.
+    pub error: Option<String>,
+}
+
+pub fn drift_report(context: ProfileContext, name: &str, expected_xml: &str) -> DriftReport {
+    let mut report = DriftReport {
+        name: name.to_string(),
+        context,
+        deployed: false,
+        in_sync: false,
+        differences: Vec::new(),
+        error: None,
+    };
+    let result = read_deployed_profile_xml(context, name).and_then(|deployed| match deployed {
+        Some(xml) => diff_profile_xml(expected_xml, &xml).map(Some),
+        None => Ok(None),
+    });
+    match result {
+        Ok(Some(differences)) => {
+            report.deployed = true;
+            report.in_sync = differences.is_empty();
+            report.differences = differences;
+        }
+        Ok(None) => {}
+        Err(e) => report.error = Some(e),
+    }
+    report
+}
+
+fn expected_profile(config: &VpnConfig, context: ProfileContext) -> (String, String) {
+    let names = config.tunnel_names();
+    match context {
+        ProfileContext::Device => (names.device, generate_device_profile_xml(config)),
+        ProfileContext::User => (names.user, generate_user_profile_xml(config)),
+    }
+}
+
+/// Compares the deployed Device and User Tunnel profiles against `config`.
+#[command]
+pub async fn check_profile_drift(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let config = resolve_config(&app, config, profile_id)?;
+    let reports: Vec<DriftReport> = [ProfileContext::Device, ProfileContext::User]
+        .into_iter()
+        .map(|context| {
+            let (name, xml) = expected_profile(&config, context);
+            drift_report(context, &name, &xml)
+        })
+        .collect();
+    serde_json::to_string(&reports).map_err(|e| format!("Failed to serialize drift report: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEVICE: &str = include_str!("../../t/dornMX_Device_Tunnel_profile.xml");
+    const USER: &str = include_str!("../../t/dornMX_User_tunnel_profile.xml");
+
+    fn route(address: &str, prefix: u8) -> String {
+        format!("<Route>\n    <Address>{}</Address><PrefixSize>{}</PrefixSize>\n  </Route>", address, prefix)
+    }
+
+    #[test]
+    fn fixture_against_itself_has_no_drift() {
+        for xml in [DEVICE, USER] {
+            assert!(diff_profile_xml(xml, xml).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn order_whitespace_and_case_are_ignored() {
+        // Routes swapped, the document squeezed onto fewer lines, list spacing and host name
+        // case changed.
+        let reordered = DEVICE
+            .replace(&route("192.168.148.20", 32), "__FIRST__")
+            .replace(&route("172.16.148.4", 32), &route("192.168.148.20", 32))
+            .replace("__FIRST__", &route("172.16.148.4", 32))
+            .replace("\n  ", "")
+            .replace("<Servers>vpn.dornmx.de</Servers>", "<Servers> VPN.DornMX.de </Servers>")
+            .replace("192.168.148.20,172.16.148.4", "192.168.148.20, 172.16.148.4");
+        assert_ne!(reordered, DEVICE);
+        let differences = diff_profile_xml(DEVICE, &reordered).unwrap();
+        assert!(differences.is_empty(), "{:?}", differences);
+    }
+
+    #[test]
+    fn changed_missing_and_unexpected_routes_are_reported() {
+        let deployed = DEVICE.replace(&route("172.16.148.4", 32), &route("10.0.0.0", 8));
+        let differences = diff_profile_xml(DEVICE, &deployed).unwrap();
+        assert_eq!(differences.len(), 2, "{:?}", differences);
+        assert!(differences.iter().all(|d| d.path == "VPNProfile/Route"));
+        assert_eq!(differences[0].kind, DriftKind::Missing);
+        assert_eq!(differences[0].expected.as_deref(), Some("172.16.148.4/32"));
+        assert_eq!(differences[1].kind, DriftKind::Unexpected);
+        assert_eq!(differences[1].deployed.as_deref(), Some("10.0.0.0/8"));
+
+        let deployed = USER.replace(&route("172.16.148.0", 24), "");
+        let differences = diff_profile_xml(USER, &deployed).unwrap();
+        assert_eq!(differences.len(), 1, "{:?}", differences);
+        assert_eq!(differences[0].kind, DriftKind::Missing);
+        assert_eq!(differences[0].expected.as_deref(), Some("172.16.148.0/24"));
+
+        let deployed = DEVICE.replace("<Servers>vpn.dornmx.de</Servers>", "<Servers>vpn2.dornmx.de</Servers>");
+        let differences = diff_profile_xml(DEVICE, &deployed).unwrap();
+        assert_eq!(differences.len(), 1, "{:?}", differences);
+        assert_eq!(differences[0].path, "VPNProfile/NativeProfile/Servers");
+        assert_eq!(differences[0].kind, DriftKind::Changed);
+        assert_eq!(differences[0].deployed.as_deref(), Some("vpn2.dornmx.de"));
+    }
+}
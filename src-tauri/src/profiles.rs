@@ -0,0 +1,385 @@
+use crate::config::{slugify, VpnConfig};
+use crate::config_file::{parse_config_file, to_config_file, ConfigFile, ConfigMetadata};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::PathBuf;
+use tauri::{command, AppHandle, Manager};
+
+/// File in the profile directory holding the id of the selected profile.
+const SELECTED_FILE: &str = "selected";
+/// `identifier` from tauri.conf.json; Tauri names the app data directory after it.
+const APP_IDENTIFIER: &str = "com.leiqos.aovpn";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileSummary {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub created: String,
+    pub company_prefix: String,
+    pub vpn_server_address: String,
+    pub selected: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldDiff {
+    pub field: String,
+    pub left: Value,
+    pub right: Value,
+}
+
+/// Saved configurations, one versioned config file per profile in `<app data>/profiles`.
+pub struct ProfileStore {
+    root: PathBuf,
+}
+
+impl ProfileStore {
+    pub fn new(root: PathBuf) -> ProfileStore {
+        ProfileStore { root }
+    }
+
+    pub fn open(app: &AppHandle) -> Result<ProfileStore, String> {
+        let dir = app
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+        Ok(ProfileStore::new(dir.join("profiles")))
+    }
+
+    /// The store `open` resolves to (`%APPDATA%\<identifier>\profiles`), for code that runs
+    /// before Tauri is set up, like the headless report.
+    pub fn open_without_app() -> Result<ProfileStore, String> {
+        let app_data = std::env::var_os("APPDATA").ok_or("APPDATA is not set.".to_string())?;
+        Ok(ProfileStore::new(PathBuf::from(app_data).join(APP_IDENTIFIER).join("profiles")))
+    }
+
+    fn path_for(&self, id: &str) -> Result<PathBuf, String> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(format!("Invalid profile id '{}'.", id));
+        }
+        Ok(self.root.join(format!("{}.json", id)))
+    }
+
+    fn ensure_root(&self) -> Result<(), String> {
+        std::fs::create_dir_all(&self.root)
+            .map_err(|e| format!("Failed to create profile directory {}: {}", self.root.display(), e))
+    }
+
+    pub fn load(&self, id: &str) -> Result<ConfigFile, String> {
+        let path = self.path_for(id)?;
+        let content = std::fs::read_to_string(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => format!("Profile '{}' does not exist.", id),
+            _ => format!("Failed to read profile '{}': {}", id, e),
+        })?;
+        parse_config_file(&content).map_err(|e| format!("Profile '{}': {}", id, e))
+    }
+
+    fn write(&self, id: &str, file: &ConfigFile) -> Result<(), String> {
+        self.ensure_root()?;
+        let content = serde_json::to_string_pretty(file).map_err(|e| format!("Failed to serialize profile: {}", e))?;
+        std::fs::write(self.path_for(id)?, content).map_err(|e| format!("Failed to write profile '{}': {}", id, e))
+    }
+
+    /// New id derived from `name` that does not collide with an existing profile.
+    fn unique_id(&self, name: &str) -> String {
+        let base = match slugify(name) {
+            b if b.is_empty() => "profile".to_string(),
+            b => b,
+        };
+        let mut id = base.clone();
+        let mut n = 2;
+        while self.root.join(format!("{}.json", id)).exists() {
+            id = format!("{}-{}", base, n);
+            n += 1;
+        }
+        id
+    }
+
+    pub fn list(&self) -> Result<Vec<ProfileSummary>, String> {
+        let selected = self.selected_id();
+        let entries = match std::fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to list profiles: {}", e)),
+        };
+        let mut profiles = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|s| s.to_str()).map(str::to_string) else {
+                continue;
+            };
+            // A damaged file should not hide the other profiles.
+            let Ok(file) = self.load(&id) else {
+                continue;
+            };
+            profiles.push(ProfileSummary {
+                selected: selected.as_deref() == Some(id.as_str()),
+                name: if file.metadata.name.is_empty() { id.clone() } else { file.metadata.name },
+                description: file.metadata.description,
+                created: file.metadata.created,
+                company_prefix: file.config.company_prefix,
+                vpn_server_address: file.config.vpn_server_address,
+                id,
+            });
+        }
+        profiles.sort_by_key(|p| p.name.to_lowercase());
+        Ok(profiles)
+    }
+
+    /// Saves `config` under `id`, or under a new id derived from `name` when `id` is `None`.
+    /// Returns the id the profile was stored under.
+    pub fn save(&self, id: Option<&str>, name: &str, description: &str, config: VpnConfig) -> Result<String, String> {
+        let (id, created) = match id {
+            Some(id) => {
+                let created = self.load(id).map(|f| f.metadata.created).unwrap_or_default();
+                (id.to_string(), created)
+            }
+            None => (self.unique_id(name), String::new()),
+        };
+        let metadata = ConfigMetadata {
+            name: name.trim().to_string(),
+            description: description.to_string(),
+            created,
+            ..ConfigMetadata::default()
+        };
+        self.write(&id, &to_config_file(config, Some(metadata)))?;
+        Ok(id)
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        std::fs::remove_file(self.path_for(id)?).map_err(|e| format!("Failed to delete profile '{}': {}", id, e))?;
+        if self.selected_id().as_deref() == Some(id) {
+            let _ = std::fs::remove_file(self.root.join(SELECTED_FILE));
+        }
+        Ok(())
+    }
+
+    pub fn clone_profile(&self, id: &str, new_name: &str) -> Result<String, String> {
+        let source = self.load(id)?;
+        self.save(None, new_name, &source.metadata.description, source.config)
+    }
+
+    pub fn select(&self, id: &str) -> Result<(), String> {
+        self.load(id)?;
+        self.ensure_root()?;
+        std::fs::write(self.root.join(SELECTED_FILE), id).map_err(|e| format!("Failed to select profile: {}", e))
+    }
+
+    pub fn selected_id(&self) -> Option<String> {
+        let id = std::fs::read_to_string(self.root.join(SELECTED_FILE)).ok()?;
+        let id = id.trim();
+        (!id.is_empty() && self.path_for(id).is_ok_and(|p| p.exists())).then(|| id.to_string())
+    }
+
+    pub fn selected_config(&self) -> Result<VpnConfig, String> {
+        match self.selected_id() {
+            Some(id) => Ok(self.load(&id)?.config),
+            None => Err("No configuration given and no profile selected.".to_string()),
+        }
+    }
+}
+
+/// Field-by-field differences between two configurations.
+pub fn diff_configs(left: &VpnConfig, right: &VpnConfig) -> Vec<FieldDiff> {
+    let (Ok(Value::Object(left)), Ok(Value::Object(right))) = (serde_json::to_value(left), serde_json::to_value(right)) else {
+        return Vec::new();
+    };
+    left.into_iter()
+        .filter_map(|(field, l)| {
+            let r = right.get(&field).cloned().unwrap_or(Value::Null);
+            (l != r).then_some(FieldDiff { field, left: l, right: r })
+        })
+        .collect()
+}
+
+/// Resolves the configuration a command should operate on: an explicit `profile_id`
+/// wins, then a `config` passed from the form, then the selected profile.
+pub fn resolve_config(app: &AppHandle, config: Option<VpnConfig>, profile_id: Option<String>) -> Result<VpnConfig, String> {
+    resolve_with(|| ProfileStore::open(app), config, profile_id)
+}
+
+/// Like `resolve_config`, but for commands that change the machine (deploy, remove, task and
+/// IPsec changes): the caller has to name the configuration, the selected profile is never
+/// picked implicitly.
+pub fn resolve_config_for_change(app: &AppHandle, config: Option<VpnConfig>, profile_id: Option<String>) -> Result<VpnConfig, String> {
+    resolve_for_change_with(|| ProfileStore::open(app), config, profile_id)
+}
+
+/// `resolve_config` with the store opened by `open`, only when it is needed.
+fn resolve_with(
+    open: impl Fn() -> Result<ProfileStore, String>,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<VpnConfig, String> {
+    if let Some(id) = profile_id {
+        return Ok(open()?.load(&id)?.config);
+    }
+    if let Some(config) = config {
+        return Ok(config);
+    }
+    open()?.selected_config()
+}
+
+fn resolve_for_change_with(
+    open: impl Fn() -> Result<ProfileStore, String>,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<VpnConfig, String> {
+    if config.is_none() && profile_id.is_none() {
+        return Err("No configuration or profile id given; refusing to change the machine with an implicitly selected profile.".to_string());
+    }
+    resolve_with(open, config, profile_id)
+}
+
+#[command]
+pub async fn list_profiles(app: AppHandle) -> Result<String, String> {
+    let profiles = ProfileStore::open(&app)?.list()?;
+    serde_json::to_string(&profiles).map_err(|e| format!("Failed to serialize profiles: {}", e))
+}
+
+#[command]
+pub async fn get_profile(app: AppHandle, id: String) -> Result<VpnConfig, String> {
+    Ok(ProfileStore::open(&app)?.load(&id)?.config)
+}
+
+#[command]
+pub async fn save_profile(
+    app: AppHandle,
+    id: Option<String>,
+    name: String,
+    description: Option<String>,
+    config: VpnConfig,
+) -> Result<String, String> {
+    let id = ProfileStore::open(&app)?.save(id.as_deref(), &name, &description.unwrap_or_default(), config)?;
+    Ok(id)
+}
+
+#[command]
+pub async fn delete_profile(app: AppHandle, id: String) -> Result<String, String> {
+    ProfileStore::open(&app)?.delete(&id)?;
+    Ok(format!("Profile '{}' deleted.", id))
+}
+
+#[command]
+pub async fn clone_profile(app: AppHandle, id: String, new_name: String) -> Result<String, String> {
+    ProfileStore::open(&app)?.clone_profile(&id, &new_name)
+}
+
+#[command]
+pub async fn diff_profiles(app: AppHandle, left: String, right: String) -> Result<String, String> {
+    let store = ProfileStore::open(&app)?;
+    let diff = diff_configs(&store.load(&left)?.config, &store.load(&right)?.config);
+    serde_json::to_string(&diff).map_err(|e| format!("Failed to serialize diff: {}", e))
+}
+
+#[command]
+pub async fn select_profile(app: AppHandle, id: String) -> Result<String, String> {
+    ProfileStore::open(&app)?.select(&id)?;
+    Ok(format!("Profile '{}' selected.", id))
+}
+
+#[command]
+pub async fn get_selected_profile(app: AppHandle) -> Result<Option<String>, String> {
+    Ok(ProfileStore::open(&app)?.selected_id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Profile store in its own temp directory, removed again on drop.
+    struct TestStore(PathBuf);
+
+    impl TestStore {
+        fn new(name: &str) -> TestStore {
+            let root = std::env::temp_dir().join(format!("aovpn-profiles-test-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+            TestStore(root)
+        }
+
+        fn store(&self) -> ProfileStore {
+            ProfileStore::new(self.0.clone())
+        }
+    }
+
+    impl Drop for TestStore {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn config(prefix: &str) -> VpnConfig {
+        VpnConfig { company_prefix: prefix.into(), ..VpnConfig::default() }
+    }
+
+    #[test]
+    fn ids_are_validated_and_unique() {
+        let dir = TestStore::new("ids");
+        let store = dir.store();
+        for id in ["../x", "a/b", "a.b", "", "x y"] {
+            assert_eq!(store.load(id).unwrap_err(), format!("Invalid profile id '{}'.", id));
+            assert!(store.select(id).is_err());
+            assert!(store.delete(id).is_err());
+        }
+
+        assert_eq!(store.save(None, "Contoso AG", "", config("Contoso")).unwrap(), "contoso-ag");
+        assert_eq!(store.save(None, "Contoso AG", "", config("Contoso")).unwrap(), "contoso-ag-2");
+        assert_eq!(store.save(None, "  ", "", config("Contoso")).unwrap(), "profile");
+        assert!(store.load("missing").unwrap_err().contains("does not exist"));
+    }
+
+    #[test]
+    fn save_clone_select_and_delete() {
+        let dir = TestStore::new("lifecycle");
+        let store = dir.store();
+        assert!(store.list().unwrap().is_empty());
+        assert_eq!(store.selected_config().unwrap_err(), "No configuration given and no profile selected.");
+
+        let id = store.save(None, "Head Office", "main site", config("Contoso")).unwrap();
+        let created = store.load(&id).unwrap().metadata.created;
+        assert_eq!(store.save(Some(&id), "Head Office", "updated", config("Contoso AG")).unwrap(), id);
+        let saved = store.load(&id).unwrap();
+        assert_eq!(saved.metadata.created, created);
+        assert_eq!(saved.metadata.description, "updated");
+        assert_eq!(saved.config.company_prefix, "Contoso AG");
+
+        let copy = store.clone_profile(&id, "Branch").unwrap();
+        assert_eq!(copy, "branch");
+        assert_eq!(store.load(&copy).unwrap().metadata.description, "updated");
+
+        store.select(&copy).unwrap();
+        assert_eq!(store.selected_id().as_deref(), Some("branch"));
+        assert_eq!(store.selected_config().unwrap().company_prefix, "Contoso AG");
+        let list = store.list().unwrap();
+        assert_eq!(list.iter().map(|p| (p.name.as_str(), p.selected)).collect::<Vec<_>>(), [("Branch", true), ("Head Office", false)]);
+
+        store.delete(&copy).unwrap();
+        assert_eq!(store.selected_id(), None);
+        assert!(store.selected_config().is_err());
+        assert!(store.select("branch").is_err());
+        assert_eq!(store.list().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn resolution_order() {
+        let dir = TestStore::new("resolve");
+        let open = || Ok(dir.store());
+        let id = dir.store().save(None, "Saved", "", config("Saved")).unwrap();
+
+        assert!(resolve_with(open, None, None).is_err());
+        assert_eq!(resolve_with(open, Some(config("Form")), None).unwrap().company_prefix, "Form");
+        assert_eq!(resolve_with(open, Some(config("Form")), Some(id.clone())).unwrap().company_prefix, "Saved");
+
+        dir.store().select(&id).unwrap();
+        assert_eq!(resolve_with(open, None, None).unwrap().company_prefix, "Saved");
+        // Changing the machine never falls back to the selected profile.
+        assert!(resolve_for_change_with(open, None, None).unwrap_err().contains("refusing to change the machine"));
+        assert_eq!(resolve_for_change_with(open, None, Some(id)).unwrap().company_prefix, "Saved");
+        assert!(resolve_for_change_with(open, None, Some("missing".into())).is_err());
+    }
+}
@@ -0,0 +1,625 @@
+use crate::config::VpnConfig;
+use crate::jobs;
+use crate::profiles::resolve_config;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{command, AppHandle};
+use x509_parser::prelude::*;
+
+/// Fixed resource every SSTP server listens on (MS-SSTP 2.2.1).
+const SSTP_PATH: &str = "/sra_{BA195980-CD49-458b-9E23-C84EE0ADCD75}/";
+/// HTTP response headers larger than this are cut off.
+const MAX_RESPONSE_BYTES: usize = 16 * 1024;
+/// Certificates expiring within this many days get a warning.
+const EXPIRY_WARNING_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SstpStatus {
+    /// TLS handshake done and the server accepted `SSTP_DUPLEX_POST` with 200.
+    Ok,
+    /// Any other HTTP status, e.g. 404 when RRAS does not serve SSTP on this binding.
+    HttpRejected,
+    NoHttpResponse,
+    TlsFailed,
+    /// TCP connection refused.
+    Unreachable,
+    NoResponse,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub dns_names: Vec<String>,
+    pub ip_addresses: Vec<String>,
+    pub not_before: String,
+    pub not_after: String,
+    pub days_remaining: i64,
+    pub extended_key_usage: Vec<String>,
+    /// SHA-1 thumbprint as shown by the Windows certificate store.
+    pub thumbprint: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NameCheck {
+    pub name: String,
+    pub matched: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SstpProbeResult {
+    pub server: String,
+    pub address: String,
+    pub port: u16,
+    pub status: SstpStatus,
+    pub summary: String,
+    pub tls_version: Option<String>,
+    pub cipher_suite: Option<String>,
+    pub chain_trusted: bool,
+    pub trust_error: Option<String>,
+    /// Chain as sent by the server, leaf first.
+    pub certificates: Vec<CertificateInfo>,
+    /// Leaf certificate checked against `eap_server_names` and `vpn_server_address`.
+    pub name_checks: Vec<NameCheck>,
+    pub http_status: Option<u16>,
+    /// Status line and headers of the reply to `SSTP_DUPLEX_POST`.
+    pub http_response: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+impl SstpProbeResult {
+    fn finish(mut self, status: SstpStatus, summary: String) -> SstpProbeResult {
+        self.status = status;
+        self.summary = summary;
+        self
+    }
+}
+
+/// Accepts every chain so the handshake completes, but records the chain and whether it
+/// validates against `roots`. Handshake signatures are still verified.
+#[derive(Debug)]
+struct RecordingVerifier {
+    provider: Arc<CryptoProvider>,
+    inner: Option<Arc<WebPkiServerVerifier>>,
+    chain: Mutex<Vec<CertificateDer<'static>>>,
+    trust: Mutex<Option<Result<(), String>>>,
+}
+
+impl ServerCertVerifier for RecordingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let mut chain = vec![end_entity.clone().into_owned()];
+        chain.extend(intermediates.iter().map(|c| c.clone().into_owned()));
+        *self.chain.lock().unwrap_or_else(|e| e.into_inner()) = chain;
+
+        let trust = match &self.inner {
+            Some(inner) => inner
+                .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            None => Err("No trusted root certificates available.".to_string()),
+        };
+        *self.trust.lock().unwrap_or_else(|e| e.into_inner()) = Some(trust);
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Root certificates from the Windows certificate store.
+pub fn native_roots() -> RootCertStore {
+    let mut roots = RootCertStore::empty();
+    let result = rustls_native_certs::load_native_certs();
+    for error in &result.errors {
+        log::warn!("Failed to load a native root certificate: {}", error);
+    }
+    roots.add_parsable_certificates(result.certs);
+    roots
+}
+
+fn eku_name(oid: &str) -> String {
+    match oid {
+        "1.3.6.1.5.5.7.3.1" => "Server Authentication".to_string(),
+        "1.3.6.1.5.5.7.3.2" => "Client Authentication".to_string(),
+        "1.3.6.1.5.5.8.2.2" => "IP security IKE intermediate".to_string(),
+        "1.3.6.1.5.5.7.3.5" => "IP security end system".to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn rfc3339(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+pub fn certificate_info(der: &[u8]) -> Result<CertificateInfo, String> {
+    let (_, cert) = X509Certificate::from_der(der).map_err(|e| format!("Failed to parse certificate: {}", e))?;
+
+    let mut dns_names = Vec::new();
+    let mut ip_addresses = Vec::new();
+    if let Ok(Some(san)) = cert.subject_alternative_name() {
+        for name in &san.value.general_names {
+            match name {
+                GeneralName::DNSName(dns) => dns_names.push(dns.to_string()),
+                GeneralName::IPAddress(bytes) => {
+                    if let Ok(v4) = <[u8; 4]>::try_from(*bytes) {
+                        ip_addresses.push(IpAddr::from(v4).to_string());
+                    } else if let Ok(v6) = <[u8; 16]>::try_from(*bytes) {
+                        ip_addresses.push(IpAddr::from(v6).to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut extended_key_usage = Vec::new();
+    if let Ok(Some(eku)) = cert.extended_key_usage() {
+        let eku = eku.value;
+        let flags = [
+            (eku.any, "Any Purpose"),
+            (eku.server_auth, "Server Authentication"),
+            (eku.client_auth, "Client Authentication"),
+            (eku.code_signing, "Code Signing"),
+            (eku.email_protection, "Secure Email"),
+            (eku.time_stamping, "Time Stamping"),
+            (eku.ocsp_signing, "OCSP Signing"),
+        ];
+        extended_key_usage.extend(flags.iter().filter(|(set, _)| *set).map(|(_, name)| name.to_string()));
+        extended_key_usage.extend(eku.other.iter().map(|oid| eku_name(&oid.to_id_string())));
+    }
+
+    let not_after = cert.validity().not_after.timestamp();
+    Ok(CertificateInfo {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        dns_names,
+        ip_addresses,
+        not_before: rfc3339(cert.validity().not_before.timestamp()),
+        not_after: rfc3339(not_after),
+        days_remaining: (not_after - chrono::Utc::now().timestamp()).div_euclid(86400),
+        extended_key_usage,
+        thumbprint: Sha1::digest(der).iter().map(|b| format!("{:02X}", b)).collect(),
+    })
+}
+
+fn common_name(subject: &str) -> Option<&str> {
+    subject.split(", ").find_map(|part| part.strip_prefix("CN="))
+}
+
+/// Whether `cert` is valid for `name`: IP SANs for addresses, DNS SANs (with a single-label
+/// wildcard) otherwise, and the common name only when there are no SANs.
+pub fn name_matches(cert: &CertificateInfo, name: &str) -> bool {
+    let name = name.trim().trim_end_matches('.').to_ascii_lowercase();
+    if let Ok(ip) = name.parse::<IpAddr>() {
+        return cert.ip_addresses.iter().any(|a| a.parse::<IpAddr>().is_ok_and(|a| a == ip));
+    }
+    let matches = |pattern: &str| {
+        let pattern = pattern.trim_end_matches('.').to_ascii_lowercase();
+        match pattern.strip_prefix("*.") {
+            Some(suffix) => name
+                .split_once('.')
+                .is_some_and(|(label, rest)| !label.is_empty() && rest == suffix),
+            None => pattern == name,
+        }
+    };
+    if cert.dns_names.is_empty() && cert.ip_addresses.is_empty() {
+        return common_name(&cert.subject).is_some_and(matches);
+    }
+    cert.dns_names.iter().any(|n| matches(n))
+}
+
+/// Names the server certificate must cover: every `eap_server_names` entry and the server address.
+pub fn expected_names(config: &VpnConfig) -> Vec<String> {
+    let mut names: Vec<String> = config
+        .eap_server_names
+        .split(';')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect();
+    let address = config.vpn_server_address.trim();
+    if !address.is_empty() && !names.iter().any(|n| n.eq_ignore_ascii_case(address)) {
+        names.push(address.to_string());
+    }
+    names
+}
+
+fn sstp_request(host: &str) -> String {
+    let mut id = [0u8; 16];
+    let _ = getrandom::getrandom(&mut id);
+    let hex: String = id.iter().map(|b| format!("{:02X}", b)).collect();
+    let correlation_id = format!("{{{}-{}-{}-{}-{}}}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..]);
+    // Content-Length is the maximum 64-bit value as required by MS-SSTP 3.2.5.1.
+    format!(
+        "SSTP_DUPLEX_POST {} HTTP/1.1\r\nHost: {}\r\nContent-Length: 18446744073709551615\r\nSSTPCORRELATIONID: {}\r\n\r\n",
+        SSTP_PATH, host, correlation_id
+    )
+}
+
+/// Reads until the end of the HTTP headers, the size limit or the read timeout.
+fn read_headers(stream: &mut impl Read) -> std::io::Result<String> {
+    let mut response = Vec::new();
+    let mut buf = [0u8; 4096];
+    while !response.windows(4).any(|w| w == b"\r\n\r\n") && response.len() < MAX_RESPONSE_BYTES {
+        match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => response.extend(&buf[..n]),
+            Err(e) if !response.is_empty() && matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => break,
+            Err(e) if !response.is_empty() && e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+    }
+    let text = String::from_utf8_lossy(&response);
+    Ok(text.split("\r\n\r\n").next().unwrap_or_default().to_string())
+}
+
+/// Connects to `addr`, presents `server` for SNI and the `Host` header, inspects the
+/// certificate chain against `roots` and `names`, and sends `SSTP_DUPLEX_POST`.
+pub fn probe_addr(server: &str, addr: SocketAddr, names: &[String], roots: RootCertStore, timeout: Duration) -> SstpProbeResult {
+    let result = SstpProbeResult {
+        server: server.to_string(),
+        address: addr.ip().to_string(),
+        port: addr.port(),
+        status: SstpStatus::NoResponse,
+        summary: String::new(),
+        tls_version: None,
+        cipher_suite: None,
+        chain_trusted: false,
+        trust_error: None,
+        certificates: Vec::new(),
+        name_checks: Vec::new(),
+        http_status: None,
+        http_response: None,
+        warnings: Vec::new(),
+    };
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let inner = if roots.is_empty() {
+        None
+    } else {
+        WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone()).build().ok()
+    };
+    let verifier = Arc::new(RecordingVerifier {
+        provider: provider.clone(),
+        inner,
+        chain: Mutex::new(Vec::new()),
+        trust: Mutex::new(None),
+    });
+    let config = match ClientConfig::builder_with_provider(provider).with_safe_default_protocol_versions() {
+        Ok(builder) => builder.dangerous().with_custom_certificate_verifier(verifier.clone()).with_no_client_auth(),
+        Err(e) => return result.finish(SstpStatus::TlsFailed, format!("TLS setup failed: {}", e)),
+    };
+    let server_name = match ServerName::try_from(server.to_string()) {
+        Ok(name) => name,
+        Err(e) => return result.finish(SstpStatus::TlsFailed, format!("Invalid server name '{}': {}", server, e)),
+    };
+
+    let mut socket = match TcpStream::connect_timeout(&addr, timeout) {
+        Ok(socket) => socket,
+        Err(e) if e.kind() == ErrorKind::ConnectionRefused => {
+            return result.finish(SstpStatus::Unreachable, "Connection refused.".to_string());
+        }
+        Err(e) => return result.finish(SstpStatus::NoResponse, format!("TCP connect failed: {}", e)),
+    };
+    let _ = socket.set_read_timeout(Some(timeout));
+    let _ = socket.set_write_timeout(Some(timeout));
+
+    let mut result = result;
+    let mut conn = match ClientConnection::new(Arc::new(config), server_name) {
+        Ok(conn) => conn,
+        Err(e) => return result.finish(SstpStatus::TlsFailed, format!("TLS setup failed: {}", e)),
+    };
+    let handshake = (|| {
+        while conn.is_handshaking() {
+            conn.complete_io(&mut socket)?;
+        }
+        Ok::<(), std::io::Error>(())
+    })();
+
+    // The chain is recorded even if the handshake fails later on.
+    let chain = verifier.chain.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    for der in &chain {
+        match certificate_info(der) {
+            Ok(info) => result.certificates.push(info),
+            Err(e) => result.warnings.push(e),
+        }
+    }
+    match verifier.trust.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        Some(Ok(())) => result.chain_trusted = true,
+        Some(Err(e)) => {
+            result.warnings.push(format!("Certificate chain is not trusted: {}", e));
+            result.trust_error = Some(e);
+        }
+        None => {}
+    }
+    if let Some(leaf) = result.certificates.first().cloned() {
+        result.name_checks = names
+            .iter()
+            .map(|name| NameCheck { name: name.clone(), matched: name_matches(&leaf, name) })
+            .collect();
+        for check in result.name_checks.iter().filter(|c| !c.matched) {
+            result.warnings.push(format!("Server certificate does not cover '{}'.", check.name));
+        }
+        if !leaf.extended_key_usage.is_empty()
+            && !leaf.extended_key_usage.iter().any(|e| e == "Server Authentication" || e == "Any Purpose")
+        {
+            result.warnings.push("Server certificate lacks the Server Authentication EKU.".to_string());
+        }
+    }
+    for cert in &result.certificates {
+        if cert.days_remaining < 0 {
+            result.warnings.push(format!("Certificate '{}' expired on {}.", cert.subject, cert.not_after));
+        } else if cert.days_remaining < EXPIRY_WARNING_DAYS {
+            result.warnings.push(format!("Certificate '{}' expires in {} day(s).", cert.subject, cert.days_remaining));
+        }
+    }
+
+    if let Err(e) = handshake {
+        return result.finish(SstpStatus::TlsFailed, format!("TLS handshake failed: {}", e));
+    }
+    result.tls_version = conn.protocol_version().map(|v| format!("{:?}", v));
+    result.cipher_suite = conn.negotiated_cipher_suite().map(|s| format!("{:?}", s.suite()));
+
+    let mut stream = rustls::Stream::new(&mut conn, &mut socket);
+    if let Err(e) = stream.write_all(sstp_request(server).as_bytes()).and_then(|_| stream.flush()) {
+        return result.finish(SstpStatus::NoHttpResponse, format!("Failed to send SSTP_DUPLEX_POST: {}", e));
+    }
+    let headers = match read_headers(&mut stream) {
+        Ok(headers) if !headers.is_empty() => headers,
+        Ok(_) => return result.finish(SstpStatus::NoHttpResponse, "Server closed the connection without an HTTP response.".to_string()),
+        Err(e) => return result.finish(SstpStatus::NoHttpResponse, format!("No HTTP response: {}", e)),
+    };
+    stream.conn.send_close_notify();
+    let _ = stream.flush();
+
+    let status_line = headers.lines().next().unwrap_or_default().to_string();
+    result.http_status = status_line.split_whitespace().nth(1).and_then(|code| code.parse().ok());
+    result.http_response = Some(headers);
+    let tls = result.tls_version.clone().unwrap_or_default();
+    match result.http_status {
+        Some(200) => result.finish(SstpStatus::Ok, format!("SSTP endpoint answering over {} ({}).", tls, status_line)),
+        Some(_) => result.finish(SstpStatus::HttpRejected, format!("SSTP_DUPLEX_POST rejected: {}", status_line)),
+        None => result.finish(SstpStatus::NoHttpResponse, format!("Unexpected reply: {}", status_line)),
+    }
+}
+
+/// Resolves `server` and probes it against the Windows root store.
+pub fn probe(server: &str, port: u16, names: &[String], timeout: Duration) -> Result<SstpProbeResult, String> {
+    let addr = (server, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve {}: {}", server, e))?
+        .next()
+        .ok_or(format!("No address found for {}.", server))?;
+    Ok(probe_addr(server, addr, names, native_roots(), timeout))
+}
+
+/// Probes the SSTP endpoint of the configured server and returns the result as JSON.
+#[command]
+pub async fn probe_sstp(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+    port: Option<u16>,
+    timeout_ms: Option<u64>,
+) -> Result<String, String> {
+    let config = resolve_config(&app, config, profile_id)?;
+    let server = config.vpn_server_address.trim();
+    if server.is_empty() {
+        return Err("No VPN server address configured.".to_string());
+    }
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(5000).max(100));
+    let server = server.to_string();
+    let names = expected_names(&config);
+    let result = jobs::spawn_blocking(move || probe(&server, port.unwrap_or(443), &names, timeout))
+        .await
+        .map_err(|e| format!("SSTP probe failed: {}", e))??;
+    serde_json::to_string(&result).map_err(|e| format!("Failed to serialize SSTP probe result: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair};
+    use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+    use rustls::{ServerConfig, ServerConnection};
+    use std::net::TcpListener;
+
+    const SERVER: &str = "vpn.example.test";
+
+    struct Pki {
+        ca: CertificateDer<'static>,
+        leaf: CertificateDer<'static>,
+        key: Vec<u8>,
+    }
+
+    fn ca(name: &str) -> (rcgen::Certificate, KeyPair) {
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params.distinguished_name.push(DnType::CommonName, name);
+        (params.self_signed(&key).unwrap(), key)
+    }
+
+    /// A test CA and a server certificate for `SERVER` issued by it.
+    fn pki() -> Pki {
+        let (ca, ca_key) = ca("Test Root");
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(vec![SERVER.to_string()]).unwrap();
+        params.distinguished_name.push(DnType::CommonName, SERVER);
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        let leaf = params.signed_by(&key, &ca, &ca_key).unwrap();
+        Pki { ca: ca.der().clone(), leaf: leaf.der().clone(), key: key.serialize_der() }
+    }
+
+    fn roots(ca: &CertificateDer<'static>) -> RootCertStore {
+        let mut roots = RootCertStore::empty();
+        roots.add(ca.clone()).unwrap();
+        roots
+    }
+
+    /// Serves one TLS connection on 127.0.0.1 and answers the request with `status_line`.
+    fn serve(pki: &Pki, status_line: &'static str) -> SocketAddr {
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(pki.key.clone()));
+        let config = ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![pki.leaf.clone(), pki.ca.clone()], key)
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut conn = ServerConnection::new(Arc::new(config)).unwrap();
+            let mut stream = rustls::Stream::new(&mut conn, &mut socket);
+            let request = read_headers(&mut stream).unwrap();
+            assert!(request.starts_with(&format!("SSTP_DUPLEX_POST {} HTTP/1.1", SSTP_PATH)), "{}", request);
+            stream.write_all(format!("{}\r\nContent-Length: 18446744073709551615\r\n\r\n", status_line).as_bytes()).unwrap();
+            stream.flush().unwrap();
+        });
+        addr
+    }
+
+    fn probe_local(server: &str, addr: SocketAddr, roots: RootCertStore) -> SstpProbeResult {
+        probe_addr(server, addr, &[server.to_string()], roots, Duration::from_secs(5))
+    }
+
+    fn cert(subject: &str, dns_names: &[&str], ip_addresses: &[&str]) -> CertificateInfo {
+        CertificateInfo {
+            subject: subject.to_string(),
+            issuer: "CN=Test Root".to_string(),
+            dns_names: dns_names.iter().map(|s| s.to_string()).collect(),
+            ip_addresses: ip_addresses.iter().map(|s| s.to_string()).collect(),
+            not_before: String::new(),
+            not_after: String::new(),
+            days_remaining: 365,
+            extended_key_usage: Vec::new(),
+            thumbprint: String::new(),
+        }
+    }
+
+    #[test]
+    fn trusted_chain_and_accepted_post() {
+        let pki = pki();
+        let result = probe_local(SERVER, serve(&pki, "HTTP/1.1 200 OK"), roots(&pki.ca));
+        assert_eq!(result.status, SstpStatus::Ok, "{}", result.summary);
+        assert!(result.chain_trusted);
+        assert_eq!(result.trust_error, None);
+        assert_eq!(result.http_status, Some(200));
+        assert_eq!(result.certificates.len(), 2);
+        assert_eq!(result.certificates[0].dns_names, [SERVER]);
+        assert_eq!(result.certificates[0].extended_key_usage, ["Server Authentication"]);
+        assert!(result.name_checks.iter().all(|check| check.matched));
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+        assert!(result.tls_version.is_some());
+    }
+
+    #[test]
+    fn untrusted_chain_is_reported() {
+        let pki = pki();
+        let (other, _) = ca("Other Root");
+        let result = probe_local(SERVER, serve(&pki, "HTTP/1.1 200 OK"), roots(other.der()));
+        // The handshake still completes so the chain and the endpoint can be inspected.
+        assert_eq!(result.status, SstpStatus::Ok, "{}", result.summary);
+        assert!(!result.chain_trusted);
+        assert!(result.trust_error.is_some());
+        assert!(result.warnings.iter().any(|w| w.starts_with("Certificate chain is not trusted")));
+
+        let result = probe_local(SERVER, serve(&pki, "HTTP/1.1 200 OK"), RootCertStore::empty());
+        assert!(!result.chain_trusted);
+        assert_eq!(result.trust_error.as_deref(), Some("No trusted root certificates available."));
+    }
+
+    #[test]
+    fn name_mismatch_is_reported() {
+        let pki = pki();
+        let result = probe_local("other.example.test", serve(&pki, "HTTP/1.1 200 OK"), roots(&pki.ca));
+        assert!(!result.chain_trusted);
+        assert_eq!(result.name_checks.len(), 1);
+        assert!(!result.name_checks[0].matched);
+        assert!(result.warnings.iter().any(|w| w == "Server certificate does not cover 'other.example.test'."));
+    }
+
+    #[test]
+    fn rejected_post_is_reported() {
+        let pki = pki();
+        let result = probe_local(SERVER, serve(&pki, "HTTP/1.1 404 Not Found"), roots(&pki.ca));
+        assert_eq!(result.status, SstpStatus::HttpRejected);
+        assert_eq!(result.http_status, Some(404));
+        assert!(result.chain_trusted);
+        assert!(result.summary.contains("404 Not Found"), "{}", result.summary);
+    }
+
+    #[test]
+    fn dns_names_and_wildcards() {
+        let leaf = cert("CN=ignored.example.test", &["vpn.example.test", "*.corp.example.test"], &[]);
+        assert!(name_matches(&leaf, "vpn.example.test"));
+        assert!(name_matches(&leaf, "VPN.Example.Test."));
+        assert!(name_matches(&leaf, "gw1.corp.example.test"));
+        assert!(!name_matches(&leaf, "corp.example.test"));
+        assert!(!name_matches(&leaf, "a.gw1.corp.example.test"));
+        assert!(!name_matches(&leaf, "example.test"));
+        // The common name is ignored once SANs are present.
+        assert!(!name_matches(&leaf, "ignored.example.test"));
+    }
+
+    #[test]
+    fn ip_addresses_only_match_ip_sans() {
+        let leaf = cert("CN=192.0.2.10", &["192.0.2.10"], &["192.0.2.1", "2001:db8::1"]);
+        assert!(name_matches(&leaf, "192.0.2.1"));
+        assert!(name_matches(&leaf, "2001:DB8:0::1"));
+        assert!(!name_matches(&leaf, "192.0.2.10"));
+        assert!(!name_matches(&leaf, "192.0.2.2"));
+    }
+
+    #[test]
+    fn common_name_is_used_without_sans() {
+        let leaf = cert("C=DE, O=Example, CN=vpn.example.test", &[], &[]);
+        assert!(name_matches(&leaf, "vpn.example.test"));
+        assert!(!name_matches(&leaf, "other.example.test"));
+        let wildcard = cert("CN=*.example.test", &[], &[]);
+        assert!(name_matches(&wildcard, "vpn.example.test"));
+        assert!(!name_matches(&cert("O=Example", &[], &[]), "vpn.example.test"));
+    }
+}
@@ -0,0 +1,445 @@
+use crate::audit::{sha256_hex, windows_machine, windows_user, AuditLog};
+use crate::config::VpnConfig;
+use crate::profile_drift::{read_deployed_profile_xml, ProfileContext};
+use crate::profiles::resolve_config;
+use crate::vpn_deploy::{check_export_path, generate_device_profile_xml, generate_user_profile_xml};
+use crate::{event_log, jobs, net_checks, profile_drift, verification, vpn_commands};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek, Write};
+use tauri::{command, AppHandle};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Identifies the zip as a support bundle; see docs/Architecture.md for the layout.
+pub const BUNDLE_FORMAT: &str = "aovpn-support-bundle";
+/// Bump when files are renamed or change meaning; readers reject newer versions.
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+const MANIFEST_PATH: &str = "manifest.json";
+/// Hours of event log included in the bundle.
+const EVENT_HOURS: u32 = 72;
+/// Decompressed size limits when reading a bundle, so a crafted archive cannot exhaust memory.
+const MAX_ENTRY_BYTES: u64 = 16 * 1024 * 1024;
+const MAX_TOTAL_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Which values are replaced with placeholders in every file of the bundle.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RedactionOptions {
+    /// VPN server, DNS suffix, trusted network, ping target, MTU reference host and EAP server names.
+    pub hostnames: bool,
+    /// DNS servers and route prefixes.
+    pub addresses: bool,
+    /// Windows user and machine names, including those in the audit log.
+    pub identities: bool,
+}
+
+/// Replaces sensitive values in text, longest first, case-insensitively, and only where the
+/// match is not part of a longer name or address (`10.0.0.1` stays in `10.0.0.10`).
+pub struct Redactor {
+    replacements: Vec<(String, String)>,
+}
+
+impl Redactor {
+    pub fn new(config: &VpnConfig, options: &RedactionOptions, user: &str, machine: &str) -> Redactor {
+        let mut replacements = Vec::new();
+        let mut add = |value: &str, placeholder: String| {
+            let value = value.trim();
+            if value.len() >= 3 && !replacements.iter().any(|(v, _): &(String, String)| v.eq_ignore_ascii_case(value)) {
+                replacements.push((value.to_string(), placeholder));
+            }
+        };
+        if options.hostnames {
+            add(&config.vpn_server_address, "[vpn-server]".to_string());
+            add(&config.dns_suffix, "[dns-suffix]".to_string());
+            add(&config.internal_ping_target, "[ping-target]".to_string());
+            add(&config.mtu_reference_host, "[mtu-reference-host]".to_string());
+            for (i, name) in config.trusted_network.split(',').enumerate() {
+                add(name, format!("[trusted-network-{}]", i + 1));
+            }
+            for (i, name) in config.eap_server_names.split(';').enumerate() {
+                add(name, format!("[eap-server-{}]", i + 1));
+            }
+        }
+        if options.addresses {
+            for (i, server) in config.dns_server_list().into_iter().enumerate() {
+                add(server, format!("[dns-server-{}]", i + 1));
+            }
+            let routes = config.device_routes.iter().chain(&config.user_routes);
+            for (i, route) in routes.enumerate() {
+                add(route, format!("[route-{}]", i + 1));
+                add(route.split('/').next().unwrap_or_default(), format!("[route-{}-address]", i + 1));
+            }
+        }
+        if options.identities {
+            add(user, "[user]".to_string());
+            // As it appears in JSON, e.g. the audit log.
+            add(&user.replace('\\', "\\\\"), "[user]".to_string());
+            if let Some((_, name)) = user.split_once('\\') {
+                add(name, "[user-name]".to_string());
+            }
+            add(machine, "[machine]".to_string());
+        }
+        replacements.sort_by_key(|(value, _)| std::cmp::Reverse(value.len()));
+        Redactor { replacements }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (value, placeholder) in &self.replacements {
+            let lower = text.to_ascii_lowercase();
+            let needle = value.to_ascii_lowercase();
+            let part_of_word = |c: Option<char>| c.is_some_and(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            let mut out = String::with_capacity(text.len());
+            let mut last = 0;
+            for (start, _) in lower.match_indices(&needle) {
+                let end = start + needle.len();
+                if part_of_word(text[..start].chars().last()) || part_of_word(text[end..].chars().next()) {
+                    continue;
+                }
+                out.push_str(&text[last..start]);
+                out.push_str(placeholder);
+                last = end;
+            }
+            out.push_str(&text[last..]);
+            text = out;
+        }
+        text
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleFile {
+    pub path: String,
+    pub description: String,
+    pub size: usize,
+    pub sha256: String,
+}
+
+/// `manifest.json` at the root of the bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleManifest {
+    pub format: String,
+    pub format_version: u32,
+    pub app_version: String,
+    /// RFC 3339, local time of the machine that created the bundle.
+    pub created: String,
+    pub machine: String,
+    pub user: String,
+    pub profile_id: Option<String>,
+    pub redaction: RedactionOptions,
+    pub files: Vec<BundleFile>,
+    /// Items that could not be collected, as `path: error`.
+    pub errors: Vec<String>,
+}
+
+/// Collects files in memory, redacting each one, then writes the zip in one go.
+pub struct BundleBuilder {
+    redactor: Redactor,
+    files: Vec<(BundleFile, String)>,
+    errors: Vec<String>,
+}
+
+impl BundleBuilder {
+    pub fn new(redactor: Redactor) -> BundleBuilder {
+        BundleBuilder { redactor, files: Vec::new(), errors: Vec::new() }
+    }
+
+    pub fn add(&mut self, path: &str, description: &str, content: Result<String, String>) {
+        match content {
+            Ok(content) => {
+                let content = self.redactor.apply(&content);
+                let file = BundleFile {
+                    path: path.to_string(),
+                    description: description.to_string(),
+                    size: content.len(),
+                    sha256: sha256_hex(content.as_bytes()),
+                };
+                self.files.push((file, content));
+            }
+            Err(e) => self.errors.push(format!("{}: {}", path, self.redactor.apply(e.trim()))),
+        }
+    }
+
+    /// Writes the files and `manifest.json`; `manifest` gets the file list and errors.
+    pub fn write<W: Write + Seek>(self, writer: W, mut manifest: BundleManifest) -> Result<BundleManifest, String> {
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        let mut zip = ZipWriter::new(writer);
+        let fail = |e: &dyn std::fmt::Display| format!("Failed to write support bundle: {}", e);
+        for (file, content) in &self.files {
+            zip.start_file(file.path.as_str(), options).map_err(|e| fail(&e))?;
+            zip.write_all(content.as_bytes()).map_err(|e| fail(&e))?;
+        }
+        manifest.files = self.files.into_iter().map(|(file, _)| file).collect();
+        manifest.errors = self.errors;
+        let json = serde_json::to_string_pretty(&manifest).map_err(|e| fail(&e))?;
+        zip.start_file(MANIFEST_PATH, options).map_err(|e| fail(&e))?;
+        zip.write_all(json.as_bytes()).map_err(|e| fail(&e))?;
+        zip.finish().map_err(|e| fail(&e))?;
+        Ok(manifest)
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleEntry {
+    pub path: String,
+    pub description: String,
+    pub content: String,
+    /// SHA-256 matches the manifest.
+    pub verified: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SupportBundle {
+    pub manifest: BundleManifest,
+    pub files: Vec<BundleEntry>,
+}
+
+/// Reads one entry of at most `MAX_ENTRY_BYTES` and what is left of `budget`.
+fn read_entry<R: Read + Seek>(zip: &mut ZipArchive<R>, path: &str, budget: &mut u64) -> Result<String, String> {
+    let limit = MAX_ENTRY_BYTES.min(*budget);
+    let mut content = String::new();
+    zip.by_name(path)
+        .map_err(|e| format!("{}: {}", path, e))?
+        .take(limit + 1)
+        .read_to_string(&mut content)
+        .map_err(|e| format!("{}: {}", path, e))?;
+    if content.len() as u64 > limit {
+        return Err(if limit < MAX_ENTRY_BYTES {
+            format!("{}: the bundle exceeds {} MB in total", path, MAX_TOTAL_BYTES >> 20)
+        } else {
+            format!("{}: larger than {} MB", path, MAX_ENTRY_BYTES >> 20)
+        });
+    }
+    *budget -= content.len() as u64;
+    Ok(content)
+}
+
+/// Reads a bundle back. Files missing from the archive, changed since export or over the
+/// size limits are reported with `verified: false` rather than failing the whole read.
+pub fn read_bundle<R: Read + Seek>(reader: R) -> Result<SupportBundle, String> {
+    let mut zip = ZipArchive::new(reader).map_err(|e| format!("Not a zip archive: {}", e))?;
+    let mut budget = MAX_TOTAL_BYTES;
+    let manifest: BundleManifest = serde_json::from_str(&read_entry(&mut zip, MANIFEST_PATH, &mut budget)?)
+        .map_err(|e| format!("Invalid support bundle manifest: {}", e))?;
+    if manifest.format != BUNDLE_FORMAT {
+        return Err(format!("Not a support bundle (format '{}').", manifest.format));
+    }
+    if manifest.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(format!(
+            "Support bundle format {} is newer than this app supports ({}).",
+            manifest.format_version, BUNDLE_FORMAT_VERSION
+        ));
+    }
+    let files = manifest
+        .files
+        .iter()
+        .map(|file| {
+            let content = read_entry(&mut zip, &file.path, &mut budget);
+            BundleEntry {
+                path: file.path.clone(),
+                description: file.description.clone(),
+                verified: content.as_ref().is_ok_and(|c| sha256_hex(c.as_bytes()) == file.sha256),
+                content: content.unwrap_or_else(|e| format!("Missing from archive: {}", e)),
+            }
+        })
+        .collect();
+    Ok(SupportBundle { manifest, files })
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string_pretty(value).map_err(|e| format!("Failed to serialize: {}", e))
+}
+
+/// Command results are compact JSON; pretty-print them for review, leave anything else as is.
+fn pretty(result: Result<String, String>) -> Result<String, String> {
+    result.map(|text| match serde_json::from_str::<serde_json::Value>(&text) {
+        Ok(value) => serde_json::to_string_pretty(&value).unwrap_or(text),
+        Err(_) => text,
+    })
+}
+
+fn deployed_xml(context: ProfileContext, name: &str) -> Result<String, String> {
+    read_deployed_profile_xml(context, name)?.ok_or(format!("'{}' is not deployed.", name))
+}
+
+async fn collect(app: &AppHandle, config: &VpnConfig, bundle: &mut BundleBuilder) -> Result<(), String> {
+    let names = config.tunnel_names();
+    let server = config.vpn_server_address.trim().to_string();
+
+    jobs::progress("Adding configuration and profiles");
+    bundle.add("config/effective.json", "Effective VpnConfig after profile resolution", to_json(config));
+    bundle.add("profiles/device-generated.xml", "ProfileXML generated for the Device Tunnel", Ok(generate_device_profile_xml(config)));
+    bundle.add("profiles/user-generated.xml", "ProfileXML generated for the User Tunnel", Ok(generate_user_profile_xml(config)));
+    bundle.add("profiles/device-deployed.xml", "Deployed Device Tunnel ProfileXML", deployed_xml(ProfileContext::Device, &names.device));
+    bundle.add("profiles/user-deployed.xml", "Deployed User Tunnel ProfileXML", deployed_xml(ProfileContext::User, &names.user));
+    bundle.add("profiles/user-all-deployed.xml", "Deployed User Tunnel (All) ProfileXML", deployed_xml(ProfileContext::User, &names.user_all));
+
+    jobs::check_cancelled()?;
+    jobs::progress("Running diagnostics");
+    bundle.add("checks/vpn_status.json", "get_vpn_status", pretty(vpn_commands::get_vpn_status().await));
+    bundle.add("checks/routes.json", "check_routes", pretty(vpn_commands::check_routes().await));
+    bundle.add("checks/certificates.json", "check_certificates", pretty(vpn_commands::check_certificates(&config.root_ca_hash).await));
+    jobs::check_cancelled()?;
+    bundle.add(
+        "checks/profile_drift.json",
+        "check_profile_drift",
+        pretty(profile_drift::check_profile_drift(app.clone(), Some(config.clone()), None).await),
+    );
+    bundle.add(
+        "checks/verification.json",
+        "verify_tunnels without test dial",
+        pretty(verification::verify_tunnels(app.clone(), Some(config.clone()), None, Some(false)).await),
+    );
+    jobs::check_cancelled()?;
+    jobs::progress("Checking ports and DNS");
+    bundle.add("checks/ports.json", "check_ports", pretty(net_checks::check_ports(server.clone(), None).await));
+    bundle.add(
+        "checks/dns.json",
+        "check_dns",
+        pretty(net_checks::check_dns(app.clone(), server, Some(config.clone()), None, None).await),
+    );
+
+    jobs::check_cancelled()?;
+    bundle.add(
+        "events/analysis.json",
+        &format!("RasClient, RasMan and IKEEXT events of the last {} hours", EVENT_HOURS),
+        pretty(event_log::analyze_vpn_events(Some(EVENT_HOURS), None).await),
+    );
+    jobs::progress("Adding the audit log");
+    let audit = AuditLog::open(app).map(|log| log.read_lines().join("\n") + "\n");
+    bundle.add("audit/audit.jsonl", "Audit log, oldest first", audit);
+    Ok(())
+}
+
+/// Collects configuration, profiles, diagnostics, events and the audit log into a zip at `path`.
+/// Returns the manifest as JSON.
+#[command]
+pub async fn create_support_bundle(
+    app: AppHandle,
+    path: String,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+    redaction: Option<RedactionOptions>,
+) -> Result<String, String> {
+    if path.trim().is_empty() {
+        return Err("No path for the support bundle given.".to_string());
+    }
+    check_export_path(&path, &[".zip"])?;
+    let config = resolve_config(&app, config, profile_id.clone())?;
+    let redaction = redaction.unwrap_or_default();
+    let (user, machine) = (windows_user(), windows_machine());
+    let redactor = Redactor::new(&config, &redaction, &user, &machine);
+    let manifest = BundleManifest {
+        format: BUNDLE_FORMAT.to_string(),
+        format_version: BUNDLE_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created: chrono::Local::now().to_rfc3339(),
+        machine: redactor.apply(&machine),
+        user: redactor.apply(&user),
+        profile_id: profile_id.map(|id| redactor.apply(&id)),
+        redaction,
+        files: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    let mut bundle = BundleBuilder::new(redactor);
+    collect(&app, &config, &mut bundle).await?;
+    jobs::progress(&format!("Writing {}", path));
+    let file = std::fs::File::create(&path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+    let manifest = bundle.write(file, manifest)?;
+    to_json(&manifest)
+}
+
+/// Opens a bundle for offline review: manifest plus the content of every file.
+#[command]
+pub async fn read_support_bundle(path: String) -> Result<String, String> {
+    let file = std::fs::File::open(&path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    serde_json::to_string(&read_bundle(file)?).map_err(|e| format!("Failed to serialize support bundle: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn manifest(files: Vec<BundleFile>) -> BundleManifest {
+        BundleManifest {
+            format: BUNDLE_FORMAT.to_string(),
+            format_version: BUNDLE_FORMAT_VERSION,
+            app_version: String::new(),
+            created: String::new(),
+            machine: String::new(),
+            user: String::new(),
+            profile_id: None,
+            redaction: RedactionOptions::default(),
+            files,
+            errors: Vec::new(),
+        }
+    }
+
+    /// A bundle whose entries are stored uncompressed, so large ones stay cheap to build.
+    fn stored_bundle(entries: &[(&str, &str)]) -> Cursor<Vec<u8>> {
+        let files = entries
+            .iter()
+            .map(|(path, content)| BundleFile {
+                path: path.to_string(),
+                description: String::new(),
+                size: content.len(),
+                sha256: sha256_hex(content.as_bytes()),
+            })
+            .collect();
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored).large_file(true);
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, content) in entries {
+            zip.start_file(*path, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.start_file(MANIFEST_PATH, options).unwrap();
+        zip.write_all(serde_json::to_string(&manifest(files)).unwrap().as_bytes()).unwrap();
+        let mut cursor = zip.finish().unwrap();
+        cursor.set_position(0);
+        cursor
+    }
+
+    #[test]
+    fn written_bundles_read_back_redacted_and_verified() {
+        let config = VpnConfig { vpn_server_address: "vpn.contoso.com".to_string(), ..VpnConfig::default() };
+        let options = RedactionOptions { hostnames: true, ..RedactionOptions::default() };
+        let mut builder = BundleBuilder::new(Redactor::new(&config, &options, "CORP\\jdoe", "PC01"));
+        builder.add("checks/a.txt", "A", Ok("Connecting to vpn.contoso.com".to_string()));
+        builder.add("checks/b.txt", "B", Err("vpn.contoso.com unreachable".to_string()));
+        let mut cursor = Cursor::new(Vec::new());
+        builder.write(&mut cursor, manifest(Vec::new())).unwrap();
+
+        let bundle = read_bundle(cursor).unwrap();
+        assert_eq!(bundle.files.len(), 1);
+        assert!(bundle.files[0].verified);
+        assert_eq!(bundle.files[0].content, "Connecting to [vpn-server]");
+        assert_eq!(bundle.manifest.errors, ["checks/b.txt: [vpn-server] unreachable"]);
+    }
+
+    #[test]
+    fn oversized_entries_are_not_read() {
+        let big = "a".repeat(MAX_ENTRY_BYTES as usize + 1);
+        let bundle = read_bundle(stored_bundle(&[("small.txt", "ok"), ("big.txt", &big)])).unwrap();
+        assert!(bundle.files[0].verified);
+        assert!(!bundle.files[1].verified);
+        assert!(bundle.files[1].content.ends_with("big.txt: larger than 16 MB"), "{}", bundle.files[1].content);
+    }
+
+    #[test]
+    fn total_size_is_bounded() {
+        let chunk = "a".repeat(MAX_ENTRY_BYTES as usize);
+        let entries: Vec<(String, &str)> = (0..4).map(|i| (format!("part{}.txt", i), chunk.as_str())).collect();
+        let entries: Vec<(&str, &str)> = entries.iter().map(|(path, content)| (path.as_str(), *content)).collect();
+        let bundle = read_bundle(stored_bundle(&entries)).unwrap();
+        // The manifest counts towards the total, so the fourth full-size entry no longer fits.
+        assert!(bundle.files[..3].iter().all(|f| f.verified));
+        assert!(!bundle.files[3].verified);
+        assert!(bundle.files[3].content.contains("exceeds 64 MB in total"), "{}", bundle.files[3].content);
+    }
+}
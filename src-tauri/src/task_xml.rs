@@ -0,0 +1,383 @@
+use std::os::windows::process::CommandExt;
+use std::path::Path;
+use std::process::Command;
+
+use crate::temp_task::TASK_AUTHOR;
+
+/// Escapes text for element content and attribute values.
+pub fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// ISO 8601 duration as used by Task Scheduler (`PT20S`, `PT2H`).
+pub fn duration(secs: u32) -> String {
+    match secs {
+        0 => "PT0S".to_string(),
+        s if s % 3600 == 0 => format!("PT{}H", s / 3600),
+        s if s % 60 == 0 => format!("PT{}M", s / 60),
+        s => format!("PT{}S", s),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Trigger {
+    Boot,
+    /// Logon of any user.
+    Logon,
+    /// Event log subscription (`Path` and XPath `Select` of a `QueryList`).
+    Event { log: String, select: String },
+}
+
+impl Trigger {
+    /// Start the task whenever `provider` writes `event_id` to `log`.
+    pub fn event(log: &str, provider: &str, event_id: u32) -> Trigger {
+        Trigger::Event {
+            log: log.to_string(),
+            select: format!("*[System[Provider[@Name='{}'] and EventID={}]]", provider, event_id),
+        }
+    }
+
+    fn to_xml(&self) -> String {
+        match self {
+            Trigger::Boot => "    <BootTrigger>\n      <Enabled>true</Enabled>\n    </BootTrigger>\n".to_string(),
+            Trigger::Logon => "    <LogonTrigger>\n      <Enabled>true</Enabled>\n    </LogonTrigger>\n".to_string(),
+            Trigger::Event { log, select } => {
+                // The subscription is itself XML, stored as escaped text.
+                let query = format!(
+                    "<QueryList><Query Id=\"0\" Path=\"{log}\"><Select Path=\"{log}\">{select}</Select></Query></QueryList>",
+                    log = xml_escape(log),
+                    select = xml_escape(select)
+                );
+                format!(
+                    "    <EventTrigger>\n      <Enabled>true</Enabled>\n      <Subscription>{}</Subscription>\n    </EventTrigger>\n",
+                    xml_escape(&query)
+                )
+            }
+        }
+    }
+}
+
+/// Account the task runs as, with the highest privileges available to it.
+#[derive(Debug, Clone)]
+pub struct Principal {
+    /// Account name or SID.
+    pub user_id: String,
+}
+
+impl Principal {
+    pub fn system() -> Principal {
+        Principal {
+            user_id: "S-1-5-18".to_string(),
+        }
+    }
+}
+
+impl Default for Principal {
+    fn default() -> Self {
+        Principal::system()
+    }
+}
+
+/// Overlapping starts are always ignored (`MultipleInstancesPolicy` `IgnoreNew`).
+#[derive(Debug, Clone)]
+pub struct TaskSettings {
+    pub execution_time_limit_secs: u32,
+    pub hidden: bool,
+    /// 0 (realtime) to 10 (idle); Task Scheduler defaults to 7.
+    pub priority: u8,
+}
+
+impl Default for TaskSettings {
+    fn default() -> Self {
+        TaskSettings {
+            execution_time_limit_secs: 3600,
+            hidden: false,
+            priority: 7,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecAction {
+    pub command: String,
+    pub arguments: String,
+}
+
+/// A Task Scheduler 1.2 task. Values are plain text; `to_xml` escapes them.
+#[derive(Debug, Clone, Default)]
+pub struct TaskDefinition {
+    pub author: String,
+    pub description: String,
+    pub triggers: Vec<Trigger>,
+    pub principal: Principal,
+    pub settings: TaskSettings,
+    pub actions: Vec<ExecAction>,
+}
+
+impl TaskDefinition {
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-16\"?>\n");
+        xml.push_str("<Task version=\"1.2\" xmlns=\"http://schemas.microsoft.com/windows/2004/02/mit/task\">\n");
+
+        xml.push_str("  <RegistrationInfo>\n");
+        if !self.author.is_empty() {
+            xml.push_str(&format!("    <Author>{}</Author>\n", xml_escape(&self.author)));
+        }
+        if !self.description.is_empty() {
+            xml.push_str(&format!("    <Description>{}</Description>\n", xml_escape(&self.description)));
+        }
+        xml.push_str("  </RegistrationInfo>\n");
+
+        if self.triggers.is_empty() {
+            xml.push_str("  <Triggers />\n");
+        } else {
+            xml.push_str("  <Triggers>\n");
+            for trigger in &self.triggers {
+                xml.push_str(&trigger.to_xml());
+            }
+            xml.push_str("  </Triggers>\n");
+        }
+
+        xml.push_str("  <Principals>\n");
+        xml.push_str("    <Principal id=\"Author\">\n");
+        xml.push_str(&format!("      <UserId>{}</UserId>\n", xml_escape(&self.principal.user_id)));
+        xml.push_str("      <RunLevel>HighestAvailable</RunLevel>\n");
+        xml.push_str("    </Principal>\n");
+        xml.push_str("  </Principals>\n");
+
+        let s = &self.settings;
+        xml.push_str("  <Settings>\n");
+        xml.push_str("    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>\n");
+        xml.push_str("    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>\n");
+        xml.push_str("    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>\n");
+        xml.push_str("    <AllowHardTerminate>true</AllowHardTerminate>\n");
+        xml.push_str("    <StartWhenAvailable>false</StartWhenAvailable>\n");
+        xml.push_str("    <RunOnlyIfNetworkAvailable>false</RunOnlyIfNetworkAvailable>\n");
+        xml.push_str("    <AllowStartOnDemand>true</AllowStartOnDemand>\n");
+        xml.push_str("    <Enabled>true</Enabled>\n");
+        xml.push_str(&format!("    <Hidden>{}</Hidden>\n", s.hidden));
+        xml.push_str("    <RunOnlyIfIdle>false</RunOnlyIfIdle>\n");
+        xml.push_str("    <WakeToRun>false</WakeToRun>\n");
+        xml.push_str(&format!("    <ExecutionTimeLimit>{}</ExecutionTimeLimit>\n", duration(s.execution_time_limit_secs)));
+        xml.push_str(&format!("    <Priority>{}</Priority>\n", s.priority.min(10)));
+        xml.push_str("  </Settings>\n");
+
+        xml.push_str("  <Actions Context=\"Author\">\n");
+        for action in &self.actions {
+            xml.push_str("    <Exec>\n");
+            xml.push_str(&format!("      <Command>{}</Command>\n", xml_escape(&action.command)));
+            if !action.arguments.is_empty() {
+                xml.push_str(&format!("      <Arguments>{}</Arguments>\n", xml_escape(&action.arguments)));
+            }
+            xml.push_str("    </Exec>\n");
+        }
+        xml.push_str("  </Actions>\n");
+        xml.push_str("</Task>\n");
+        xml
+    }
+
+    /// `to_xml` as UTF-16LE with byte order mark, matching the declared encoding.
+    pub fn to_utf16_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(self.to_xml().encode_utf16().flat_map(|u| u.to_le_bytes()));
+        bytes
+    }
+
+    pub fn write_to(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.to_utf16_bytes()).map_err(|e| format!("Failed to write task XML: {}", e))
+    }
+
+    /// Registers (or replaces) the task under `task_name` via `schtasks /create /xml`. The XML is
+    /// written to `xml_path` (inside a `PrivateDir`) and deleted again afterwards.
+    pub fn register(&self, task_name: &str, xml_path: &Path) -> Result<(), String> {
+        self.write_to(xml_path)?;
+
+        let register_out = Command::new("schtasks")
+            .args(["/create", "/tn", task_name, "/xml", &xml_path.to_string_lossy(), "/f"])
+            .creation_flags(0x08000000)
+            .output();
+        let _ = std::fs::remove_file(xml_path);
+
+        let register_out = register_out.map_err(|e| format!("Failed to run schtasks /create: {}", e))?;
+        if register_out.status.success() {
+            Ok(())
+        } else {
+            Err(format!("Task create failed: {}", String::from_utf8_lossy(&register_out.stderr)))
+        }
+    }
+}
+
+/// An on-demand task running `command` once as SYSTEM, as used for the temporary elevation tasks.
+pub fn system_task(description: &str, command: &str, arguments: &str, hidden: bool) -> TaskDefinition {
+    TaskDefinition {
+        author: TASK_AUTHOR.to_string(),
+        description: description.to_string(),
+        settings: TaskSettings {
+            hidden,
+            priority: 4,
+            ..TaskSettings::default()
+        },
+        actions: vec![ExecAction {
+            command: command.to_string(),
+            arguments: arguments.to_string(),
+        }],
+        ..TaskDefinition::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TASK_NS: &str = "http://schemas.microsoft.com/windows/2004/02/mit/task";
+
+    /// Child elements the Task Scheduler 1.2 schema (task.xsd) allows for the elements we write.
+    fn allowed_children(element: &str) -> Option<&'static [&'static str]> {
+        Some(match element {
+            "Task" => &["RegistrationInfo", "Triggers", "Settings", "Data", "Principals", "Actions"],
+            "RegistrationInfo" => &["URI", "SecurityDescriptor", "Source", "Date", "Author", "Version", "Description", "Documentation"],
+            "Triggers" => &["BootTrigger", "RegistrationTrigger", "IdleTrigger", "TimeTrigger", "EventTrigger", "LogonTrigger", "SessionStateChangeTrigger", "CalendarTrigger"],
+            "BootTrigger" | "LogonTrigger" => &["Enabled", "StartBoundary", "EndBoundary", "Repetition", "ExecutionTimeLimit", "Delay", "UserId"],
+            "EventTrigger" => &["Enabled", "StartBoundary", "EndBoundary", "Repetition", "ExecutionTimeLimit", "Subscription", "Delay", "PeriodOfOccurrence", "NumberOfOccurrences", "MatchingElement", "ValueQueries"],
+            "Principals" => &["Principal"],
+            "Principal" => &["UserId", "LogonType", "GroupId", "DisplayName", "RunLevel", "ProcessTokenSidType", "RequiredPrivileges"],
+            "Settings" => &[
+                "AllowStartOnDemand", "RestartOnFailure", "MultipleInstancesPolicy", "DisallowStartIfOnBatteries",
+                "StopIfGoingOnBatteries", "AllowHardTerminate", "StartWhenAvailable", "NetworkProfileName",
+                "RunOnlyIfNetworkAvailable", "WakeToRun", "Enabled", "Hidden", "DeleteExpiredTaskAfter", "IdleSettings",
+                "NetworkSettings", "ExecutionTimeLimit", "Priority", "RunOnlyIfIdle", "UseUnifiedSchedulingEngine",
+                "DisallowStartOnRemoteAppSession",
+            ],
+            "Actions" => &["Exec", "ComHandler", "SendEmail", "ShowMessage"],
+            "Exec" => &["Command", "Arguments", "WorkingDirectory"],
+            _ => return None,
+        })
+    }
+
+    fn is_duration(value: &str) -> bool {
+        let Some(rest) = value.strip_prefix("PT") else { return false };
+        let digits = rest.trim_end_matches(['H', 'M', 'S']);
+        rest.len() == digits.len() + 1 && !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+    }
+
+    /// Checks `xml` against the parts of task.xsd that apply to what `to_xml` writes: namespace and
+    /// version, allowed and unique children, required elements and the value types.
+    fn validate(xml: &str) {
+        let doc = roxmltree::Document::parse(xml).unwrap_or_else(|e| panic!("not well-formed: {}", e));
+        let root = doc.root_element();
+        assert_eq!(root.tag_name().name(), "Task");
+        assert_eq!(root.tag_name().namespace(), Some(TASK_NS));
+        assert_eq!(root.attribute("version"), Some("1.2"));
+
+        for node in root.descendants().filter(|n| n.is_element()) {
+            assert_eq!(node.tag_name().namespace(), Some(TASK_NS), "{} outside the task namespace", node.tag_name().name());
+            let name = node.tag_name().name();
+            let children: Vec<&str> = node.children().filter(|n| n.is_element()).map(|n| n.tag_name().name()).collect();
+            if let Some(allowed) = allowed_children(name) {
+                for child in &children {
+                    assert!(allowed.contains(child), "<{}> is not allowed in <{}>", child, name);
+                }
+                // Only trigger and action lists repeat their children.
+                if !matches!(name, "Triggers" | "Actions" | "Principals") {
+                    let mut sorted = children.clone();
+                    sorted.sort();
+                    sorted.dedup();
+                    assert_eq!(sorted.len(), children.len(), "repeated child in <{}>", name);
+                }
+            }
+            let text = node.text().unwrap_or_default();
+            match name {
+                "Enabled" | "Hidden" | "AllowStartOnDemand" | "DisallowStartIfOnBatteries" | "StopIfGoingOnBatteries"
+                | "AllowHardTerminate" | "StartWhenAvailable" | "RunOnlyIfNetworkAvailable" | "RunOnlyIfIdle"
+                | "WakeToRun" => assert!(text == "true" || text == "false", "<{}>{}</{}>", name, text, name),
+                "ExecutionTimeLimit" => assert!(is_duration(text), "bad duration {}", text),
+                "Priority" => assert!(text.parse::<u8>().is_ok_and(|p| p <= 10), "bad priority {}", text),
+                "RunLevel" => assert!(text == "LeastPrivilege" || text == "HighestAvailable"),
+                "MultipleInstancesPolicy" => assert!(["Parallel", "Queue", "IgnoreNew", "StopExisting"].contains(&text)),
+                "Command" | "UserId" => assert!(!text.trim().is_empty(), "<{}> must not be empty", name),
+                "Subscription" => {
+                    roxmltree::Document::parse(text).unwrap_or_else(|e| panic!("subscription is not XML: {}", e));
+                }
+                _ => {}
+            }
+        }
+        let actions = root.children().find(|n| n.has_tag_name("Actions")).expect("<Actions> is required");
+        assert!(actions.children().any(|n| n.is_element()), "at least one action is required");
+        assert_eq!(actions.attribute("Context"), Some("Author"));
+        let principal = root.descendants().find(|n| n.has_tag_name("Principal")).expect("<Principal> missing");
+        assert_eq!(principal.attribute("id"), actions.attribute("Context"));
+    }
+
+    fn full_task() -> TaskDefinition {
+        TaskDefinition {
+            author: TASK_AUTHOR.to_string(),
+            description: "Connects \"Contoso\" <Device> & more".to_string(),
+            triggers: vec![
+                Trigger::Boot,
+                Trigger::Logon,
+                Trigger::event("Microsoft-Windows-NetworkProfile/Operational", "Microsoft-Windows-NetworkProfile", 10000),
+            ],
+            principal: Principal::system(),
+            settings: TaskSettings { execution_time_limit_secs: 7200, hidden: true, priority: 4 },
+            actions: vec![ExecAction {
+                command: "powershell.exe".to_string(),
+                arguments: "-Command \"Write-Host 'a & b' <x>\"".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn output_matches_the_task_schema() {
+        validate(&full_task().to_xml());
+        validate(&system_task("Temporary task", "cmd.exe", "/c exit 0", false).to_xml());
+        validate(&system_task("No arguments", "cmd.exe", "", true).to_xml());
+    }
+
+    #[test]
+    fn values_are_escaped_and_round_trip() {
+        let task = full_task();
+        let xml = task.to_xml();
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+        let text = |name: &str| doc.descendants().find(|n| n.has_tag_name(name)).and_then(|n| n.text()).unwrap().to_string();
+        assert_eq!(text("Description"), task.description);
+        assert_eq!(text("Arguments"), task.actions[0].arguments);
+        assert!(!xml.contains("<Device>"));
+
+        // The subscription is escaped twice: once as the query XML, once as element text.
+        let subscription = text("Subscription");
+        let query = roxmltree::Document::parse(&subscription).unwrap();
+        let select = query.descendants().find(|n| n.has_tag_name("Select")).unwrap();
+        assert_eq!(select.attribute("Path"), Some("Microsoft-Windows-NetworkProfile/Operational"));
+        assert_eq!(
+            select.text(),
+            Some("*[System[Provider[@Name='Microsoft-Windows-NetworkProfile'] and EventID=10000]]")
+        );
+    }
+
+    #[test]
+    fn utf16_bytes_match_the_declared_encoding() {
+        let task = full_task();
+        let bytes = task.to_utf16_bytes();
+        assert_eq!(&bytes[..2], &[0xFF, 0xFE], "UTF-16LE byte order mark");
+        assert_eq!(bytes.len() % 2, 0);
+        let units: Vec<u16> = bytes[2..].chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+        let decoded = String::from_utf16(&units).unwrap();
+        assert!(decoded.starts_with("<?xml version=\"1.0\" encoding=\"UTF-16\"?>"));
+        assert_eq!(decoded, task.to_xml());
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(duration(0), "PT0S");
+        assert_eq!(duration(20), "PT20S");
+        assert_eq!(duration(120), "PT2M");
+        assert_eq!(duration(7200), "PT2H");
+        assert_eq!(duration(3660), "PT61M");
+        assert!(is_duration(&duration(u32::MAX)));
+    }
+}
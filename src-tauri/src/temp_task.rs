@@ -0,0 +1,153 @@
+use std::os::windows::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use windows_sys::Win32::Foundation::LocalFree;
+use windows_sys::Win32::Security::Authorization::{ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1};
+use windows_sys::Win32::Security::{PSECURITY_DESCRIPTOR, SECURITY_ATTRIBUTES};
+use windows_sys::Win32::Storage::FileSystem::CreateDirectoryW;
+
+/// Prefix of the private temp directories, used by the startup sweep.
+const DIR_PREFIX: &str = "aovpn-";
+/// Author written into every task we register; the sweep only touches tasks carrying it.
+pub const TASK_AUTHOR: &str = "VPN Manager App";
+/// Tasks and directories younger than this may belong to another instance that is still using
+/// them, even if that instance's process can no longer be found.
+const SWEEP_GRACE: Duration = Duration::from_secs(600);
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// `<prefix>-<pid>-<millis>-<n>`, unique per invocation so concurrent calls never share a task or file.
+pub fn unique_name(prefix: &str) -> String {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or_default();
+    format!("{}-{}-{}-{}", prefix, std::process::id(), millis, COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Protected DACL: full control for SYSTEM and Administrators only, inherited by everything
+/// created inside. Registering SYSTEM tasks needs an elevated token, so that covers this process.
+const PRIVATE_DIR_SDDL: &str = "D:P(A;OICI;FA;;;SY)(A;OICI;FA;;;BA)";
+
+/// Creates `path` with `PRIVATE_DIR_SDDL` in one call. Restricting the ACL afterwards would leave
+/// a window in which the user's unelevated processes could plant a script for the SYSTEM task.
+/// Fails if the path already exists, so a planted directory is never reused.
+fn create_private_dir(path: &Path) -> std::io::Result<()> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "temp path is not valid Unicode"))?;
+    let wide = |s: &str| s.encode_utf16().chain(std::iter::once(0)).collect::<Vec<u16>>();
+    let sddl = wide(PRIVATE_DIR_SDDL);
+    let path = wide(path);
+    let mut descriptor: PSECURITY_DESCRIPTOR = std::ptr::null_mut();
+    // SAFETY: both strings are NUL-terminated and outlive the calls; the descriptor allocated by
+    // ConvertStringSecurityDescriptorToSecurityDescriptorW is freed with LocalFree as documented.
+    unsafe {
+        if ConvertStringSecurityDescriptorToSecurityDescriptorW(sddl.as_ptr(), SDDL_REVISION_1, &mut descriptor, std::ptr::null_mut()) == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let attributes = SECURITY_ATTRIBUTES {
+            nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+            lpSecurityDescriptor: descriptor,
+            bInheritHandle: 0,
+        };
+        let created = CreateDirectoryW(path.as_ptr(), &attributes);
+        let error = std::io::Error::last_os_error();
+        LocalFree(descriptor);
+        if created == 0 {
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+/// A freshly created temp directory accessible only by SYSTEM and Administrators.
+/// Removed with its contents when dropped.
+pub struct PrivateDir {
+    path: PathBuf,
+}
+
+impl PrivateDir {
+    pub fn new(name: &str) -> Result<PrivateDir, String> {
+        let path = std::env::temp_dir().join(format!("{}{}", DIR_PREFIX, name));
+        create_private_dir(&path).map_err(|e| format!("Failed to create temp directory: {}", e))?;
+        Ok(PrivateDir { path })
+    }
+
+    pub fn file(&self, name: &str) -> PathBuf {
+        self.path.join(name)
+    }
+}
+
+impl Drop for PrivateDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// A uniquely named one-shot task and its private directory. Dropping it deletes the task and
+/// the directory, so early returns and panics clean up too.
+pub struct TempTask {
+    pub name: String,
+    pub dir: PrivateDir,
+}
+
+impl TempTask {
+    pub fn new(prefix: &str) -> Result<TempTask, String> {
+        let name = unique_name(prefix);
+        let dir = PrivateDir::new(&name)?;
+        Ok(TempTask { name, dir })
+    }
+}
+
+impl Drop for TempTask {
+    fn drop(&mut self) {
+        let _ = Command::new("schtasks")
+            .args(["/delete", "/tn", &self.name, "/f"])
+            .creation_flags(0x08000000)
+            .output();
+    }
+}
+
+/// Removes `Temp*` tasks and private temp directories left behind by runs that crashed or were killed.
+/// A task is only removed once the process named in it (see `unique_name`) has exited or it is
+/// older than `SWEEP_GRACE`, so tasks another running instance registered but has not started yet
+/// are left alone.
+pub fn sweep_leftovers() -> Result<String, String> {
+    let script = format!(r#"
+$cutoff = [DateTimeOffset]::UtcNow.ToUnixTimeMilliseconds() - {grace_ms}
+$removed = 0
+Get-ScheduledTask -TaskPath '\' -ErrorAction SilentlyContinue |
+    Where-Object {{ $_.TaskName -like 'Temp*' -and $_.Author -eq '{author}' -and $_.State -ne 'Running' }} |
+    Where-Object {{
+        # Names without the pid/time marker come from releases that used fixed task names.
+        if ($_.TaskName -notmatch '-(\d+)-(\d+)-\d+$') {{ return $true }}
+        $owner = Get-Process -Id ([int]$Matches[1]) -ErrorAction SilentlyContinue
+        (-not $owner) -or ([int64]$Matches[2] -lt $cutoff)
+    }} |
+    ForEach-Object {{ Unregister-ScheduledTask -TaskName $_.TaskName -TaskPath '\' -Confirm:$false -ErrorAction SilentlyContinue; $removed++ }}
+$removed
+"#, author = TASK_AUTHOR, grace_ms = SWEEP_GRACE.as_millis());
+    let out = Command::new("powershell")
+        .args(["-NoProfile", "-Command", &script])
+        .creation_flags(0x08000000)
+        .output()
+        .map_err(|e| format!("PowerShell error: {}", e))?;
+    let tasks = String::from_utf8_lossy(&out.stdout).trim().parse::<u32>().unwrap_or(0);
+
+    let cutoff = SystemTime::now() - SWEEP_GRACE;
+    let mut dirs = 0;
+    if let Ok(entries) = std::fs::read_dir(std::env::temp_dir()) {
+        for entry in entries.flatten() {
+            let stale = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .map(|modified| modified < cutoff)
+                .unwrap_or(false);
+            let ours = entry.file_name().to_string_lossy().starts_with(DIR_PREFIX);
+            if stale && ours && std::fs::remove_dir_all(entry.path()).is_ok() {
+                dirs += 1;
+            }
+        }
+    }
+    Ok(format!("Removed {} leftover task(s) and {} temp director(ies).", tasks, dirs))
+}
@@ -0,0 +1,403 @@
+use crate::backups::TunnelKind;
+use crate::config::{ps_quote, VpnConfig};
+use crate::jobs;
+use crate::profile_drift::{diff_profile_xml, read_deployed_profile_xml, ProfileContext};
+use crate::profiles::resolve_config;
+use crate::vpn_deploy::{generate_device_profile_xml, generate_user_profile_xml};
+use serde::{Deserialize, Serialize};
+use std::os::windows::process::CommandExt;
+use std::process::Command;
+use tauri::{command, AppHandle};
+
+/// OID of the Client Authentication extended key usage.
+const CLIENT_AUTH_EKU: &str = "1.3.6.1.5.5.7.3.2";
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Pass,
+    /// Not caused by the deployment itself (e.g. missing certificates); never triggers a rollback.
+    Warning,
+    Fail,
+    Skipped,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckResult {
+    pub check: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+fn check(name: &str, status: CheckStatus, detail: impl Into<String>) -> CheckResult {
+    CheckResult { check: name.to_string(), status, detail: detail.into() }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TunnelVerification {
+    pub name: String,
+    pub kind: TunnelKind,
+    pub passed: bool,
+    pub checks: Vec<CheckResult>,
+}
+
+impl TunnelVerification {
+    fn new(name: &str, kind: TunnelKind, checks: Vec<CheckResult>) -> TunnelVerification {
+        TunnelVerification {
+            name: name.to_string(),
+            kind,
+            passed: !checks.iter().any(|c| c.status == CheckStatus::Fail),
+            checks,
+        }
+    }
+
+    /// One line per check, for the terminal output of deploy commands.
+    pub fn summary(&self) -> String {
+        let mut out = format!("Verification of '{}':", self.name);
+        for c in &self.checks {
+            let mark = match c.status {
+                CheckStatus::Pass => "OK  ",
+                CheckStatus::Warning => "WARN",
+                CheckStatus::Fail => "FAIL",
+                CheckStatus::Skipped => "SKIP",
+            };
+            out.push_str(&format!("\n  [{}] {}: {}", mark, c.check, c.detail));
+        }
+        out
+    }
+}
+
+/// What the machine reports for one connection.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ConnectionFacts {
+    pub exists: bool,
+    pub connection_status: String,
+    pub routes: Vec<String>,
+    /// Namespaces of the NRPT rules in effect.
+    pub nrpt: Vec<String>,
+    pub root_ca: bool,
+    /// Valid certificates with a private key and the Client Authentication EKU.
+    pub client_certs: u32,
+    /// Of those, the ones that also carry the configured EKU (if any).
+    pub eku_certs: u32,
+}
+
+pub fn expected_routes(config: &VpnConfig, kind: TunnelKind) -> Vec<String> {
+    let routes = if kind == TunnelKind::Device { &config.device_routes } else { &config.user_routes };
+    routes
+        .iter()
+        .map(|r| r.trim().to_string())
+        .filter(|r| r.contains('/'))
+        .collect()
+}
+
+/// Evaluates everything except the profile comparison and the test connection.
+pub fn evaluate(config: &VpnConfig, kind: TunnelKind, facts: &ConnectionFacts) -> Vec<CheckResult> {
+    let mut checks = Vec::new();
+    if !facts.exists {
+        checks.push(check("Phonebook entry", CheckStatus::Fail, "Get-VpnConnection does not find the connection."));
+        return checks;
+    }
+    checks.push(check("Phonebook entry", CheckStatus::Pass, format!("Present ({}).", facts.connection_status)));
+
+    let missing: Vec<String> = expected_routes(config, kind)
+        .into_iter()
+        .filter(|r| !facts.routes.iter().any(|d| d.eq_ignore_ascii_case(r)))
+        .collect();
+    // Windows only lists the routes of a connected tunnel, and a just-deployed one usually is not.
+    checks.push(if missing.is_empty() {
+        check("Routes", CheckStatus::Pass, format!("{} route(s) present.", facts.routes.len()))
+    } else if facts.connection_status.eq_ignore_ascii_case("Connected") {
+        check("Routes", CheckStatus::Fail, format!("Missing: {}", missing.join(", ")))
+    } else {
+        check("Routes", CheckStatus::Warning, format!("Not connected; not yet present: {}", missing.join(", ")))
+    });
+
+    let suffix = config.dns_suffix.trim().trim_start_matches('.');
+    if kind == TunnelKind::UserAll || suffix.is_empty() {
+        checks.push(check("DNS/NRPT", CheckStatus::Skipped, "No DomainNameInformation in this profile."));
+    } else {
+        let namespace = format!(".{}", suffix);
+        let present = facts.nrpt.iter().any(|n| n.trim().eq_ignore_ascii_case(&namespace));
+        // NRPT rules are applied asynchronously by the DNS client, so this is only a warning.
+        checks.push(if present {
+            check("DNS/NRPT", CheckStatus::Pass, format!("NRPT rule for {} present.", namespace))
+        } else {
+            check("DNS/NRPT", CheckStatus::Warning, format!("No NRPT rule for {} yet.", namespace))
+        });
+    }
+
+    checks.push(if config.root_ca_hash.trim().is_empty() {
+        check("Root CA", CheckStatus::Skipped, "No root CA thumbprint configured.")
+    } else if facts.root_ca {
+        check("Root CA", CheckStatus::Pass, "Trusted root CA installed.")
+    } else {
+        check("Root CA", CheckStatus::Warning, "Root CA thumbprint not found in LocalMachine\\Root.")
+    });
+
+    let store = if kind == TunnelKind::Device { "LocalMachine\\My" } else { "CurrentUser\\My" };
+    let eku_required = kind != TunnelKind::Device
+        && config.eku_oid.as_deref().is_some_and(|o| !o.trim().is_empty());
+    checks.push(if facts.client_certs == 0 {
+        check("Certificate", CheckStatus::Warning, format!("No valid client authentication certificate in {}.", store))
+    } else if eku_required && facts.eku_certs == 0 {
+        check("Certificate", CheckStatus::Warning, format!("No certificate in {} carries the configured EKU.", store))
+    } else {
+        check("Certificate", CheckStatus::Pass, format!("{} usable certificate(s) in {}.", facts.client_certs, store))
+    });
+    checks
+}
+
+pub fn profile_check(expected_xml: &str, deployed_xml: Option<&str>) -> CheckResult {
+    let Some(deployed) = deployed_xml else {
+        return check("Profile", CheckStatus::Fail, "Not found in MDM_VPNv2_01.");
+    };
+    match diff_profile_xml(expected_xml, deployed) {
+        Ok(d) if d.is_empty() => check("Profile", CheckStatus::Pass, "Deployed ProfileXML matches the configuration."),
+        Ok(d) => {
+            let paths: Vec<&str> = d.iter().map(|d| d.path.as_str()).collect();
+            check("Profile", CheckStatus::Fail, format!("Differs at {}", paths.join(", ")))
+        }
+        Err(e) => check("Profile", CheckStatus::Fail, e),
+    }
+}
+
+fn run_powershell(script: &str) -> Result<String, String> {
+    let output = Command::new("powershell")
+        .args(["-Command", script])
+        .creation_flags(0x08000000)
+        .output()
+        .map_err(|e| format!("PowerShell error: {}", e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).to_string())
+    }
+}
+
+fn facts_script(config: &VpnConfig, kind: TunnelKind, name: &str) -> String {
+    format!(r#"
+$c = Get-VpnConnection -Name {name} {scope} -ErrorAction SilentlyContinue
+$nrpt = @(Get-DnsClientNrptRule -ErrorAction SilentlyContinue | ForEach-Object {{ $_.Namespace }})
+$thumb = {root_ca}.Replace(' ', '')
+$rootCa = $thumb -ne '' -and (Test-Path "Cert:\LocalMachine\Root\$thumb")
+$certs = @(Get-ChildItem "{store}" -ErrorAction SilentlyContinue | Where-Object {{
+    $_.HasPrivateKey -and $_.NotAfter -gt (Get-Date) -and ($_.EnhancedKeyUsageList.ObjectId -contains "{client_auth}")
+}})
+$ekuCerts = @($certs | Where-Object {{ {eku_oid} -ne '' -and ($_.EnhancedKeyUsageList.ObjectId -contains {eku_oid}) }})
+@{{
+    exists = [bool]$c
+    connectionStatus = "$($c.ConnectionStatus)"
+    routes = @($c.Routes | ForEach-Object {{ "$($_.DestinationPrefix)" }})
+    nrpt = $nrpt
+    rootCa = [bool]$rootCa
+    clientCerts = $certs.Count
+    ekuCerts = $ekuCerts.Count
+}} | ConvertTo-Json -Compress
+"#,
+        name = ps_quote(name),
+        scope = if kind == TunnelKind::User { "" } else { "-AllUserConnection" },
+        root_ca = ps_quote(&config.root_ca_hash),
+        store = if kind == TunnelKind::Device { "Cert:\\LocalMachine\\My" } else { "Cert:\\CurrentUser\\My" },
+        client_auth = CLIENT_AUTH_EKU,
+        eku_oid = ps_quote(config.eku_oid.as_deref().unwrap_or_default().trim()),
+    )
+}
+
+pub fn read_facts(config: &VpnConfig, kind: TunnelKind, name: &str) -> Result<ConnectionFacts, String> {
+    let output = run_powershell(&facts_script(config, kind, name))?;
+    serde_json::from_str(output.trim()).map_err(|e| format!("Unexpected verification output: {}", e))
+}
+
+/// Dials the connection and reports whether it comes up. The Device Tunnel is dialed
+/// as SYSTEM, so the status is polled afterwards.
+pub fn test_connection(kind: TunnelKind, name: &str) -> CheckResult {
+    let dial = match kind {
+        TunnelKind::Device => crate::vpn_deploy::dial_as_system(name),
+        _ => run_powershell(&format!("rasdial {}", ps_quote(name))),
+    };
+    if let Err(e) = dial {
+        return check("Test connection", CheckStatus::Fail, e);
+    }
+    let scope = if kind == TunnelKind::User { "" } else { "-AllUserConnection" };
+    let script = format!(
+        "for ($i = 0; $i -lt 15; $i++) {{ $s = (Get-VpnConnection -Name {} {} -ErrorAction SilentlyContinue).ConnectionStatus; if ($s -eq 'Connected') {{ break }}; Start-Sleep -Seconds 2 }}; \"$s\"",
+        ps_quote(name), scope
+    );
+    match run_powershell(&script) {
+        Ok(status) if status.trim() == "Connected" => check("Test connection", CheckStatus::Pass, "Connected."),
+        Ok(status) => check("Test connection", CheckStatus::Fail, format!("Status after 30 s: {}", status.trim())),
+        Err(e) => check("Test connection", CheckStatus::Fail, e),
+    }
+}
+
+/// Full checklist for one tunnel.
+pub fn verify_tunnel(config: &VpnConfig, kind: TunnelKind, test: bool) -> TunnelVerification {
+    let names = config.tunnel_names();
+    let (name, expected_xml) = match kind {
+        TunnelKind::Device => (names.device, Some(generate_device_profile_xml(config))),
+        TunnelKind::User => (names.user, Some(generate_user_profile_xml(config))),
+        TunnelKind::UserAll => (names.user_all, None),
+    };
+
+    let mut checks = match read_facts(config, kind, &name) {
+        Ok(facts) => evaluate(config, kind, &facts),
+        Err(e) => vec![check("Phonebook entry", CheckStatus::Fail, e)],
+    };
+    if let Some(expected) = expected_xml {
+        let context = if kind == TunnelKind::Device { ProfileContext::Device } else { ProfileContext::User };
+        checks.insert(
+            1.min(checks.len()),
+            match read_deployed_profile_xml(context, &name) {
+                Ok(deployed) => profile_check(&expected, deployed.as_deref()),
+                Err(e) => check("Profile", CheckStatus::Fail, e),
+            },
+        );
+    }
+    let exists = checks.first().is_some_and(|c| c.status == CheckStatus::Pass);
+    checks.push(match (test, exists) {
+        (true, true) => test_connection(kind, &name),
+        (true, false) => check("Test connection", CheckStatus::Skipped, "Connection does not exist."),
+        (false, _) => check("Test connection", CheckStatus::Skipped, "Not requested."),
+    });
+    TunnelVerification::new(&name, kind, checks)
+}
+
+/// Verification run by deploy commands: appends the checklist to `output`, or fails
+/// (which triggers the rollback) if any check failed.
+pub fn verify_after_deploy(config: &VpnConfig, kind: TunnelKind, output: String) -> Result<String, String> {
+    jobs::progress("Running post-deployment checks");
+    let verification = verify_tunnel(config, kind, false);
+    if verification.passed {
+        Ok(format!("{}\n{}", output.trim_end(), verification.summary()))
+    } else {
+        Err(format!("Post-deployment verification failed.\n{}", verification.summary()))
+    }
+}
+
+#[command]
+pub async fn verify_tunnels(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+    test_connection: Option<bool>,
+) -> Result<String, String> {
+    let config = resolve_config(&app, config, profile_id)?;
+    let test = test_connection.unwrap_or(false);
+    let mut results = vec![verify_tunnel(&config, TunnelKind::Device, test)];
+    // Only one of the two User Tunnel variants is normally deployed.
+    let user_all = config.tunnel_names().user_all;
+    let user_all_exists = read_facts(&config, TunnelKind::UserAll, &user_all).is_ok_and(|f| f.exists);
+    results.push(verify_tunnel(&config, if user_all_exists { TunnelKind::UserAll } else { TunnelKind::User }, test));
+    serde_json::to_string(&results).map_err(|e| format!("Failed to serialize verification: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> VpnConfig {
+        VpnConfig {
+            company_prefix: "Contoso".into(),
+            vpn_server_address: "vpn.contoso.com".into(),
+            dns_suffix: "corp.contoso.com".into(),
+            root_ca_hash: "AB CD".into(),
+            device_routes: vec!["10.0.0.0/24".into()],
+            user_routes: vec!["10.0.0.0/8".into(), " 172.16.0.0/12 ".into(), "bogus".into()],
+            eku_oid: Some("1.3.6.1.4.1.311.99".into()),
+            ..VpnConfig::default()
+        }
+    }
+
+    fn facts(status: &str, routes: &[&str]) -> ConnectionFacts {
+        ConnectionFacts {
+            exists: true,
+            connection_status: status.into(),
+            routes: routes.iter().map(|r| r.to_string()).collect(),
+            nrpt: vec![".corp.contoso.com".into()],
+            root_ca: true,
+            client_certs: 1,
+            eku_certs: 1,
+        }
+    }
+
+    fn status_of(checks: &[CheckResult], name: &str) -> CheckStatus {
+        checks.iter().find(|c| c.check == name).unwrap_or_else(|| panic!("no {} check", name)).status
+    }
+
+    #[test]
+    fn complete_connection_passes() {
+        let checks = evaluate(&config(), TunnelKind::User, &facts("Connected", &["10.0.0.0/8", "172.16.0.0/12"]));
+        assert_eq!(checks.len(), 5);
+        assert!(checks.iter().all(|c| c.status == CheckStatus::Pass), "{:?}", checks);
+        assert_eq!(expected_routes(&config(), TunnelKind::User), ["10.0.0.0/8", "172.16.0.0/12"]);
+    }
+
+    #[test]
+    fn missing_connection_fails_without_further_checks() {
+        let checks = evaluate(&config(), TunnelKind::Device, &ConnectionFacts::default());
+        assert_eq!(checks.len(), 1);
+        assert_eq!(status_of(&checks, "Phonebook entry"), CheckStatus::Fail);
+    }
+
+    #[test]
+    fn missing_routes_fail_only_while_connected() {
+        let connected = evaluate(&config(), TunnelKind::User, &facts("Connected", &["10.0.0.0/8"]));
+        assert_eq!(status_of(&connected, "Routes"), CheckStatus::Fail);
+        assert!(connected.iter().any(|c| c.detail == "Missing: 172.16.0.0/12"));
+
+        let disconnected = evaluate(&config(), TunnelKind::User, &facts("Disconnected", &[]));
+        assert_eq!(status_of(&disconnected, "Routes"), CheckStatus::Warning);
+        assert!(TunnelVerification::new("Contoso User Tunnel", TunnelKind::User, disconnected).passed);
+    }
+
+    #[test]
+    fn machine_state_outside_the_profile_only_warns() {
+        let state = ConnectionFacts { nrpt: Vec::new(), root_ca: false, client_certs: 2, eku_certs: 0, ..facts("Connected", &["10.0.0.0/24"]) };
+        let device = evaluate(&config(), TunnelKind::Device, &state);
+        assert_eq!(status_of(&device, "DNS/NRPT"), CheckStatus::Warning);
+        assert_eq!(status_of(&device, "Root CA"), CheckStatus::Warning);
+        // The configured EKU only applies to user certificates.
+        assert_eq!(status_of(&device, "Certificate"), CheckStatus::Pass);
+
+        let user = evaluate(&config(), TunnelKind::User, &ConnectionFacts { routes: vec!["10.0.0.0/8".into(), "172.16.0.0/12".into()], ..state });
+        assert_eq!(status_of(&user, "Certificate"), CheckStatus::Warning);
+        assert!(TunnelVerification::new("Contoso User Tunnel", TunnelKind::User, user).passed);
+
+        let unconfigured = VpnConfig { dns_suffix: String::new(), root_ca_hash: String::new(), ..config() };
+        let checks = evaluate(&unconfigured, TunnelKind::UserAll, &ConnectionFacts { client_certs: 0, ..facts("Connected", &["10.0.0.0/8", "172.16.0.0/12"]) });
+        assert_eq!(status_of(&checks, "DNS/NRPT"), CheckStatus::Skipped);
+        assert_eq!(status_of(&checks, "Root CA"), CheckStatus::Skipped);
+        assert_eq!(status_of(&checks, "Certificate"), CheckStatus::Warning);
+    }
+
+    #[test]
+    fn profile_check_compares_the_deployed_xml() {
+        let expected = generate_user_profile_xml(&config());
+        assert_eq!(profile_check(&expected, Some(&expected)).status, CheckStatus::Pass);
+        assert_eq!(profile_check(&expected, None).status, CheckStatus::Fail);
+
+        let changed = generate_user_profile_xml(&VpnConfig { vpn_server_address: "vpn2.contoso.com".into(), ..config() });
+        let result = profile_check(&expected, Some(&changed));
+        assert_eq!(result.status, CheckStatus::Fail);
+        assert!(result.detail.starts_with("Differs at "), "{}", result.detail);
+
+        assert_eq!(profile_check(&expected, Some("<VPNProfile>")).status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn facts_script_quotes_names_and_settings() {
+        let config = VpnConfig {
+            root_ca_hash: "AB'CD".into(),
+            eku_oid: Some(" 1.2.3\"; $(whoami) ".into()),
+            ..config()
+        };
+        let script = facts_script(&config, TunnelKind::UserAll, "Contoso's $(VPN)");
+        assert!(script.contains("Get-VpnConnection -Name 'Contoso''s $(VPN)' -AllUserConnection "));
+        assert!(script.contains("$thumb = 'AB''CD'.Replace(' ', '')"));
+        assert!(script.contains("Where-Object { '1.2.3\"; $(whoami)' -ne '' -and ($_.EnhancedKeyUsageList.ObjectId -contains '1.2.3\"; $(whoami)') }"));
+    }
+}
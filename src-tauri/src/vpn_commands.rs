@@ -1,4 +1,5 @@
-use tauri::command;
+use crate::audit::{self, audited};
+use tauri::{command, AppHandle};
 use std::process::Command;
 use std::os::windows::process::CommandExt;
 
@@ -18,9 +19,18 @@ pub async fn get_vpn_status() -> Result<String, String> {
 }
 
 #[command]
-pub async fn get_vpn_xml(name: &str, config: crate::config::VpnConfig) -> Result<String, String> {
-    let lower_name = name.to_lowercase();
-    if lower_name.contains("device") {
+pub async fn get_vpn_xml(
+    app: AppHandle,
+    name: &str,
+    config: Option<crate::config::VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let config = crate::profiles::resolve_config(&app, config, profile_id)?;
+    let names = config.tunnel_names();
+    // Configured names first; otherwise fall back to guessing from the name.
+    let is_device = name.trim().eq_ignore_ascii_case(&names.device)
+        || (!name.trim().eq_ignore_ascii_case(&names.user) && name.to_lowercase().contains("device"));
+    if is_device {
         Ok(crate::vpn_deploy::generate_device_profile_xml(&config))
     } else {
         Ok(crate::vpn_deploy::generate_user_profile_xml(&config))
@@ -122,87 +132,6 @@ pub async fn check_routes() -> Result<String, String> {
     }
 }
 
-#[command]
-pub async fn check_dns(server: &str) -> Result<String, String> {
-    let script = format!(r#"
-try {{
-    $res = Resolve-DnsName -Name "{server}" -ErrorAction Stop | Select-Object -First 1
-    if ($res.IPAddress) {{ "DNS Resolved: $($res.IPAddress)" }} else {{ "DNS Resolved: $($res.IP4Address)" }}
-}} catch {{
-    "Failed to resolve DNS. The server name might be incorrect or unreachable."
-}}
-"#);
-    let output = Command::new("powershell")
-        .args(&["-Command", &script])
-        .creation_flags(0x08000000)
-        .output()
-        .map_err(|e| format!("Failed to check DNS: {}", e))?;
-
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
-    }
-}
-
-#[command]
-pub async fn check_ports(server: &str) -> Result<String, String> {
-    // We rewrite the powershell script to use TcpClient with a 2 second timeout instead of Test-NetConnection 
-    // to avoid massive 21-second timeouts per port, and we also mention UDP for 500/4500.
-    let script = format!(r#"
-$out = @()
-foreach ($port in @(443, 500, 4500)) {{
-    $client = New-Object System.Net.Sockets.TcpClient
-    $task = $client.ConnectAsync("{server}", $port)
-    if ($task.Wait(2000)) {{
-        if ($client.Connected) {{
-            $out += "Port $port (TCP) is OPEN"
-        }} else {{
-            $out += "Port $port (TCP) is CLOSED/BLOCKED"
-        }}
-    }} else {{
-        $out += "Port $port (TCP) is TIMEOUT/BLOCKED"
-    }}
-    $client.Close()
-}}
-
-foreach ($port in @(500, 4500)) {{
-    $udp = New-Object System.Net.Sockets.UdpClient
-    $udp.Client.ReceiveTimeout = 2000
-    try {{
-        $udp.Connect("{server}", $port)
-        $bytes = [Text.Encoding]::ASCII.GetBytes("ping")
-        [void]$udp.Send($bytes, $bytes.Length)
-        $ep = New-Object System.Net.IPEndPoint([System.Net.IPAddress]::Any, 0)
-        [void]$udp.Receive([ref]$ep)
-        $out += "Port $port (UDP) is OPEN (Responded)"
-    }} catch {{
-        $errStr = $_.Exception.ToString()
-        if ($errStr -match "forcibly closed" -or $errStr -match "gewaltsam" -or $errStr -match "10054") {{
-            $out += "Port $port (UDP) is CLOSED (ICMP Port Unreachable)"
-        }} else {{
-            $out += "Port $port (UDP) is OPEN/FILTERED (No rejection received)"
-        }}
-    }} finally {{
-        $udp.Close()
-    }}
-}}
-$out -join "`n"
-"#);
-
-    let output = Command::new("powershell")
-        .args(&["-Command", &script])
-        .creation_flags(0x08000000)
-        .output()
-        .map_err(|e| format!("Failed to check ports: {}", e))?;
-
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
-    }
-}
-
 #[command]
 pub async fn check_nps_role() -> Result<String, String> {
     let script = r#"
@@ -223,7 +152,7 @@ if ($role -and $role.Installed) { "INSTALLED" } else { "MISSING" }
 }
 
 #[command]
-pub async fn install_nps_role() -> Result<String, String> {
+pub async fn install_nps_role(app: AppHandle) -> Result<String, String> {
     let script = r#"
 Write-Host "Installing Network Policy and Access Services (NPAS)..."
 $res = Install-WindowsFeature -Name NPAS -IncludeManagementTools
@@ -235,17 +164,20 @@ if ($res.Success) {
     exit 1
 }
 "#;
-    let output = Command::new("powershell")
-        .args(&["-Command", script])
-        .creation_flags(0x08000000)
-        .output()
-        .map_err(|e| format!("Failed to install NPS role: {}", e))?;
-
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
-    }
+    audited(&app, "install_nps_role", None, || {
+        audit::track_script(script);
+        let output = Command::new("powershell")
+            .args(&["-Command", script])
+            .creation_flags(0x08000000)
+            .output()
+            .map_err(|e| format!("Failed to install NPS role: {}", e))?;
+
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).to_string())
+        }
+    })
 }
 
 #[command]
@@ -268,7 +200,7 @@ if ($role -and $role.Installed) { "INSTALLED" } else { "MISSING" }
 }
 
 #[command]
-pub async fn install_rras_role() -> Result<String, String> {
+pub async fn install_rras_role(app: AppHandle) -> Result<String, String> {
     let script = r#"
 Write-Host "Installing Routing and Remote Access (RRAS)..."
 $res = Install-WindowsFeature -Name DirectAccess-VPN -IncludeManagementTools
@@ -280,17 +212,20 @@ if ($res.Success) {
     exit 1
 }
 "#;
-    let output = Command::new("powershell")
-        .args(&["-Command", script])
-        .creation_flags(0x08000000)
-        .output()
-        .map_err(|e| format!("Failed to install RRAS role: {}", e))?;
-
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
-    }
+    audited(&app, "install_rras_role", None, || {
+        audit::track_script(script);
+        let output = Command::new("powershell")
+            .args(&["-Command", script])
+            .creation_flags(0x08000000)
+            .output()
+            .map_err(|e| format!("Failed to install RRAS role: {}", e))?;
+
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).to_string())
+        }
+    })
 }
 
 #[command]
@@ -313,7 +248,7 @@ if ($role -and $role.Installed) { "INSTALLED" } else { "MISSING" }
 }
 
 #[command]
-pub async fn install_iis_role() -> Result<String, String> {
+pub async fn install_iis_role(app: AppHandle) -> Result<String, String> {
     let script = r#"
 Write-Host "Installing IIS Web Server for CRL Distribution Point..."
 $res = Install-WindowsFeature -Name Web-Server -IncludeManagementTools
@@ -325,17 +260,20 @@ if ($res.Success) {
     exit 1
 }
 "#;
-    let output = Command::new("powershell")
-        .args(&["-Command", script])
-        .creation_flags(0x08000000)
-        .output()
-        .map_err(|e| format!("Failed to install IIS role: {}", e))?;
-
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
-    }
+    audited(&app, "install_iis_role", None, || {
+        audit::track_script(script);
+        let output = Command::new("powershell")
+            .args(&["-Command", script])
+            .creation_flags(0x08000000)
+            .output()
+            .map_err(|e| format!("Failed to install IIS role: {}", e))?;
+
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).to_string())
+        }
+    })
 }
 
 #[command]
@@ -1,7 +1,27 @@
+use crate::audit::{self, audited};
 use crate::config::{encode_powershell_script, VpnConfig};
+use crate::ipsec::IOS_MAC_PRESET;
+use crate::jobs;
+use crate::phonebook::with_orphan_warnings;
+use crate::backups::{backup_before, backup_note, deploy_with_backup, TunnelKind};
+use crate::profiles::{resolve_config, resolve_config_for_change};
+use crate::task_xml::system_task;
+use crate::temp_task::{unique_name, PrivateDir, TempTask};
+use crate::verification::verify_after_deploy;
+use serde::Deserialize;
+use std::fmt;
 use std::process::Command;
 use std::os::windows::process::CommandExt;
-use tauri::command;
+use tauri::{command, AppHandle};
+
+/// Only written when set, so profiles deployed before the option existed still match.
+fn fragmentation_xml(config: &VpnConfig) -> &'static str {
+    if config.disable_ikev2_fragmentation {
+        "    <DisableIKEv2Fragmentation>true</DisableIKEv2Fragmentation>\n"
+    } else {
+        ""
+    }
+}
 
 #[command]
 pub fn generate_device_profile_xml(config: &VpnConfig) -> String {
@@ -25,7 +45,7 @@ pub fn generate_device_profile_xml(config: &VpnConfig) -> String {
     <Authentication>
       <MachineMethod>Certificate</MachineMethod>
     </Authentication>
-  </NativeProfile>
+{fragmentation_xml}  </NativeProfile>
 {routes_xml}  <DomainNameInformation>
     <DomainName>.{dns_suffix}</DomainName>
     <DnsServers>{dns_servers}</DnsServers> 
@@ -40,15 +60,52 @@ pub fn generate_device_profile_xml(config: &VpnConfig) -> String {
         trusted_network = config.trusted_network,
         dns_servers = config.dns_servers,
         always_on = if config.device_tunnel_always_on { "true" } else { "false" },
+        fragmentation_xml = fragmentation_xml(config),
     )
 }
 
+/// How a deploy treats an existing profile of the same name.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum DeployMode {
+    /// Skip when the deployed profile already matches, otherwise `ModifyInstance` in place.
+    /// Keeps the tunnel connected; falls back to recreating if the update is rejected.
+    #[default]
+    Update,
+    /// Always `DeleteInstance` + `CreateInstance` (the behaviour up to 1.7).
+    Recreate,
+}
+
+impl fmt::Display for DeployMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            DeployMode::Update => "Update",
+            DeployMode::Recreate => "Recreate",
+        })
+    }
+}
+
 #[command]
-pub async fn deploy_device_tunnel(config: VpnConfig) -> Result<String, String> {
+pub async fn deploy_device_tunnel(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+    mode: Option<DeployMode>,
+) -> Result<String, String> {
+    let config = resolve_config_for_change(&app, config, profile_id)?;
     let profile_xml = generate_device_profile_xml(&config);
+    let profile_name = config.tunnel_names().device;
+    let mode = mode.unwrap_or_default();
+    audited(&app, "deploy_device_tunnel", Some(&config), || {
+        deploy_with_backup(&app, "deploy_device_tunnel", TunnelKind::Device, &profile_name, Some(&profile_xml), mode, || {
+            apply_device_profile(&profile_name, &profile_xml, mode)
+                .and_then(|output| verify_after_deploy(&config, TunnelKind::Device, output))
+        })
+    })
+}
 
-    let profile_name = format!("{} Device Tunnel", config.company_prefix);
-
+/// Creates the Device Tunnel `MDM_VPNv2_01` instance, or updates it according to `mode`.
+pub(crate) fn apply_device_profile(profile_name: &str, profile_xml: &str, mode: DeployMode) -> Result<String, String> {
     let script = format!(r#"
 $ErrorActionPreference = "Stop"
 $ProfileXML = @"
@@ -61,27 +118,38 @@ $session = New-CimSession
 $namespace = "root\cimv2\mdm\dmmap"
 $className = "MDM_VPNv2_01"
 
-$instances = $session.EnumerateInstances($namespace, $className)
-if ($instances) {{
-    foreach ($i in $instances) {{
-        if ($i.InstanceID -eq $profileNameEscaped) {{
-            Write-Host "Removing existing Device Tunnel..."
-            $session.DeleteInstance($namespace, $i)
-        }}
+$existing = $session.EnumerateInstances($namespace, $className) | Where-Object {{ $_.InstanceID -eq $profileNameEscaped }} | Select-Object -First 1
+$updated = $false
+
+if ($existing -and "{mode}" -eq "Update") {{
+    try {{
+        Write-Host "Updating existing Device Tunnel in place..."
+        $existing.CimInstanceProperties["ProfileXML"].Value = $escapedXml
+        $session.ModifyInstance($namespace, $existing) | Out-Null
+        $updated = $true
+    }} catch {{
+        Write-Host "In-place update failed ($($_.Exception.Message)), recreating the profile..."
     }}
 }}
 
-Write-Host "Creating new Device Tunnel profile..."
-$newInstance = New-Object Microsoft.Management.Infrastructure.CimInstance $className, $namespace
-$newInstance.CimInstanceProperties.Add([Microsoft.Management.Infrastructure.CimProperty]::Create("ParentID", "./Vendor/MSFT/VPNv2", "String", "Key"))
-$newInstance.CimInstanceProperties.Add([Microsoft.Management.Infrastructure.CimProperty]::Create("InstanceID", "$profileNameEscaped", "String", "Key"))
-$newInstance.CimInstanceProperties.Add([Microsoft.Management.Infrastructure.CimProperty]::Create("ProfileXML", "$escapedXml", "String", "Property"))
+if (-not $updated) {{
+    if ($existing) {{
+        Write-Host "Removing existing Device Tunnel..."
+        $session.DeleteInstance($namespace, $existing)
+    }}
+
+    Write-Host "Creating new Device Tunnel profile..."
+    $newInstance = New-Object Microsoft.Management.Infrastructure.CimInstance $className, $namespace
+    $newInstance.CimInstanceProperties.Add([Microsoft.Management.Infrastructure.CimProperty]::Create("ParentID", "./Vendor/MSFT/VPNv2", "String", "Key"))
+    $newInstance.CimInstanceProperties.Add([Microsoft.Management.Infrastructure.CimProperty]::Create("InstanceID", "$profileNameEscaped", "String", "Key"))
+    $newInstance.CimInstanceProperties.Add([Microsoft.Management.Infrastructure.CimProperty]::Create("ProfileXML", "$escapedXml", "String", "Property"))
 
-$session.CreateInstance($namespace, $newInstance)
+    $session.CreateInstance($namespace, $newInstance)
+}}
 
 $check = $session.EnumerateInstances($namespace, $className) | Where-Object {{ $_.InstanceID -eq $profileNameEscaped }}
 if ($check) {{
-    Write-Host "SUCCESS: Device Tunnel was created."
+    if ($updated) {{ Write-Host "SUCCESS: Device Tunnel was updated." }} else {{ Write-Host "SUCCESS: Device Tunnel was created." }}
 }} else {{
     throw "FAILURE: Profile was created but could not be found via WMI."
 }}
@@ -91,183 +159,145 @@ if ($check) {{
     run_as_system_task("TempDeployDeviceTunnel", &script)
 }
 
-fn run_as_system_task(task_name: &str, powershell_script: &str) -> Result<String, String> {
-    let encoded = encode_powershell_script(powershell_script);
-
-    let xml = format!(r#"<?xml version="1.0" encoding="UTF-16"?>
-<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
-  <RegistrationInfo>
-    <Author>VPN Manager App</Author>
-    <Description>Temporary task to execute system elevation</Description>
-  </RegistrationInfo>
-  <Triggers />
-  <Principals>
-    <Principal id="Author">
-      <UserId>S-1-5-18</UserId> <!-- SYSTEM SID -->
-      <RunLevel>HighestAvailable</RunLevel>
-    </Principal>
-  </Principals>
-  <Settings>
-    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>
-    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>
-    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>
-    <AllowHardTerminate>true</AllowHardTerminate>
-    <StartWhenAvailable>false</StartWhenAvailable>
-    <RunOnlyIfNetworkAvailable>false</RunOnlyIfNetworkAvailable>
-    <IdleSettings>
-      <StopOnIdleEnd>true</StopOnIdleEnd>
-      <RestartOnIdle>false</RestartOnIdle>
-    </IdleSettings>
-    <AllowStartOnDemand>true</AllowStartOnDemand>
-    <Enabled>true</Enabled>
-    <Hidden>false</Hidden>
-    <RunOnlyIfIdle>false</RunOnlyIfIdle>
-    <WakeToRun>false</WakeToRun>
-    <ExecutionTimeLimit>PT1H</ExecutionTimeLimit>
-    <Priority>4</Priority>
-  </Settings>
-  <Actions Context="Author">
-    <Exec>
-      <Command>powershell.exe</Command>
-      <Arguments>-NoProfile -NonInteractive -ExecutionPolicy Bypass -EncodedCommand {}</Arguments>
-    </Exec>
-  </Actions>
-</Task>"#, encoded);
-
-    // 1. Create temporary XML file
-    let temp_dir = std::env::temp_dir();
-    let xml_path = temp_dir.join(format!("{}.xml", task_name));
-    std::fs::write(&xml_path, xml).map_err(|e| format!("Failed to write task XML: {}", e))?;
-
-    // 2. Register task
-    let register_out = Command::new("schtasks")
-        .args(&["/create", "/tn", task_name, "/xml", xml_path.to_str().unwrap(), "/f"])
-        .creation_flags(0x08000000)
-        .output()
-        .map_err(|e| format!("Failed to run schtasks /create: {}", e))?;
+/// Registers and starts `task` as a one-shot SYSTEM task running `powershell_script`.
+fn start_system_task(task: &TempTask, powershell_script: &str) -> Result<(), String> {
+    let arguments = format!(
+        "-NoProfile -NonInteractive -ExecutionPolicy Bypass -EncodedCommand {}",
+        encode_powershell_script(powershell_script)
+    );
 
-    if !register_out.status.success() {
-        let _ = std::fs::remove_file(&xml_path);
-        return Err(format!("Task create failed: {}", String::from_utf8_lossy(&register_out.stderr)));
-    }
+    // 1. Register task
+    jobs::check_cancelled()?;
+    audit::track_script(powershell_script);
+    jobs::progress(&format!("Starting SYSTEM task {}", task.name));
+    system_task("Temporary task to execute system elevation", "powershell.exe", &arguments, false)
+        .register(&task.name, &task.dir.file("task.xml"))?;
 
-    // 3. Run task
+    // 2. Run task
     let run_out = Command::new("schtasks")
-        .args(&["/run", "/tn", task_name])
+        .args(["/run", "/tn", &task.name])
         .creation_flags(0x08000000)
         .output()
         .map_err(|e| format!("Failed to run task: {}", e))?;
-        
+
     if !run_out.status.success() {
-        // Try cleanup anyway
-        let _ = Command::new("schtasks").args(&["/delete", "/tn", task_name, "/f"]).output();
-        let _ = std::fs::remove_file(&xml_path);
         return Err(format!("Task run failed: {}", String::from_utf8_lossy(&run_out.stderr)));
     }
+    Ok(())
+}
 
-    // Since we can't easily capture the output of a SYSTEM task dynamically in real time without IPC or writing to file,
-    // we assume success if triggered. Better approach: WMI from Powershell writes to a log file.
-    // For simplicity, we just wait a bit and delete the task.
-
-    std::thread::sleep(std::time::Duration::from_secs(5));
+/// How long to wait for a SYSTEM task to report its result. WMI calls on a slow or busy machine
+/// can take well over the few seconds they usually need.
+const SYSTEM_TASK_TIMEOUT_SECS: u64 = 120;
+
+/// Runs `powershell_script` as SYSTEM and waits until it has finished, so callers (and the
+/// verification after a deploy) see its effect. Fails if the script throws.
+///
+/// `task_name` is only a prefix: every call gets its own task and private temp directory, both
+/// removed when the guard goes out of scope.
+pub(crate) fn run_as_system_task(task_name: &str, powershell_script: &str) -> Result<String, String> {
+    let output = run_as_system_task_with_output(task_name, powershell_script)?;
+    Ok(if output.is_empty() { "SYSTEM task completed.".to_string() } else { output })
+}
 
-    // 4. Cleanup
-    let _ = Command::new("schtasks")
-        .args(&["/delete", "/tn", task_name, "/f"])
-        .creation_flags(0x08000000)
-        .output();
-        
-    let _ = std::fs::remove_file(&xml_path);
+/// Like `run_as_system_task`, but returns what the script wrote to the pipeline. The SYSTEM
+/// task writes its output to a temporary file in the task's private directory and then renames
+/// it, so the polled `output.txt` only ever appears complete.
+pub(crate) fn run_as_system_task_with_output(task_name: &str, powershell_script: &str) -> Result<String, String> {
+    let task = TempTask::new(task_name)?;
+    let tmp_path = task.dir.file("output.tmp");
+    let out_path = task.dir.file("output.txt");
 
-    Ok("WMI Injection Task successfully triggered and completed.".to_string())
+    let script = format!(r#"
+try {{
+    $taskOutput = & {{
+{powershell_script}
+    }} *>&1 | Out-String
+}} catch {{
+    $taskOutput = "ERROR: " + $_.Exception.Message
+}}
+[System.IO.File]::WriteAllText('{tmp_path}', $taskOutput, (New-Object System.Text.UTF8Encoding $false))
+Move-Item -LiteralPath '{tmp_path}' -Destination '{out_path}' -Force
+"#,
+        tmp_path = tmp_path.display().to_string().replace('\'', "''"),
+        out_path = out_path.display().to_string().replace('\'', "''"));
+
+    start_system_task(&task, &script)?;
+
+    // Once started the script runs to completion regardless, so a cancelled job still waits for
+    // it instead of reporting a state the machine is not in.
+    jobs::uncancellable(|| {
+        for _ in 0..SYSTEM_TASK_TIMEOUT_SECS * 2 {
+            if let Ok(output) = std::fs::read_to_string(&out_path) {
+                return match output.trim().strip_prefix("ERROR: ") {
+                    Some(error) => Err(error.to_string()),
+                    None => Ok(output.trim().to_string()),
+                };
+            }
+            jobs::sleep(std::time::Duration::from_millis(500))?;
+        }
+        Err(format!("Task '{}' did not report a result within {} seconds.", task_name, SYSTEM_TASK_TIMEOUT_SECS))
+    })
 }
 
 fn run_cmd_as_system(task_name: &str, command: &str, arguments: &str) -> Result<String, String> {
-    let escaped_args = arguments.replace("&", "&amp;").replace("<", "&lt;").replace(">", "&gt;").replace("\"", "&quot;");
-    let xml = format!(r#"<?xml version="1.0" encoding="UTF-16"?>
-<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
-  <RegistrationInfo><Author>VPN Manager App</Author></RegistrationInfo>
-  <Triggers />
-  <Principals>
-    <Principal id="Author">
-      <UserId>S-1-5-18</UserId>
-      <RunLevel>HighestAvailable</RunLevel>
-    </Principal>
-  </Principals>
-  <Settings>
-    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>
-    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>
-    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>
-    <AllowHardTerminate>true</AllowHardTerminate>
-    <StartWhenAvailable>false</StartWhenAvailable>
-    <RunOnlyIfNetworkAvailable>false</RunOnlyIfNetworkAvailable>
-    <AllowStartOnDemand>true</AllowStartOnDemand>
-    <Enabled>true</Enabled>
-    <Hidden>true</Hidden>
-    <RunOnlyIfIdle>false</RunOnlyIfIdle>
-    <WakeToRun>false</WakeToRun>
-    <ExecutionTimeLimit>PT1H</ExecutionTimeLimit>
-    <Priority>4</Priority>
-  </Settings>
-  <Actions Context="Author">
-    <Exec>
-      <Command>{}</Command>
-      <Arguments>{}</Arguments>
-    </Exec>
-  </Actions>
-</Task>"#, command, escaped_args);
-
-    let temp_dir = std::env::temp_dir();
-    let xml_path = temp_dir.join(format!("{}.xml", task_name));
-    std::fs::write(&xml_path, &xml).map_err(|e| format!("Failed to write task XML: {}", e))?;
-
-    let register_out = Command::new("schtasks")
-        .args(&["/create", "/tn", task_name, "/xml", xml_path.to_str().unwrap(), "/f"])
-        .creation_flags(0x08000000)
-        .output()
-        .map_err(|e| format!("Failed to run schtasks /create: {}", e))?;
-
-    if !register_out.status.success() {
-        let _ = std::fs::remove_file(&xml_path);
-        return Err(format!("Task create failed: {}", String::from_utf8_lossy(&register_out.stderr)));
-    }
+    let task = TempTask::new(task_name)?;
+    jobs::progress(&format!("Running {} as SYSTEM", command));
+    audit::track_script(&format!("{} {}", command, arguments));
+    system_task("Temporary task to run a command as SYSTEM", command, arguments, true)
+        .register(&task.name, &task.dir.file("task.xml"))?;
 
     let run_out = Command::new("schtasks")
-        .args(&["/run", "/tn", task_name])
+        .args(["/run", "/tn", &task.name])
         .creation_flags(0x08000000)
         .output()
         .map_err(|e| format!("Failed to run task: {}", e))?;
-        
-    // wait a brief moment for it to execute
-    std::thread::sleep(std::time::Duration::from_millis(1500));
-
-    let _ = Command::new("schtasks").args(&["/delete", "/tn", task_name, "/f"]).creation_flags(0x08000000).output();
-    let _ = std::fs::remove_file(&xml_path);
 
     if !run_out.status.success() {
         return Err(format!("Task run failed: {}", String::from_utf8_lossy(&run_out.stderr)));
     }
 
+    // wait a brief moment for it to execute
+    jobs::uncancellable(|| jobs::sleep(std::time::Duration::from_millis(1500)))?;
+
     Ok("Command successfully executed natively as SYSTEM.".to_string())
 }
 
 #[command]
-pub async fn disconnect_device_tunnel(config: VpnConfig) -> Result<String, String> {
-    let tunnel_name = format!("{} Device Tunnel", config.company_prefix);
+pub async fn disconnect_device_tunnel(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let config = resolve_config(&app, config, profile_id)?;
+    let tunnel_name = config.tunnel_names().device;
     let args = format!("\"{}\" /disconnect", tunnel_name);
     run_cmd_as_system("TempDisconnectDeviceTunnel", "rasdial.exe", &args)
 }
 
 #[command]
-pub async fn connect_device_tunnel(config: VpnConfig) -> Result<String, String> {
-    let tunnel_name = format!("{} Device Tunnel", config.company_prefix);
+pub async fn connect_device_tunnel(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let config = resolve_config(&app, config, profile_id)?;
+    dial_as_system(&config.tunnel_names().device)
+}
+
+pub(crate) fn dial_as_system(tunnel_name: &str) -> Result<String, String> {
     let args = format!("\"{}\"", tunnel_name);
     run_cmd_as_system("TempConnectDeviceTunnel", "rasdial.exe", &args)
 }
 
 #[command]
-pub async fn disconnect_user_tunnel(config: VpnConfig) -> Result<String, String> {
-    let tunnel_name = format!("{} User Tunnel", config.company_prefix);
+pub async fn disconnect_user_tunnel(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let config = resolve_config(&app, config, profile_id)?;
+    let tunnel_name = config.tunnel_names().user;
     let output = Command::new("rasdial")
         .args(&[&tunnel_name, "/disconnect"])
         .creation_flags(0x08000000)
@@ -278,8 +308,13 @@ pub async fn disconnect_user_tunnel(config: VpnConfig) -> Result<String, String>
 }
 
 #[command]
-pub async fn connect_user_tunnel(config: VpnConfig) -> Result<String, String> {
-    let tunnel_name = format!("{} User Tunnel", config.company_prefix);
+pub async fn connect_user_tunnel(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let config = resolve_config(&app, config, profile_id)?;
+    let tunnel_name = config.tunnel_names().user;
     let output = Command::new("rasdial")
         .args(&[&tunnel_name])
         .creation_flags(0x08000000)
@@ -290,9 +325,31 @@ pub async fn connect_user_tunnel(config: VpnConfig) -> Result<String, String> {
 }
 
 #[command]
-pub async fn remove_device_tunnel(config: VpnConfig) -> Result<String, String> {
-    let profile_name = format!("{} Device Tunnel", config.company_prefix);
+pub async fn remove_device_tunnel(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+    remove_task: Option<bool>,
+) -> Result<String, String> {
+    let config = resolve_config_for_change(&app, config, profile_id)?;
+    let names = config.tunnel_names();
+    let profile_name = names.device;
+    audited(&app, "remove_device_tunnel", Some(&config), || {
+        let backup = backup_before(&app, "remove_device_tunnel", &[(TunnelKind::Device, profile_name.clone())])?;
+        let output = delete_device_profile(&profile_name)?;
+        let mut output = format!("{}\n{}", backup_note(&backup), with_orphan_warnings(output, &profile_name));
+        // The auto-connect task would otherwise keep dialing a tunnel that no longer exists.
+        if remove_task.unwrap_or(false) {
+            match crate::auto_connect_task::remove_task(&names.auto_connect_task) {
+                Ok(message) => output.push_str(&format!("\n{}", message)),
+                Err(e) => output.push_str(&format!("\nWARNING: Failed to remove the auto-connect task: {}", e)),
+            }
+        }
+        Ok(output)
+    })
+}
 
+pub(crate) fn delete_device_profile(profile_name: &str) -> Result<String, String> {
     // Single atomic SYSTEM script: disconnect first, wait briefly, then remove WMI + phonebook.
     // Running as one task prevents AlwaysOn from reconnecting between steps.
     let script = format!(r#"
@@ -326,8 +383,21 @@ Write-Host "Device Tunnel removed."
 }
 
 #[command]
-pub async fn remove_user_tunnel(config: VpnConfig) -> Result<String, String> {
-    let profile_name = format!("{} User Tunnel", config.company_prefix);
+pub async fn remove_user_tunnel(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let config = resolve_config_for_change(&app, config, profile_id)?;
+    let profile_name = config.tunnel_names().user;
+    audited(&app, "remove_user_tunnel", Some(&config), || {
+        let backup = backup_before(&app, "remove_user_tunnel", &[(TunnelKind::User, profile_name.clone())])?;
+        let output = delete_user_profile(&profile_name)?;
+        Ok(format!("{}\n{}", backup_note(&backup), with_orphan_warnings(output, &profile_name)))
+    })
+}
+
+pub(crate) fn delete_user_profile(profile_name: &str) -> Result<String, String> {
     let script = format!(r#"
 $profileNameEscaped = "{profile_name}".Replace(' ', '%20')
 $namespaceName = "root\cimv2\mdm\dmmap"
@@ -367,6 +437,7 @@ Remove-VpnConnection -Name "{profile_name}" -Force -ErrorAction SilentlyContinue
 Write-Host "Successfully cleaned up User Tunnel."
 "#, profile_name=profile_name);
 
+    audit::track_script(&script);
     let output = Command::new("powershell")
         .args(&["-Command", &script])
         .creation_flags(0x08000000)
@@ -404,24 +475,27 @@ pub async fn get_sstp_revocation_status() -> Result<bool, String> {
 }
 
 #[command]
-pub async fn set_sstp_revocation(disable: bool) -> Result<String, String> {
+pub async fn set_sstp_revocation(app: AppHandle, disable: bool) -> Result<String, String> {
     let val = if disable { "1" } else { "0" };
     // Setting both keys to be safe, as Microsoft documentation sometimes references SstpSvc or RemoteAccess
     let script = format!(
         "New-ItemProperty -Path 'HKLM:\\SYSTEM\\CurrentControlSet\\Services\\SstpSvc\\Parameters' -Name 'NoCertRevocationCheck' -PropertyType DWord -Value {val} -Force; New-ItemProperty -Path 'HKLM:\\System\\CurrentControlSet\\Services\\RemoteAccess\\Parameters' -Name 'IgnoreRevocationOffline' -PropertyType DWord -Value {val} -Force",
         val=val
     );
-    let output = Command::new("powershell")
-        .args(&["-Command", &script])
-        .creation_flags(0x08000000)
-        .output()
-        .map_err(|e| format!("PowerShell error: {}", e))?;
-
-    if output.status.success() {
-        Ok(format!("SSTP Revocation Check successfully set to disable = {}", disable))
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
-    }
+    audited(&app, "set_sstp_revocation", None, || {
+        audit::track_script(&script);
+        let output = Command::new("powershell")
+            .args(&["-Command", &script])
+            .creation_flags(0x08000000)
+            .output()
+            .map_err(|e| format!("PowerShell error: {}", e))?;
+
+        if output.status.success() {
+            Ok(format!("SSTP Revocation Check successfully set to disable = {}", disable))
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).to_string())
+        }
+    })
 }
 
 #[command]
@@ -504,7 +578,7 @@ pub fn generate_user_profile_xml(config: &VpnConfig) -> String {
         <Configuration>{eap_settings}</Configuration>
       </Eap>
     </Authentication>
-{disable_route_xml}  </NativeProfile>
+{disable_route_xml}{fragmentation_xml}  </NativeProfile>
 {routes_xml}  <DomainNameInformation>
     <DomainName>.{dns_suffix}</DomainName>
     <DnsServers>{dns_servers}</DnsServers>
@@ -519,16 +593,33 @@ pub fn generate_user_profile_xml(config: &VpnConfig) -> String {
         vpn_server_address = config.vpn_server_address,
         trusted_network = config.trusted_network,
         dns_servers = config.dns_servers,
+        fragmentation_xml = fragmentation_xml(config),
         eap_settings = eap_settings.replace("<", "&lt;").replace(">", "&gt;").replace("\"", "&quot;")
     )
 }
 
 #[command]
-pub async fn deploy_user_tunnel(config: VpnConfig) -> Result<String, String> {
+pub async fn deploy_user_tunnel(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+    mode: Option<DeployMode>,
+) -> Result<String, String> {
+    let config = resolve_config_for_change(&app, config, profile_id)?;
     let profile_xml = generate_user_profile_xml(&config);
+    let profile_name = config.tunnel_names().user;
+    let mode = mode.unwrap_or_default();
+    audited(&app, "deploy_user_tunnel", Some(&config), || {
+        deploy_with_backup(&app, "deploy_user_tunnel", TunnelKind::User, &profile_name, Some(&profile_xml), mode, || {
+            apply_user_profile(&profile_name, &profile_xml, mode)
+                .and_then(|output| verify_after_deploy(&config, TunnelKind::User, output))
+        })
+    })
+}
 
-    let profile_name = format!("{} User Tunnel", config.company_prefix);
-
+/// Creates the User Tunnel `MDM_VPNv2_01` instance for the logged-on user, or updates it
+/// according to `mode`.
+pub(crate) fn apply_user_profile(profile_name: &str, profile_xml: &str, mode: DeployMode) -> Result<String, String> {
     let script = format!(r#"
 $ErrorActionPreference = "Stop"
 $ProfileXML = @"
@@ -558,26 +649,41 @@ $session = New-CimSession
 if ($options) {{ $existing = $session.EnumerateInstances($namespaceName, $className, $options) }}
 else {{ $existing = $session.EnumerateInstances($namespaceName, $className) }}
 
-if ($existing) {{
-    foreach ($instance in $existing) {{
-        if ($instance.InstanceID -eq $profileNameEscaped) {{
-            if ($options) {{ $session.DeleteInstance($namespaceName, $instance, $options) }}
-            else {{ $session.DeleteInstance($namespaceName, $instance) }}
-        }}
+$instance = $existing | Where-Object {{ $_.InstanceID -eq $profileNameEscaped }} | Select-Object -First 1
+$updated = $false
+
+if ($instance -and "{mode}" -eq "Update") {{
+    try {{
+        $instance.CimInstanceProperties["ProfileXML"].Value = $escapedXML
+        if ($options) {{ $session.ModifyInstance($namespaceName, $instance, $options) | Out-Null }}
+        else {{ $session.ModifyInstance($namespaceName, $instance) | Out-Null }}
+        $updated = $true
+    }} catch {{
+        Write-Host "In-place update failed ($($_.Exception.Message)), recreating the profile..."
     }}
 }}
 
-$newInstance = New-Object Microsoft.Management.Infrastructure.CimInstance $className, $namespaceName
-$newInstance.CimInstanceProperties.Add([Microsoft.Management.Infrastructure.CimProperty]::Create("ParentID", "./Vendor/MSFT/VPNv2", "String", "Key"))
-$newInstance.CimInstanceProperties.Add([Microsoft.Management.Infrastructure.CimProperty]::Create("InstanceID", "$profileNameEscaped", "String", "Key"))
-$newInstance.CimInstanceProperties.Add([Microsoft.Management.Infrastructure.CimProperty]::Create("ProfileXML", "$escapedXML", "String", "Property"))
+if ($updated) {{
+    Write-Host "Success! Profile was updated in place."
+}} else {{
+    if ($instance) {{
+        if ($options) {{ $session.DeleteInstance($namespaceName, $instance, $options) }}
+        else {{ $session.DeleteInstance($namespaceName, $instance) }}
+    }}
 
-if ($options) {{ $session.CreateInstance($namespaceName, $newInstance, $options) }}
-else {{ $session.CreateInstance($namespaceName, $newInstance) }}
+    $newInstance = New-Object Microsoft.Management.Infrastructure.CimInstance $className, $namespaceName
+    $newInstance.CimInstanceProperties.Add([Microsoft.Management.Infrastructure.CimProperty]::Create("ParentID", "./Vendor/MSFT/VPNv2", "String", "Key"))
+    $newInstance.CimInstanceProperties.Add([Microsoft.Management.Infrastructure.CimProperty]::Create("InstanceID", "$profileNameEscaped", "String", "Key"))
+    $newInstance.CimInstanceProperties.Add([Microsoft.Management.Infrastructure.CimProperty]::Create("ProfileXML", "$escapedXML", "String", "Property"))
 
-Write-Host "Success! Profile was created."
+    if ($options) {{ $session.CreateInstance($namespaceName, $newInstance, $options) }}
+    else {{ $session.CreateInstance($namespaceName, $newInstance) }}
+
+    Write-Host "Success! Profile was created."
+}}
 "#);
 
+    audit::track_script(&script);
     let output = Command::new("powershell")
         .args(&["-Command", &script])
         .creation_flags(0x08000000)
@@ -592,7 +698,12 @@ Write-Host "Success! Profile was created."
 }
 
 #[command]
-pub async fn deploy_user_tunnel_all(config: VpnConfig) -> Result<String, String> {
+pub async fn deploy_user_tunnel_all(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let config = resolve_config_for_change(&app, config, profile_id)?;
     let mut eku_xml = String::new();
     if let (Some(eku_name), Some(eku_oid)) = (&config.eku_name, &config.eku_oid) {
         if !eku_name.trim().is_empty() && !eku_oid.trim().is_empty() {
@@ -644,7 +755,7 @@ pub async fn deploy_user_tunnel_all(config: VpnConfig) -> Result<String, String>
   </Config>
 </EapHostConfig>"#, eap_server_names = config.eap_server_names, root_ca_hash = config.root_ca_hash, eku_xml = eku_xml);
 
-    let profile_name = format!("{} User Tunnel All", config.company_prefix);
+    let profile_name = config.tunnel_names().user_all;
     
     let mut routes_script = String::new();
     for route in &config.user_routes {
@@ -695,138 +806,108 @@ Write-Host "Success! User Tunnel (All Users) was created."
     );
 
     // Creates the connection system-wide via an elevated system task
-    run_as_system_task("TempDeployUserTunnelAll", &script)
+    audited(&app, "deploy_user_tunnel_all", Some(&config), || {
+        deploy_with_backup(&app, "deploy_user_tunnel_all", TunnelKind::UserAll, &profile_name, None, DeployMode::Recreate, || {
+            run_as_system_task("TempDeployUserTunnelAll", &script)
+                .and_then(|output| verify_after_deploy(&config, TunnelKind::UserAll, output))
+        })
+    })
 }
 
 #[command]
-pub async fn enable_task_scheduler_trigger(config: VpnConfig) -> Result<String, String> {
-    let vpn_name = format!("{} Device Tunnel", config.company_prefix);
-    let task_name = format!("Start {} Device Tunnel", config.company_prefix);
-    let ping_target = if config.internal_ping_target.trim().is_empty() {
-        &config.dns_suffix
-    } else {
-        &config.internal_ping_target
-    };
-
-    let script = format!(r#"
-$vpnName = "{vpn_name}"
-$internalDomain = "{ping_target}"
-$maxRetries = 5
-$retryCount = 0
-
-Start-Sleep -Seconds 20
-
-if (Test-Connection -ComputerName $internalDomain -Count 2 -Quiet) {{ exit 0 }}
-
-do {{
-    $retryCount++
-    rasdial "$vpnName"
-    $status = Get-VpnConnection -Name "$vpnName" -ErrorAction SilentlyContinue
-    if ($status.ConnectionStatus -eq 'Connected') {{ exit 0 }}
-    Start-Sleep -Seconds 10
-}} while ($retryCount -lt $maxRetries)
-exit 1
-"#, vpn_name=vpn_name, ping_target=ping_target);
-    
-    // Convert to a system Scheduled Task at Startup using the same method, but trigger is AtStartup
-    // and we don't delete it immediately.
-    let encoded = encode_powershell_script(&script);
-    let xml = format!(r#"<?xml version="1.0" encoding="UTF-16"?>
-<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
-  <Triggers>
-    <BootTrigger>
-      <Enabled>true</Enabled>
-    </BootTrigger>
-  </Triggers>
-  <Principals>
-    <Principal id="Author">
-      <UserId>S-1-5-18</UserId>
-      <RunLevel>HighestAvailable</RunLevel>
-    </Principal>
-  </Principals>
-  <Settings>
-    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>
-    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>
-    <ExecutionTimeLimit>PT2H</ExecutionTimeLimit>
-  </Settings>
-  <Actions Context="Author">
-    <Exec>
-      <Command>powershell.exe</Command>
-      <Arguments>-NoProfile -NonInteractive -ExecutionPolicy Bypass -EncodedCommand {}</Arguments>
-    </Exec>
-  </Actions>
-</Task>"#, encoded);
-
-    let temp_dir = std::env::temp_dir();
-    let xml_path = temp_dir.join(format!("{}.xml", "VpnWorkaround"));
-    std::fs::write(&xml_path, xml).map_err(|e| format!("Failed to write: {}", e))?;
-
-    let register_out = Command::new("schtasks")
-        .args(&["/create", "/tn", &format!("\\{}\\{}", config.company_prefix, task_name), "/xml", xml_path.to_str().unwrap(), "/f"])
-        .creation_flags(0x08000000)
-        .output()
-        .map_err(|e| format!("Failed to create task: {}", e))?;
-        
-    let _ = std::fs::remove_file(&xml_path);
-    
-    if register_out.status.success() {
+pub async fn enable_task_scheduler_trigger(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let config = resolve_config_for_change(&app, config, profile_id)?;
+    let task_name = config.tunnel_names().auto_connect_task;
+    let task = crate::auto_connect_task::auto_connect_task(&config);
+    audited(&app, "enable_task_scheduler_trigger", Some(&config), || {
+        audit::track_script(&task.to_xml());
+        let dir = PrivateDir::new(&unique_name("AutoConnectTask"))?;
+        task.register(&task_name, &dir.file("task.xml"))?;
         Ok("Workaround Task Registered Successfully".to_string())
-    } else {
-        Err(String::from_utf8_lossy(&register_out.stderr).to_string())
+    })
+}
+
+/// Every command that writes a file chosen in the UI checks its extension first, so a
+/// compromised frontend cannot overwrite scripts or executables.
+pub(crate) fn check_export_path(path: &str, allowed: &[&str]) -> Result<(), String> {
+    let lower = path.trim().to_lowercase();
+    if allowed.iter().any(|ext| lower.ends_with(ext)) {
+        return Ok(());
     }
+    let list = match allowed.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        _ => allowed.join(""),
+    };
+    Err(format!("Only {} files are allowed for export.", list))
 }
 
 #[command]
 pub async fn write_file_to_path(path: String, content: String) -> Result<String, String> {
-    let lower = path.to_lowercase();
-    if !lower.ends_with(".json") && !lower.ends_with(".xml") {
-        return Err("Only .json and .xml files are allowed for export.".to_string());
-    }
+    check_export_path(&path, &[".json", ".jsonl", ".xml", ".conf", ".mobileconfig", ".nmconnection", ".pbk"])?;
     std::fs::write(&path, content).map_err(|e| format!("Failed to write file: {}", e))?;
     Ok(format!("File successfully saved to: {}", path))
 }
 
 #[command]
-pub async fn configure_device_ipsec(config: VpnConfig) -> Result<String, String> {
-    let dev_tunnel = format!("{} Device Tunnel", config.company_prefix);
+pub async fn configure_device_ipsec(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let config = resolve_config_for_change(&app, config, profile_id)?;
+    let dev_tunnel = config.tunnel_names().device;
 
     let sys_script = format!(r#"
 $devTunnel = "{dev_tunnel}"
-Set-VpnConnectionIPsecConfiguration -ConnectionName $devTunnel -AuthenticationTransformConstants SHA256128 -CipherTransformConstants AES256 -DHGroup Group14 -EncryptionMethod AES256 -IntegrityCheckMethod SHA256 -PFSgroup PFS2048 -Force -ErrorAction SilentlyContinue
+Set-VpnConnectionIPsecConfiguration -ConnectionName $devTunnel {ipsec_args} -Force -ErrorAction SilentlyContinue
 Write-Host "Device IPsec configuration applied."
-"#, dev_tunnel = dev_tunnel);
+"#, dev_tunnel = dev_tunnel, ipsec_args = IOS_MAC_PRESET.powershell_args());
 
-    match run_as_system_task("TempConfigureDevIpsec", &sys_script) {
-        Ok(_) => Ok("Device Tunnel IPsec ciphers successfully applied.".to_string()),
-        Err(e) => Err(format!("Device Tunnel IPsec failed: {}", e)),
-    }
+    audited(&app, "configure_device_ipsec", Some(&config), || {
+        match run_as_system_task("TempConfigureDevIpsec", &sys_script) {
+            Ok(_) => Ok("Device Tunnel IPsec ciphers successfully applied.".to_string()),
+            Err(e) => Err(format!("Device Tunnel IPsec failed: {}", e)),
+        }
+    })
 }
 
 #[command]
-pub async fn configure_user_ipsec(config: VpnConfig) -> Result<String, String> {
-    let user_tunnel = format!("{} User Tunnel", config.company_prefix);
+pub async fn configure_user_ipsec(
+    app: AppHandle,
+    config: Option<VpnConfig>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let config = resolve_config_for_change(&app, config, profile_id)?;
+    let user_tunnel = config.tunnel_names().user;
 
     let user_script = format!(r#"
 $userTunnel = "{user_tunnel}"
-Set-VpnConnectionIPsecConfiguration -ConnectionName $userTunnel -AuthenticationTransformConstants SHA256128 -CipherTransformConstants AES256 -DHGroup Group14 -EncryptionMethod AES256 -IntegrityCheckMethod SHA256 -PFSgroup PFS2048 -Force -ErrorAction SilentlyContinue
+Set-VpnConnectionIPsecConfiguration -ConnectionName $userTunnel {ipsec_args} -Force -ErrorAction SilentlyContinue
 Write-Host "User IPsec configuration applied."
-"#, user_tunnel = user_tunnel);
-
-    let user_output = Command::new("powershell")
-        .args(&["-Command", &user_script])
-        .creation_flags(0x08000000)
-        .output();
-
-    match user_output {
-        Ok(out) => {
-            if out.status.success() {
-                Ok("User Tunnel IPsec ciphers successfully applied.".to_string())
-            } else {
-                Err(format!("User Tunnel IPsec failed: {}", String::from_utf8_lossy(&out.stderr)))
-            }
-        },
-        Err(e) => Err(format!("Failed to execute User IPsec command: {}", e)),
-    }
+"#, user_tunnel = user_tunnel, ipsec_args = IOS_MAC_PRESET.powershell_args());
+
+    audited(&app, "configure_user_ipsec", Some(&config), || {
+        audit::track_script(&user_script);
+        let user_output = Command::new("powershell")
+            .args(&["-Command", &user_script])
+            .creation_flags(0x08000000)
+            .output();
+
+        match user_output {
+            Ok(out) => {
+                if out.status.success() {
+                    Ok("User Tunnel IPsec ciphers successfully applied.".to_string())
+                } else {
+                    Err(format!("User Tunnel IPsec failed: {}", String::from_utf8_lossy(&out.stderr)))
+                }
+            },
+            Err(e) => Err(format!("Failed to execute User IPsec command: {}", e)),
+        }
+    })
 }
 
 
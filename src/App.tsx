@@ -1,5 +1,6 @@
 import { useState, useRef, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import './App.css';
 
 interface VpnConfig {
@@ -17,14 +18,45 @@ interface VpnConfig {
   userTunnelAlwaysOn: boolean;
   forceTunneling: boolean;
   disableClassBasedRoute: boolean;
+  disableIkev2Fragmentation: boolean;
+  mtuReferenceHost: string;
   disableDisconnectButton: boolean;
   sstpDisableRevocation: boolean;
   internalPingTarget: string;
   deviceTunnelAlwaysOn: boolean;
   ekuName: string;
   ekuOid: string;
+  deviceTunnelName: string;
+  userTunnelName: string;
+  userTunnelAllName: string;
+  autoConnectTaskName: string;
+  autoConnectTriggers: string[];
+  autoConnectInitialDelaySecs: number;
+  autoConnectMaxRetries: number;
+  autoConnectRetryIntervalSecs: number;
+  autoConnectExponentialBackoff: boolean;
+  autoConnectMaxRetryIntervalSecs: number;
 }
 
+interface JobProgress {
+  jobId: string;
+  operation: string;
+  state: 'running' | 'succeeded' | 'failed' | 'cancelled' | 'timedOut';
+  cancelRequested: boolean;
+  message: string | null;
+  result: string | null;
+  error: string | null;
+}
+
+// Long-running commands run as backend jobs (start_job) and stream progress via job://progress.
+const JOB_COMMANDS = [
+  'deploy_device_tunnel', 'deploy_user_tunnel', 'deploy_user_tunnel_all',
+  'remove_device_tunnel', 'remove_user_tunnel',
+  'configure_device_ipsec', 'configure_user_ipsec', 'enable_task_scheduler_trigger',
+  'restore_backup', 'verify_tunnels', 'check_profile_drift', 'diagnose_mtu', 'analyze_vpn_events', 'create_support_bundle',
+  'generate_diagnostics_report'
+];
+
 interface LogEntry {
   time: string;
   cmd: string;
@@ -40,13 +72,18 @@ const en = {
   btnDeployDevice: "Device Only",
   btnDeploying: "Deploying...",
   clear: "Clear Log",
+  cancelJob: "Cancel",
   coreParams: "1. Core & Network",
   prefixLabel: "Profile Prefix",
   serverLabel: "VPN Server FQDN",
   dnsSuffixLabel: "Internal DNS Suffix",
   trustedLabel: "Trusted Network",
   pingTargetLabel: "Internal Ping Target",
+  namingLabel: "Naming (%PREFIX%, %DEVICE%)",
+  tt_naming: "Templates for the connection and task names. %PREFIX% is replaced by the company prefix, %DEVICE% (task only) by the Device Tunnel name. Leave empty for the default.",
   tt_ping: "Optional: IP or internal DNS to ping to verify if on corporate network. Fallbacks to DNS Suffix if empty.",
+  mtuReferenceHostLabel: "MTU Reference Host",
+  tt_mtuReference: "Optional: host measured by the MTU diagnosis when the VPN server drops ICMP. Skipped if empty.",
   dnsServersLabel: "Internal DNS Servers",
   tunnelConstraints: "2. Routing & Rules",
   devRoutesLabel: "Device Routes",
@@ -60,6 +97,7 @@ const en = {
   applyIosMacUser: "iOS/Mac User",
   forceTunnel: "Force Tunneling",
   disableClass: "Hide Default Routes",
+  disableIkeFrag: "Disable IKEv2 Fragmentation",
   hideDisconnect: "Hide Disconnect UI",
   secPKI: "3. Certificates",
   rootCALabel: "Root CA Hash",
@@ -78,18 +116,46 @@ const en = {
   up: "Up",
   down: "Down",
   remove: "Del",
+  autoConnectLabel: "Auto-Connect triggers / retries",
+  tt_auto_connect: "When the auto-connect task runs and how it retries: initial delay, attempts and interval in seconds. Backoff doubles the interval after every failed attempt up to the maximum.",
+  trigBoot: "Boot",
+  trigNetwork: "Network change",
+  trigLogon: "Logon",
+  trigResume: "Resume",
+  backoffLabel: "Exponential backoff",
+  taskCtrl: "Auto-Connect Task",
+  taskStatus: "Status",
+  taskEnable: "On",
+  taskDisable: "Off",
   inspection: "System Checks",
   getState: "Connections",
   verifyRoutes: "Routes",
+  checkDrift: "Drift Check",
+  verifyTunnels: "Verify Deployment",
+  verifyTunnelsConnect: "Verify + Test Connection",
   getXml: "EAP XML",
   checkDns: "DNS Check",
   checkPorts: "Port Check",
+  probeIke: "IKEv2 Probe (UDP 500/4500)",
+  probeSstp: "SSTP Probe (TLS 443)",
+  diagnoseMtu: "Path MTU / IKE Fragmentation",
+  analyzeEvents: "Event Log (24h)",
+  analyzeEventFile: "Analyze Export",
+  explainCode: "Explain Code",
+  supportBundle: "Support Bundle",
+  openBundle: "Open Bundle",
+  htmlReport: "HTML Report",
   restartService: "Restart RasMan",
   terminal: "Terminal Output",
   streamReady: "Ready...",
   exportConfig: "Export",
+  exportAudit: "Audit Log",
   importConfig: "Import",
   promptVpnName: "Enter the name of the VPN connection to extract XML from:",
+  promptBackupId: "Enter the id of the backup to restore (see log):",
+  promptErrorCode: "Enter the error code from the event log (e.g. 809 or 0x80092013):",
+  confirmRedact: "Redact server names, IP addresses and user/machine names in the bundle?",
+  restoreBackup: "Restore Backup",
   tabClient: "Client Setup",
   tabServer: "Server Setup",
   serverAdminTitle: "Server Infrastructure",
@@ -129,13 +195,18 @@ const de = {
   btnDeployDevice: "Nur Device",
   btnDeploying: "Wird Deployt...",
   clear: "Log Leeren",
+  cancelJob: "Abbrechen",
   coreParams: "1. Kern-Netzwerk",
   prefixLabel: "Profil-Präfix",
   serverLabel: "VPN Server FQDN",
   dnsSuffixLabel: "Internes DNS Suffix",
   trustedLabel: "Trusted Net",
   pingTargetLabel: "Internes Ping Ziel",
+  namingLabel: "Benennung (%PREFIX%, %DEVICE%)",
+  tt_naming: "Vorlagen für Verbindungs- und Aufgabennamen. %PREFIX% wird durch das Firmenpräfix ersetzt, %DEVICE% (nur Aufgabe) durch den Namen des Device Tunnels. Leer lassen für den Standard.",
   tt_ping: "Optional: IP oder internes DNS zum Pingen, um Firmennetzwerk zu prüfen. Fallback auf DNS Suffix.",
+  mtuReferenceHostLabel: "MTU Referenz-Host",
+  tt_mtuReference: "Optional: Host, den die MTU-Diagnose misst, wenn der VPN Server ICMP verwirft. Entfällt wenn leer.",
   dnsServersLabel: "Interne DNS Server",
   tunnelConstraints: "2. Routing & Regeln",
   devRoutesLabel: "Device Routen",
@@ -149,6 +220,7 @@ const de = {
   applyIosMacUser: "iOS/Mac User",
   forceTunnel: "Force Tunneling",
   disableClass: "Standardrouten Aus",
+  disableIkeFrag: "IKEv2-Fragmentierung Aus",
   hideDisconnect: "Trennen-UI Verbergen",
   secPKI: "3. Zertifikate",
   rootCALabel: "Root CA Hash",
@@ -167,18 +239,46 @@ const de = {
   up: "Up",
   down: "Down",
   remove: "Del",
+  autoConnectLabel: "Auto-Connect Auslöser / Wiederholungen",
+  tt_auto_connect: "Wann die Auto-Connect Aufgabe läuft und wie sie wiederholt: Startverzögerung, Versuche und Intervall in Sekunden. Backoff verdoppelt das Intervall nach jedem Fehlversuch bis zum Maximum.",
+  trigBoot: "Systemstart",
+  trigNetwork: "Netzwerkwechsel",
+  trigLogon: "Anmeldung",
+  trigResume: "Fortsetzen",
+  backoffLabel: "Exponentielles Backoff",
+  taskCtrl: "Auto-Connect Aufgabe",
+  taskStatus: "Status",
+  taskEnable: "An",
+  taskDisable: "Aus",
   inspection: "System Checks",
   getState: "Verbindungen",
   verifyRoutes: "Routen",
+  checkDrift: "Abweichungen prüfen",
+  verifyTunnels: "Bereitstellung prüfen",
+  verifyTunnelsConnect: "Prüfen + Testverbindung",
   getXml: "EAP XML",
   checkDns: "DNS Test",
   checkPorts: "Port Test",
+  probeIke: "IKEv2 Test (UDP 500/4500)",
+  probeSstp: "SSTP Test (TLS 443)",
+  diagnoseMtu: "Pfad-MTU / IKE-Fragmentierung",
+  analyzeEvents: "Ereignisprotokoll (24h)",
+  analyzeEventFile: "Export Analysieren",
+  explainCode: "Code Erklären",
+  supportBundle: "Support-Paket",
+  openBundle: "Paket Öffnen",
+  htmlReport: "HTML-Bericht",
   restartService: "RasMan Neustart",
   terminal: "Terminal Ausgabe",
   streamReady: "Bereit...",
   exportConfig: "Export",
+  exportAudit: "Audit Log",
   importConfig: "Import",
   promptVpnName: "Geben Sie den Namen der VPN-Verbindung ein, um deren XML zu extrahieren:",
+  promptBackupId: "ID der wiederherzustellenden Sicherung eingeben (siehe Log):",
+  promptErrorCode: "Fehlercode aus dem Ereignisprotokoll eingeben (z.B. 809 oder 0x80092013):",
+  confirmRedact: "Servernamen, IP-Adressen und Benutzer-/Computernamen im Paket schwärzen?",
+  restoreBackup: "Sicherung wiederherstellen",
   tabClient: "Client Setup",
   tabServer: "Server Setup",
   serverAdminTitle: "Server Infrastruktur",
@@ -217,6 +317,7 @@ import { guides } from './guides';
 
 function App() {
   const [logs, setLogs] = useState<LogEntry[]>([]);
+  const [activeJobs, setActiveJobs] = useState<string[]>([]);
   const [isDeploying, setIsDeploying] = useState(false);
   const [lang, setLang] = useState<'en' | 'de'>('de'); // Default German
   const [activeTab, setActiveTab] = useState<'client' | 'server'>('client');
@@ -241,12 +342,24 @@ function App() {
     userTunnelAlwaysOn: true,
     forceTunneling: false,
     disableClassBasedRoute: false,
+    disableIkev2Fragmentation: false,
+    mtuReferenceHost: "",
     disableDisconnectButton: false,
     sstpDisableRevocation: false,
     internalPingTarget: "",
     deviceTunnelAlwaysOn: true,
     ekuName: "",
-    ekuOid: ""
+    ekuOid: "",
+    deviceTunnelName: "%PREFIX% Device Tunnel",
+    userTunnelName: "%PREFIX% User Tunnel",
+    userTunnelAllName: "%PREFIX% User Tunnel All",
+    autoConnectTaskName: "\\%PREFIX%\\Start %DEVICE%",
+    autoConnectTriggers: ['boot'],
+    autoConnectInitialDelaySecs: 20,
+    autoConnectMaxRetries: 5,
+    autoConnectRetryIntervalSecs: 10,
+    autoConnectExponentialBackoff: false,
+    autoConnectMaxRetryIntervalSecs: 300
   });
 
   const [showRrasGuide, setShowRrasGuide] = useState(false);
@@ -307,9 +420,40 @@ function App() {
     setConfig(prev => ({ ...prev, [field]: routesArray }));
   };
 
+  const runJob = (command: string, args: any) => new Promise<string>(async (resolve, reject) => {
+    let jobId: string | null = null;
+    const early: JobProgress[] = [];
+    const handle = (p: JobProgress) => {
+      if (p.message) addLog(command, p.message, false);
+      if (p.state === 'running') return;
+      unlisten();
+      setActiveJobs(prev => prev.filter(id => id !== p.jobId));
+      if (p.state === 'succeeded') resolve(p.result ?? '');
+      else reject(p.error ?? p.state);
+    };
+    // Subscribe before starting so no event is missed; events that arrive before the id is known are replayed.
+    const unlisten = await listen<JobProgress>('job://progress', ({ payload }) => {
+      if (jobId === null) early.push(payload);
+      else if (payload.jobId === jobId) handle(payload);
+    });
+    try {
+      jobId = await invoke<string>('start_job', { operation: command, args });
+    } catch (e) {
+      unlisten();
+      reject(e);
+      return;
+    }
+    setActiveJobs(prev => [...prev, jobId!]);
+    early.filter(p => p.jobId === jobId).forEach(handle);
+  });
+
+  const cancelJobs = () => {
+    activeJobs.forEach(id => invoke('cancel_job', { id }).catch(() => { }));
+  };
+
   const callEndpoint = async (command: string, args: any = {}) => {
     try {
-      const res: string = await invoke(command, args);
+      const res: string = JOB_COMMANDS.includes(command) ? await runJob(command, args) : await invoke(command, args);
       let formattedRes = res.trim();
       try {
         const obj = JSON.parse(res);
@@ -497,6 +641,92 @@ function App() {
     }
   };
 
+  const restoreBackup = async () => {
+    try {
+      const backups = JSON.parse(await callEndpoint('list_backups'));
+      const id = window.prompt(T.promptBackupId, backups[0]?.id ?? '');
+      if (!id) return;
+      await callEndpoint('restore_backup', { id });
+    } catch (e) {
+      // Errors are already logged by callEndpoint
+    }
+  };
+
+  const analyzeEventFile = async () => {
+    try {
+      const { open } = await import('@tauri-apps/plugin-dialog');
+      const path = await open({
+        filters: [{ name: 'Event Log', extensions: ['evtx', 'xml'] }],
+        multiple: false
+      });
+      if (path) {
+        await callEndpoint('analyze_event_log_file', { path });
+      }
+    } catch (e) {
+      // Errors are already logged by callEndpoint
+    }
+  };
+
+  const explainErrorCode = async () => {
+    const code = window.prompt(T.promptErrorCode);
+    if (!code) return;
+    try {
+      await callEndpoint('explain_error_code', { code });
+    } catch (e) {
+      // Errors are already logged by callEndpoint
+    }
+  };
+
+  const createSupportBundle = async () => {
+    try {
+      const redact = window.confirm(T.confirmRedact);
+      const { save } = await import('@tauri-apps/plugin-dialog');
+      const path = await save({
+        filters: [{ name: 'Support Bundle', extensions: ['zip'] }],
+        defaultPath: `vpn_support_${new Date().toISOString().split('T')[0]}.zip`
+      });
+      if (path) {
+        await callEndpoint('create_support_bundle', {
+          path,
+          config,
+          redaction: { hostnames: redact, addresses: redact, identities: redact }
+        });
+      }
+    } catch (e) {
+      // Errors are already logged by callEndpoint
+    }
+  };
+
+  const generateReport = async () => {
+    try {
+      const { save } = await import('@tauri-apps/plugin-dialog');
+      const path = await save({
+        filters: [{ name: 'HTML Report', extensions: ['html'] }],
+        defaultPath: `vpn_report_${new Date().toISOString().split('T')[0]}.html`
+      });
+      if (path) {
+        await callEndpoint('generate_diagnostics_report', { path, config });
+      }
+    } catch (e) {
+      // Errors are already logged by callEndpoint
+    }
+  };
+
+  const openSupportBundle = async () => {
+    try {
+      const { open } = await import('@tauri-apps/plugin-dialog');
+      const path = await open({
+        filters: [{ name: 'Support Bundle', extensions: ['zip'] }],
+        multiple: false
+      });
+      if (path) {
+        await callEndpoint('read_support_bundle', { path });
+      }
+    } catch (e) {
+      // Errors are already logged by callEndpoint
+    }
+  };
+
   const exportConfig = async () => {
     try {
       const { save } = await import('@tauri-apps/plugin-dialog');
@@ -510,24 +740,42 @@ function App() {
       });
 
       if (filePath) {
-        await callEndpoint('write_file_to_path', { path: filePath, content: JSON.stringify(config, null, 2) });
+        const content: string = await invoke('export_config_file', { config, metadata: null });
+        await callEndpoint('write_file_to_path', { path: filePath, content });
       }
     } catch (e) {
       addLog('SYSTEM', `Failed to export configuration.`, true);
     }
   };
 
+  const exportAuditLog = async () => {
+    try {
+      const content: string = await invoke('export_audit_log');
+      const { save } = await import('@tauri-apps/plugin-dialog');
+      const filePath = await save({
+        filters: [{ name: 'Audit Log', extensions: ['jsonl'] }],
+        defaultPath: `vpn_audit_${new Date().toISOString().split('T')[0]}.jsonl`
+      });
+      if (filePath) {
+        await callEndpoint('write_file_to_path', { path: filePath, content });
+      }
+    } catch (e) {
+      addLog('SYSTEM', String(e), true);
+    }
+  };
+
   const importConfig = (e: React.ChangeEvent<HTMLInputElement>) => {
     const file = e.target.files?.[0];
     if (!file) return;
     const reader = new FileReader();
-    reader.onload = (event) => {
+    reader.onload = async (event) => {
       try {
-        const json = JSON.parse(event.target?.result as string);
-        setConfig(prev => ({ ...prev, ...json }));
+        // The backend migrates older (bare VpnConfig) exports to the current schema.
+        const imported: VpnConfig = await invoke('import_config_file', { content: event.target?.result as string });
+        setConfig(prev => ({ ...prev, ...imported, ekuName: imported.ekuName ?? '', ekuOid: imported.ekuOid ?? '' }));
         window.alert("Configuration imported successfully!");
       } catch (err) {
-        window.alert("Failed to read configuration file. Invalid JSON format.");
+        window.alert(`Failed to read configuration file. ${err}`);
       }
     };
     reader.readAsText(file);
@@ -634,6 +882,9 @@ function App() {
             <button className="btn btn-outline" style={{ width: 'auto', padding: '0.2rem 0.4rem', fontSize: '0.75rem' }} onClick={exportConfig}>
               📤 {T.exportConfig}
             </button>
+            <button className="btn btn-outline" style={{ width: 'auto', padding: '0.2rem 0.4rem', fontSize: '0.75rem' }} onClick={exportAuditLog}>
+              📜 {T.exportAudit}
+            </button>
             <button className="btn btn-outline" style={{ width: 'auto', padding: '0.2rem 0.4rem', fontSize: '0.75rem' }} onClick={toggleLang}>
               {lang === 'de' ? '🇺🇸 EN' : '🇩🇪 DE'}
             </button>
@@ -681,6 +932,10 @@ function App() {
                       <label title={T.tt_ping}>{T.pingTargetLabel}</label>
                       <input type="text" placeholder="e.g. 192.168.1.10" value={config.internalPingTarget} onChange={e => handleChange('internalPingTarget', e.target.value)} title={T.tt_ping} />
                     </div>
+                    <div className="form-group">
+                      <label title={T.tt_mtuReference}>{T.mtuReferenceHostLabel}</label>
+                      <input type="text" placeholder="e.g. 1.1.1.1" value={config.mtuReferenceHost} onChange={e => handleChange('mtuReferenceHost', e.target.value)} title={T.tt_mtuReference} />
+                    </div>
                     <div className="form-group" style={{ margin: 0 }}>
                       <label>{T.dnsServersLabel}</label>
                       <input type="text" placeholder="e.g. 192.168.1.10, 192.168.1.11" value={config.dnsServers} onChange={e => handleChange('dnsServers', e.target.value)} />
@@ -717,10 +972,42 @@ function App() {
                           <input type="checkbox" id="disable_class" checked={config.disableClassBasedRoute} onChange={e => handleChange('disableClassBasedRoute', e.target.checked)} />
                           <label htmlFor="disable_class">{T.disableClass}</label>
                         </div>
+                        <div className="checkbox-group">
+                          <input type="checkbox" id="disable_ike_frag" checked={config.disableIkev2Fragmentation} onChange={e => handleChange('disableIkev2Fragmentation', e.target.checked)} />
+                          <label htmlFor="disable_ike_frag">{T.disableIkeFrag}</label>
+                        </div>
                         <div className="checkbox-group">
                           <input type="checkbox" id="disable_ui" checked={config.disableDisconnectButton} onChange={e => handleChange('disableDisconnectButton', e.target.checked)} />
                           <label htmlFor="disable_ui">{T.hideDisconnect}</label>
                         </div>
+                        <label title={T.tt_naming} style={{ fontSize: '0.7rem' }}>{T.namingLabel}</label>
+                        <input type="text" placeholder="%PREFIX% Device Tunnel" value={config.deviceTunnelName} onChange={e => handleChange('deviceTunnelName', e.target.value)} title={T.tt_naming} />
+                        <input type="text" placeholder="%PREFIX% User Tunnel" value={config.userTunnelName} onChange={e => handleChange('userTunnelName', e.target.value)} title={T.tt_naming} />
+                        <input type="text" placeholder="%PREFIX% User Tunnel All" value={config.userTunnelAllName} onChange={e => handleChange('userTunnelAllName', e.target.value)} title={T.tt_naming} />
+                        <input type="text" placeholder="\%PREFIX%\Start %DEVICE%" value={config.autoConnectTaskName} onChange={e => handleChange('autoConnectTaskName', e.target.value)} title={T.tt_naming} />
+                        {config.enableTaskSchedulerTrigger && (
+                          <>
+                            <label title={T.tt_auto_connect} style={{ fontSize: '0.7rem' }}>{T.autoConnectLabel}</label>
+                            {([['boot', T.trigBoot], ['networkChange', T.trigNetwork], ['logon', T.trigLogon], ['resume', T.trigResume]] as const).map(([trigger, label]) => (
+                              <div className="checkbox-group" key={trigger}>
+                                <input type="checkbox" id={`trig_${trigger}`} checked={config.autoConnectTriggers.includes(trigger)} onChange={e => handleChange('autoConnectTriggers', e.target.checked ? [...config.autoConnectTriggers, trigger] : config.autoConnectTriggers.filter(t => t !== trigger))} />
+                                <label htmlFor={`trig_${trigger}`}>{label}</label>
+                              </div>
+                            ))}
+                            <div style={{ display: 'flex', gap: '0.3rem' }}>
+                              <input type="number" min={0} value={config.autoConnectInitialDelaySecs} onChange={e => handleChange('autoConnectInitialDelaySecs', Number(e.target.value))} title={T.tt_auto_connect} />
+                              <input type="number" min={1} value={config.autoConnectMaxRetries} onChange={e => handleChange('autoConnectMaxRetries', Number(e.target.value))} title={T.tt_auto_connect} />
+                              <input type="number" min={1} value={config.autoConnectRetryIntervalSecs} onChange={e => handleChange('autoConnectRetryIntervalSecs', Number(e.target.value))} title={T.tt_auto_connect} />
+                            </div>
+                            <div className="checkbox-group">
+                              <input type="checkbox" id="backoff" checked={config.autoConnectExponentialBackoff} onChange={e => handleChange('autoConnectExponentialBackoff', e.target.checked)} />
+                              <label htmlFor="backoff">{T.backoffLabel}</label>
+                            </div>
+                            {config.autoConnectExponentialBackoff && (
+                              <input type="number" min={1} value={config.autoConnectMaxRetryIntervalSecs} onChange={e => handleChange('autoConnectMaxRetryIntervalSecs', Number(e.target.value))} title={T.tt_auto_connect} />
+                            )}
+                          </>
+                        )}
                       </div>
                     </div>
                   </div>
@@ -796,7 +1083,14 @@ function App() {
                     <div style={{ display: 'flex', gap: '0.3rem', marginBottom: '0.3rem' }}>
                       <button className="btn btn-outline" onClick={() => callEndpoint('connect_device_tunnel', { config })}>{T.up}</button>
                       <button className="btn btn-outline" onClick={() => callEndpoint('disconnect_device_tunnel', { config })}>{T.down}</button>
-                      <button className="btn btn-danger" style={{ padding: '0.3rem 0.6rem', color: '#ff7b72', borderColor: 'rgba(248, 81, 73, 0.4)' }} onClick={() => callEndpoint('remove_device_tunnel', { config })}>{T.remove}</button>
+                      <button className="btn btn-danger" style={{ padding: '0.3rem 0.6rem', color: '#ff7b72', borderColor: 'rgba(248, 81, 73, 0.4)' }} onClick={() => callEndpoint('remove_device_tunnel', { config, removeTask: config.enableTaskSchedulerTrigger })}>{T.remove}</button>
+                    </div>
+                    <div className="control-label">{T.taskCtrl}</div>
+                    <div style={{ display: 'flex', gap: '0.3rem', marginBottom: '0.3rem' }}>
+                      <button className="btn btn-outline" onClick={() => callEndpoint('get_auto_connect_task_status', { config })}>{T.taskStatus}</button>
+                      <button className="btn btn-outline" onClick={() => callEndpoint('set_auto_connect_task_enabled', { config, enabled: true })}>{T.taskEnable}</button>
+                      <button className="btn btn-outline" onClick={() => callEndpoint('set_auto_connect_task_enabled', { config, enabled: false })}>{T.taskDisable}</button>
+                      <button className="btn btn-danger" style={{ padding: '0.3rem 0.6rem', color: '#ff7b72', borderColor: 'rgba(248, 81, 73, 0.4)' }} onClick={() => callEndpoint('remove_auto_connect_task', { config })}>{T.remove}</button>
                     </div>
                   </div>
                   <div style={{ flex: 1 }}>
@@ -820,13 +1114,34 @@ function App() {
                         <button className="btn btn-outline" style={{ padding: '0.2rem', fontSize: '0.75rem', overflow: 'hidden', textOverflow: 'ellipsis' }} onClick={() => callEndpoint('check_routes')}>{T.verifyRoutes}</button>
                       </div>
                       <div style={{ display: 'grid', gridTemplateColumns: 'minmax(0,1fr) minmax(0,1fr)', gap: '0.4rem' }}>
-                        <button className="btn btn-outline" style={{ padding: '0.2rem', fontSize: '0.75rem', overflow: 'hidden', textOverflow: 'ellipsis' }} onClick={() => callEndpoint('check_dns', { server: config.vpnServerAddress })}>{T.checkDns}</button>
+                        <button className="btn btn-outline" style={{ padding: '0.2rem', fontSize: '0.75rem', overflow: 'hidden', textOverflow: 'ellipsis' }} onClick={() => callEndpoint('check_dns', { server: config.vpnServerAddress, config })}>{T.checkDns}</button>
                         <button className="btn btn-outline" style={{ padding: '0.2rem', fontSize: '0.75rem', overflow: 'hidden', textOverflow: 'ellipsis' }} onClick={() => callEndpoint('check_ports', { server: config.vpnServerAddress })}>{T.checkPorts}</button>
                       </div>
+                      <div style={{ display: 'grid', gridTemplateColumns: 'minmax(0,1fr) minmax(0,1fr)', gap: '0.4rem' }}>
+                        <button className="btn btn-outline" style={{ padding: '0.2rem', fontSize: '0.75rem', overflow: 'hidden', textOverflow: 'ellipsis' }} onClick={() => callEndpoint('probe_ike', { server: config.vpnServerAddress })}>{T.probeIke}</button>
+                        <button className="btn btn-outline" style={{ padding: '0.2rem', fontSize: '0.75rem', overflow: 'hidden', textOverflow: 'ellipsis' }} onClick={() => callEndpoint('probe_sstp', { config })}>{T.probeSstp}</button>
+                      </div>
+                      <button className="btn btn-outline" style={{ padding: '0.2rem', fontSize: '0.75rem', overflow: 'hidden', textOverflow: 'ellipsis' }} onClick={() => callEndpoint('diagnose_mtu', { config })}>{T.diagnoseMtu}</button>
+                      <div style={{ display: 'grid', gridTemplateColumns: 'repeat(3, minmax(0,1fr))', gap: '0.4rem' }}>
+                        <button className="btn btn-outline" style={{ padding: '0.2rem', fontSize: '0.75rem', overflow: 'hidden', textOverflow: 'ellipsis' }} onClick={() => callEndpoint('analyze_vpn_events', { hours: 24 })}>{T.analyzeEvents}</button>
+                        <button className="btn btn-outline" style={{ padding: '0.2rem', fontSize: '0.75rem', overflow: 'hidden', textOverflow: 'ellipsis' }} onClick={analyzeEventFile}>{T.analyzeEventFile}</button>
+                        <button className="btn btn-outline" style={{ padding: '0.2rem', fontSize: '0.75rem', overflow: 'hidden', textOverflow: 'ellipsis' }} onClick={explainErrorCode}>{T.explainCode}</button>
+                      </div>
+                      <div style={{ display: 'grid', gridTemplateColumns: 'repeat(3, minmax(0,1fr))', gap: '0.4rem' }}>
+                        <button className="btn btn-outline" style={{ padding: '0.2rem', fontSize: '0.75rem', overflow: 'hidden', textOverflow: 'ellipsis' }} onClick={generateReport}>{T.htmlReport}</button>
+                        <button className="btn btn-outline" style={{ padding: '0.2rem', fontSize: '0.75rem', overflow: 'hidden', textOverflow: 'ellipsis' }} onClick={createSupportBundle}>{T.supportBundle}</button>
+                        <button className="btn btn-outline" style={{ padding: '0.2rem', fontSize: '0.75rem', overflow: 'hidden', textOverflow: 'ellipsis' }} onClick={openSupportBundle}>{T.openBundle}</button>
+                      </div>
                       <div style={{ display: 'grid', gridTemplateColumns: 'minmax(0,1fr) minmax(0,1fr)', gap: '0.4rem' }}>
                         <button className="btn btn-outline" style={{ padding: '0.2rem', fontSize: '0.75rem', overflow: 'hidden', textOverflow: 'ellipsis' }} onClick={extractXmlAndSave}>{T.getXml}</button>
                         <button className="btn btn-danger" style={{ padding: '0.2rem', fontSize: '0.75rem', overflow: 'hidden', textOverflow: 'ellipsis' }} onClick={() => callEndpoint('restart_vpn_service')}>{T.restartService}</button>
                       </div>
+                      <div style={{ display: 'grid', gridTemplateColumns: 'minmax(0,1fr) minmax(0,1fr)', gap: '0.4rem' }}>
+                        <button className="btn btn-outline" style={{ padding: '0.2rem', fontSize: '0.75rem', overflow: 'hidden', textOverflow: 'ellipsis' }} onClick={() => callEndpoint('verify_tunnels', { config, testConnection: false })}>{T.verifyTunnels}</button>
+                        <button className="btn btn-outline" style={{ padding: '0.2rem', fontSize: '0.75rem', overflow: 'hidden', textOverflow: 'ellipsis' }} onClick={() => callEndpoint('verify_tunnels', { config, testConnection: true })}>{T.verifyTunnelsConnect}</button>
+                      </div>
+                      <button className="btn btn-outline" style={{ padding: '0.2rem', fontSize: '0.75rem' }} onClick={() => callEndpoint('check_profile_drift', { config })}>{T.checkDrift}</button>
+                      <button className="btn btn-outline" style={{ padding: '0.2rem', fontSize: '0.75rem' }} onClick={restoreBackup}>{T.restoreBackup}</button>
                     </div>
                   </div>
 
@@ -851,7 +1166,10 @@ function App() {
               <div className="card" style={{ flex: 1, display: 'flex', flexDirection: 'column', overflow: 'hidden', padding: 0 }}>
                 <div className="flex-between" style={{ padding: '0.6rem 0.6rem 0.2rem 0.6rem' }}>
                   <h2 style={{ margin: 0, fontSize: '0.85rem' }}>{T.terminal}</h2>
-                  <button className="btn btn-outline" style={{ width: 'auto', padding: '0.1rem 0.4rem', fontSize: '0.7rem' }} onClick={clearLogs}>{T.clear}</button>
+                  <div style={{ display: 'flex', gap: '0.3rem' }}>
+                    {activeJobs.length > 0 && <button className="btn btn-outline" style={{ width: 'auto', padding: '0.1rem 0.4rem', fontSize: '0.7rem', color: '#ff7b72' }} onClick={cancelJobs}>{T.cancelJob} ({activeJobs.length})</button>}
+                    <button className="btn btn-outline" style={{ width: 'auto', padding: '0.1rem 0.4rem', fontSize: '0.7rem' }} onClick={clearLogs}>{T.clear}</button>
+                  </div>
                 </div>
                 <div className="terminal" ref={terminalRef} style={{ flex: 1, height: '100%', minHeight: '320px', border: 'none', borderTop: '1px solid var(--card-border)', borderRadius: 0, padding: '0.6rem', background: '#090c10' }}>
                   {logs.length === 0 ? <div style={{ color: '#484f58' }}>{T.streamReady}</div> : null}
@@ -955,7 +1273,10 @@ function App() {
               <div className="card" style={{ flex: 1, display: 'flex', flexDirection: 'column', overflow: 'hidden', padding: 0 }}>
                 <div className="flex-between" style={{ padding: '0.6rem 0.6rem 0.2rem 0.6rem' }}>
                   <h2 style={{ margin: 0, fontSize: '0.85rem' }}>{T.terminal}</h2>
-                  <button className="btn btn-outline" style={{ width: 'auto', padding: '0.1rem 0.4rem', fontSize: '0.7rem' }} onClick={clearLogs}>{T.clear}</button>
+                  <div style={{ display: 'flex', gap: '0.3rem' }}>
+                    {activeJobs.length > 0 && <button className="btn btn-outline" style={{ width: 'auto', padding: '0.1rem 0.4rem', fontSize: '0.7rem', color: '#ff7b72' }} onClick={cancelJobs}>{T.cancelJob} ({activeJobs.length})</button>}
+                    <button className="btn btn-outline" style={{ width: 'auto', padding: '0.1rem 0.4rem', fontSize: '0.7rem' }} onClick={clearLogs}>{T.clear}</button>
+                  </div>
                 </div>
                 <div className="terminal" ref={terminalRef} style={{ flex: 1, border: 'none', borderTop: '1px solid var(--card-border)', borderRadius: 0, padding: '0.6rem', background: '#090c10' }}>
                   {logs.length === 0 ? <div style={{ color: '#484f58' }}>{T.streamReady}</div> : null}